@@ -0,0 +1,83 @@
+//! 指令分发的吞吐量测试，运行 `cargo bench --bench cpu`。
+//!
+//! 以 automation 模式反复执行 nestest，在它开始访问 APU 寄存器之前停下，统计每秒执行的指令数。
+//! 只计执行指令的时间，解析卡带、创建总线和复位都不计入。
+//!
+//! 除了 `CPU::run_for_cycles` 的实际吞吐量，还用同一个简化的取指循环比较两种查找方式：
+//! 以操作码为下标的指令表（现在的做法），和按操作码查 `HashMap`（改用指令表之前的做法），
+//! 两者之比就是指令表带来的提升。
+
+#![allow(clippy::needless_return)]
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nes_emulator::bus::Bus;
+use nes_emulator::cartridge::Cartridge;
+use nes_emulator::cpu::opcodes::Opcode;
+use nes_emulator::cpu::CPU;
+use nes_emulator::memory::Memory;
+
+/// nestest 在第 26520 个周期开始写 APU 寄存器
+const CYCLES_PER_RUN: u64 = 26_000;
+
+const MEASURE_TIME: Duration = Duration::from_secs(3);
+
+/// 反复从 nestest 的开头执行 `run`，返回每秒执行的指令数（百万条）
+fn measure<F>(bytes: &[u8], mut run: F) -> f64
+where
+  F: FnMut(&mut CPU<Bus>) -> u64,
+{
+  let mut instructions: u64 = 0;
+  let mut elapsed = Duration::ZERO;
+  while elapsed < MEASURE_TIME {
+    let mut cpu = CPU::new(Bus::new(Cartridge::new(bytes).unwrap()).unwrap());
+    cpu.reset();
+    cpu.registers.program_counter = 0xC000;
+
+    let start = Instant::now();
+    instructions += run(&mut cpu);
+    elapsed += start.elapsed();
+  }
+  return instructions as f64 / elapsed.as_secs_f64() / 1_000_000.0;
+}
+
+/// 只做取指、查找、执行和推进总线的循环，不处理中断和 DMA，返回执行的指令条数
+fn run_with_lookup<'a, L>(cpu: &mut CPU<Bus>, lookup: L) -> u64
+where
+  L: Fn(u8) -> &'a Opcode<Bus>,
+{
+  let end = cpu.cycles + CYCLES_PER_RUN;
+  let mut instructions = 0;
+  while cpu.cycles < end {
+    let code = cpu.bus.read(cpu.registers.program_counter);
+    cpu.registers.program_counter = cpu.registers.program_counter.wrapping_add(1);
+    let program_counter_state = cpu.registers.program_counter;
+
+    let opcode = lookup(code);
+    (opcode.handler)(cpu, &opcode.mode);
+    if program_counter_state == cpu.registers.program_counter {
+      cpu.registers.program_counter = cpu.registers.program_counter.wrapping_add((opcode.length - 1) as u16);
+    }
+    cpu.cycles += opcode.cycles as u64;
+    cpu.bus.tick(opcode.cycles as u64);
+    instructions += 1;
+  }
+  return instructions;
+}
+
+fn main() {
+  let bytes = std::fs::read("nestest.nes").expect("nestest.nes should be in the repository root");
+
+  let actual = measure(&bytes, |cpu| cpu.run_for_cycles(CYCLES_PER_RUN).instructions);
+  println!("nestest run_for_cycles: {:.2} M instructions/s", actual);
+
+  let table = CPU::<Bus>::OPCODES;
+  let map: HashMap<u8, &Opcode<Bus>> = table.iter().map(|opcode| (opcode.code, opcode)).collect();
+
+  let indexed = measure(&bytes, |cpu| run_with_lookup(cpu, |code| &table[code as usize]));
+  let hashed = measure(&bytes, |cpu| run_with_lookup(cpu, |code| map[&code]));
+  println!("table lookup:   {:.2} M instructions/s", indexed);
+  println!("HashMap lookup: {:.2} M instructions/s (baseline)", hashed);
+  println!("speedup: {:.2}x", indexed / hashed);
+}
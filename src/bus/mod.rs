@@ -1,28 +1,77 @@
 
 use crate::cartridge::Cartridge;
+use crate::cpu::interrupt::IrqSource;
+use crate::error::UnsupportedMapper;
+use crate::joypad::Joypad;
+use crate::mapper::{self, SharedMapper};
+use crate::memory::Memory;
+use crate::ppu::PPU;
 
 pub struct Bus {
   cpu_vram: [u8; 0x800],
-  cartridge: Cartridge,
+  mapper: SharedMapper,
+  pub ppu: PPU,
+
+  /// 1P 手柄，`$4016`
+  pub joypad1: Joypad,
+
+  /// 2P 手柄，`$4017`
+  pub joypad2: Joypad,
+
+  /// 写入了 `$4014`，等待 CPU 暂停
+  dma_pending: bool,
 }
 
 impl Bus {
 
-  pub fn new<'a>(cartridge: Cartridge) -> Self {
+  /// 按卡带的 mapper 编号创建 mapper，尚未实现的 mapper 返回错误
+  pub fn new(cartridge: Cartridge) -> Result<Self, UnsupportedMapper> {
+    return Ok(Bus::with_mapper(mapper::create_shared(cartridge)?));
+  }
+
+  pub fn with_mapper(mapper: SharedMapper) -> Self {
     return Bus {
       cpu_vram: [0; 0x800],
-      cartridge,
+      ppu: PPU::new(mapper.clone()),
+      mapper,
+      joypad1: Joypad::new(),
+      joypad2: Joypad::new(),
+      dma_pending: false,
     };
   }
 
-  pub fn read(&self, mut address: u16) -> u8 {
+  /// 卡带需要保存的数据，见 [`Mapper::save_data`](crate::mapper::Mapper::save_data)
+  pub fn save_data(&self) -> Option<Vec<u8>> {
+    return self.mapper.borrow().save_data().map(|data| data.to_vec());
+  }
+
+  /// 开机时载入存档文件
+  pub fn load_save_data(&mut self, data: &[u8]) {
+    self.mapper.borrow_mut().load_save_data(data);
+  }
+
+  /// OAMDMA `$4014`：把 CPU 的 `$XX00-$XXFF` 经由 OAMDATA 复制到 OAM，从当前的 OAMADDR 开始写入
+  fn oam_dma(&mut self, page: u8) {
+    let base = (page as u16) << 8;
+    for offset in 0..=0xFF {
+      let data = self.read(base | offset);
+      self.ppu.write_register(0x2004, data);
+    }
+    self.dma_pending = true;
+  }
+
+}
+
+impl Memory for Bus {
+
+  fn peek(&self, address: u16) -> u8 {
     return match address {
       // internal RAM
       0x0000..=0x1FFF => self.cpu_vram[(address & 0x7FF) as usize],
-      // NES PPU registers
-      0x2000..=0x3FFF => {
-        todo!("PPU memory not impl {:04X}", address);
-      }
+      // NES PPU registers，每 8 个字节镜像一次
+      0x2000..=0x3FFF => self.ppu.peek_register(address & 0x2007),
+      0x4016 => self.joypad1.peek(),
+      0x4017 => self.joypad2.peek(),
       // // NES APU and I/O registers
       // 0x4000..=0x4017 => {
 
@@ -31,51 +80,235 @@ impl Bus {
       // 0x4018..=0x401F => {
 
       // }
-      // // Cartridge space: PRG ROM, PRG RAM, and mapper registers
-      // 0x4020..=0xFFFF => {
-
-      // }
-      0x8000..=0xFFFF => {
-        // 16 * 1024 = 0x4000
-        if self.cartridge.prg_rom.len() == 0x4000 {
-          address = address & 0xBFFF;
-        }
-        return self.cartridge.prg_rom[(address & 0x7FFF) as usize];
-      },
-      _ => {
-        println!("Ignoring mem access at {:04X}", address);
-        return 0;
-      }
+      // 尚未实现的 APU 和 I/O 寄存器，与 nestest.log 一致按 $FF 处理
+      0x4000..=0x401F => 0xFF,
+      // Cartridge space: PRG ROM, PRG RAM, and mapper registers
+      0x4020..=0xFFFF => self.mapper.borrow().cpu_peek(address),
     };
   }
 
-  pub fn write(&mut self, address: u16, data: u8) {
+  fn read(&mut self, address: u16) -> u8 {
+    match address {
+      0x2000..=0x3FFF => return self.ppu.read_register(address & 0x2007),
+      0x4016 => return self.joypad1.read(),
+      0x4017 => return self.joypad2.read(),
+      // 尚未实现的 APU 寄存器，读取没有副作用
+      0x4000..=0x401F => {}
+      0x4020..=0xFFFF => return self.mapper.borrow_mut().cpu_read(address),
+      _ => {}
+    }
+    return self.peek(address);
+  }
+
+  fn write(&mut self, address: u16, data: u8) {
     match address {
       // internal RAM
       0x0000..=0x1FFF => self.cpu_vram[(address & 0x7FF) as usize] = data,
       0x2000..=0x3FFF => {
-        todo!("PPU memory not impl {:04X}", address);
-      }
-      0x8000..=0xFFFF => {
-        panic!("Attempt to write to Cartridge ROM space");
+        self.ppu.write_register(address & 0x2007, data);
+        self.mapper.borrow_mut().ppu_register_write(address & 0x2007, data);
       }
-      _ => {
-        println!("Ignoring mem write-access at {:04X}", address);
+      0x4014 => self.oam_dma(data),
+      // 两个手柄共用 $4016 的锁存信号，$4017 写入的是 APU 帧计数器
+      0x4016 => {
+        self.joypad1.write(data);
+        self.joypad2.write(data);
       }
+      0x4020..=0xFFFF => self.mapper.borrow_mut().cpu_write(address, data),
+      // 尚未实现的 APU 寄存器
+      _ => {}
     };
   }
 
-  pub fn read_u16(&self, address: u16) -> u16 {
-    let lo = self.read(address) as u16;
-    let hi = self.read(address + 1) as u16;
-    return (hi << 8) | lo;
+  fn tick(&mut self, cycles: u64) {
+    self.ppu.tick(cycles * 3);
+    self.mapper.borrow_mut().tick(cycles);
+  }
+
+  fn poll_nmi(&mut self) -> bool {
+    return self.ppu.poll_nmi();
+  }
+
+  fn poll_dma(&mut self) -> bool {
+    return std::mem::take(&mut self.dma_pending);
+  }
+
+  fn frame_count(&self) -> Option<u64> {
+    return Some(self.ppu.frame_count());
+  }
+
+  fn ppu_position(&self) -> Option<(u16, u16)> {
+    return Some((self.ppu.scanline, self.ppu.dot));
+  }
+
+  fn irq(&self) -> IrqSource {
+    let mut sources = IrqSource::empty();
+    sources.set(IrqSource::MAPPER, self.mapper.borrow().irq());
+    return sources;
   }
 
-  pub fn write_u16(&mut self, address: u16, data: u16) {
-    let lo = (data & 0x00FF) as u8;
-    let hi = (data >> 8) as u8;
-    self.write(address, lo);
-    self.write(address + 1, hi);
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::cartridge::test::test_rom;
+  use crate::cartridge::mirroring::Mirroring;
+  use crate::joypad::JoypadButton;
+  use crate::mapper::test::banked_cartridge;
+  use crate::mapper::Mapper;
+  use crate::ppu::registers::mask::MaskRegister;
+  use std::cell::{Cell, RefCell};
+  use std::rc::Rc;
+
+  /// 记录扫描线通知次数，计满 `irq_after` 条后拉低 IRQ
+  struct CountingMapper {
+    scanlines: Rc<Cell<u32>>,
+    irq_after: u32,
   }
 
+  impl Mapper for CountingMapper {
+    fn cpu_peek(&self, _address: u16) -> u8 {
+      return 0;
+    }
+
+    fn cpu_write(&mut self, _address: u16, _data: u8) {}
+
+    fn ppu_read(&mut self, _address: u16) -> u8 {
+      return 0;
+    }
+
+    fn ppu_write(&mut self, _address: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+      return Mirroring::Vertical;
+    }
+
+    fn irq(&self) -> bool {
+      return self.scanlines.get() >= self.irq_after;
+    }
+
+    fn scanline(&mut self) {
+      self.scanlines.set(self.scanlines.get() + 1);
+    }
+  }
+
+  #[test]
+  fn test_ppu_registers_are_mirrored_every_8_bytes() {
+    let mut bus = Bus::new(test_rom()).unwrap();
+    // PPUADDR 写在 $3FFE，PPUDATA 写在 $200F
+    bus.write(0x3FFE, 0x23);
+    bus.write(0x2006, 0x05);
+    bus.write(0x200F, 0x66);
+
+    assert_eq!(bus.ppu.read_vram(0x2305), 0x66);
+    assert_eq!(bus.ppu.vram_address(), 0x2306);
+  }
+
+  #[test]
+  fn test_mapper_receives_scanlines_and_drives_irq() {
+    let scanlines = Rc::new(Cell::new(0));
+    let mapper = CountingMapper { scanlines: scanlines.clone(), irq_after: 100 };
+    let mut bus = Bus::with_mapper(Rc::new(RefCell::new(Box::new(mapper))));
+
+    // 关闭渲染时没有扫描线通知
+    bus.tick(341 * 262 / 3);
+    assert_eq!(scanlines.get(), 0);
+
+    bus.ppu.mask = MaskRegister::SHOW_BACKGROUND;
+    bus.tick(341 * 50 / 3);
+    assert_eq!(scanlines.get(), 50);
+    assert!(bus.irq().is_empty());
+
+    bus.tick(341 * 262 / 3);
+    assert_eq!(scanlines.get(), 50 + 241);
+    assert_eq!(bus.irq(), IrqSource::MAPPER);
+  }
+
+  #[test]
+  fn test_mmc3_counts_a12_rising_edges_from_rendering() {
+    let mut bus = Bus::new(banked_cartridge(4, 0x2000, 4, 0x0400, 8)).unwrap();
+    // 背景用 $0000，精灵用 $1000，每条扫描线在取精灵时 A12 上升一次
+    bus.write(0x2000, 0x08);
+    bus.write(0x2001, 0x18);
+    bus.write(0xC000, 10);
+    bus.write(0xC001, 0);
+    bus.write(0xE001, 0);
+
+    // 第 0 条扫描线装载 10，第 1-9 条减到 1
+    bus.tick(341 * 10 / 3);
+    assert!(bus.irq().is_empty());
+    bus.tick(341 / 3 + 1);
+    assert_eq!(bus.irq(), IrqSource::MAPPER);
+  }
+
+  #[test]
+  fn test_mmc5_detects_scanlines_from_ppu_fetches() {
+    let mut bus = Bus::new(banked_cartridge(5, 0x2000, 4, 0x0400, 8)).unwrap();
+    bus.write(0x2001, 0x18);
+    bus.write(0x5203, 10);
+    bus.write(0x5204, 0x80);
+
+    // 第一帧从第 0 条扫描线中间开始，计数差一条，确认它的 IRQ 之后等到下一帧。
+    // MMC5 靠几个周期没有 PPU 读取判断 vblank，所以要像 CPU 一样逐个周期前进
+    for _ in 0..341 * 262 / 3 {
+      bus.tick(1);
+    }
+    bus.read(0x5204);
+    while bus.ppu.scanline != 10 {
+      assert!(bus.irq().is_empty());
+      bus.tick(1);
+    }
+    bus.tick(2);
+    assert_eq!(bus.irq(), IrqSource::MAPPER);
+    assert_eq!(bus.read(0x5204), 0xC0);
+  }
+
+  #[test]
+  fn test_battery_ram_save_data() {
+    let bus = Bus::new(banked_cartridge(1, 0x4000, 2, 0x1000, 2)).unwrap();
+    assert_eq!(bus.save_data(), None);
+
+    let mut cartridge = banked_cartridge(1, 0x4000, 2, 0x1000, 2);
+    cartridge.battery = true;
+    let mut bus = Bus::new(cartridge).unwrap();
+    bus.load_save_data(&[0x11, 0x22]);
+    assert_eq!(bus.read(0x6001), 0x22);
+    bus.write(0x6002, 0x33);
+    let save = bus.save_data().unwrap();
+    assert_eq!(save.len(), 0x2000);
+    assert_eq!(&save[..3], &[0x11, 0x22, 0x33]);
+  }
+
+  #[test]
+  fn test_joypad_ports() {
+    let mut bus = Bus::new(test_rom()).unwrap();
+    bus.joypad1.set_button(JoypadButton::B, true);
+    bus.joypad2.set_button(JoypadButton::A, true);
+    bus.write(0x4016, 1);
+    bus.write(0x4016, 0);
+
+    assert_eq!(bus.read(0x4016), 0x40);
+    assert_eq!(bus.peek(0x4016), 0x41);
+    assert_eq!(bus.read(0x4016), 0x41);
+    assert_eq!(bus.read(0x4017), 0x41);
+    assert_eq!(bus.read(0x4017), 0x40);
+  }
+
+  #[test]
+  fn test_oam_dma_copies_page_starting_at_oam_address() {
+    let mut bus = Bus::new(test_rom()).unwrap();
+    for i in 0..=0xFF {
+      bus.write(0x0200 + i, i as u8);
+    }
+    bus.write(0x2003, 0x10);
+    bus.write(0x4014, 0x02);
+
+    assert!(bus.poll_dma());
+    assert!(!bus.poll_dma());
+    assert_eq!(bus.ppu.oam_data[0x10], 0x00);
+    assert_eq!(bus.ppu.oam_data[0xFF], 0xEF);
+    assert_eq!(bus.ppu.oam_data[0x00], 0xF0);
+    assert_eq!(bus.ppu.oam_address, 0x10);
+  }
 }
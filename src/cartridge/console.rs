@@ -0,0 +1,25 @@
+/// 卡带针对的 CPU/PPU 时序，NES 2.0 头第 12 字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+  /// 北美、日本的 RP2C02
+  Ntsc,
+  /// 欧洲的 RP2C07
+  Pal,
+  /// 在两种主机上都能正确运行
+  MultiRegion,
+  /// 俄罗斯等地的兼容机 UA6538
+  Dendy,
+}
+
+/// 卡带运行的主机类型，NES 2.0 头第 7 字节的低 2 位和第 13 字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleType {
+  /// NES 或者 Famicom
+  Nes,
+  /// Vs. System 街机，`ppu` 是 PPU 的型号，`hardware` 是硬件类型（保护方式等）
+  VsSystem { ppu: u8, hardware: u8 },
+  /// PlayChoice-10 街机
+  Playchoice10,
+  /// 扩展的主机类型编号（3 及以上），如带 BCD 模式的兼容机、VT 系列芯片
+  Extended(u8),
+}
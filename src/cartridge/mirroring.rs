@@ -1,7 +1,27 @@
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mirroring {
   Horizontal,
   Vertical,
   FourScreen,
+
+  /// 4 个命名表都是第一块 1 KiB 命名表 RAM
+  SingleScreenLower,
+
+  /// 4 个命名表都是第二块 1 KiB 命名表 RAM
+  SingleScreenUpper,
+}
+
+impl Mirroring {
+  /// `$2000-$2FFF` 中第 `table`（0-3）个命名表使用的 1 KiB 命名表 RAM
+  pub fn nametable_bank(self, table: u16) -> usize {
+    return match (self, table) {
+      (Mirroring::Horizontal, 0 | 1) => 0,
+      (Mirroring::Horizontal, _) => 1,
+      (Mirroring::Vertical, 0 | 2) => 0,
+      (Mirroring::Vertical, _) => 1,
+      (Mirroring::FourScreen, table) => table as usize,
+      (Mirroring::SingleScreenLower, _) => 0,
+      (Mirroring::SingleScreenUpper, _) => 1,
+    };
+  }
 }
@@ -1,56 +1,228 @@
+pub mod console;
 pub mod mirroring;
 
+use bitflags::bitflags;
+
+use self::console::{ConsoleType, Timing};
 use self::mirroring::Mirroring;
+use crate::error::CartridgeError;
 
 const MAGIC_NUMBERS: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
 
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+
+bitflags! {
+  /// 文件头第 6 字节的低 4 位，高 4 位是 mapper 编号的低 4 位
+  struct Flags6: u8 {
+    /// 没有四屏时，0 是水平镜像，1 是垂直镜像
+    const VERTICAL_MIRRORING = 0b0000_0001;
+
+    /// 卡带上有电池供电的 PRG RAM 或者其他存储
+    const BATTERY = 0b0000_0010;
+
+    /// PRG ROM 之前有 512 字节的 trainer
+    const TRAINER = 0b0000_0100;
+
+    /// 卡带提供另外两块命名表 RAM，忽略镜像位
+    const FOUR_SCREEN = 0b0000_1000;
+  }
+}
+
+/// 卡带头的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  /// 早期的 iNES 头，第 7-15 字节是垃圾数据（例如 `DiskDude!`），只有第 4-6 字节可信
+  Archaic,
+  INes,
+  Nes2,
+}
+
+impl Format {
+  /// 按[判断方法](https://www.nesdev.org/wiki/INES#Variant_comparison)区分头的格式：
+  /// 第 7 字节的第 2-3 位为 `10` 是 NES 2.0；为 `00` 且第 12-15 字节全为 0 是 iNES；
+  /// 其余情形（包括 `DiskDude!` 这类工具写入的签名）按早期 iNES 头处理
+  fn detect(header: &[u8]) -> Format {
+    if header[7] & 0x0C == 0x08 {
+      return Format::Nes2;
+    }
+    if &header[7..16] == b"DiskDude!" {
+      return Format::Archaic;
+    }
+    if header[7] & 0x0C == 0x00 && header[12..16].iter().all(|&byte| byte == 0) {
+      return Format::INes;
+    }
+    return Format::Archaic;
+  }
+}
+
 /// ## [iNES format](https://www.nesdev.org/wiki/INES)
+///
+/// 同时支持 [NES 2.0](https://www.nesdev.org/wiki/NES_2.0)：第 7 字节的第 2-3 位为 `10` 时，
+/// 第 8-15 字节扩展了 mapper 编号、ROM 大小、各种 RAM 的大小和主机类型。
 pub struct Cartridge {
-  pub mapper: u8,
+  pub format: Format,
+
+  /// mapper 编号，NES 2.0 为 12 位
+  pub mapper: u16,
+
+  /// NES 2.0 头中的子 mapper 编号，区分同一个 mapper 编号下接线不同的板子。iNES 头没有这一项，为 0
+  pub submapper: u8,
+
   pub prg_rom: Vec<u8>,
   pub chr_rom: Vec<u8>,
   pub nametable_mirroring: Mirroring,
+
+  /// 不需要保存的 PRG RAM 的大小。iNES 头第 8 字节以 8 KiB 为单位，0 按 8 KiB 处理
+  pub prg_ram_size: usize,
+
+  /// 电池供电的 PRG RAM（NVRAM）的大小。iNES 头有电池时 PRG RAM 都算作 NVRAM
+  pub prg_nvram_size: usize,
+
+  /// CHR RAM 的大小。iNES 头没有 CHR ROM 时为 8 KiB
+  pub chr_ram_size: usize,
+
+  /// 电池供电的 CHR RAM 的大小，只有 NES 2.0 头会写
+  pub chr_nvram_size: usize,
+
+  /// 卡带上有电池，PRG RAM 或者其他存储的内容需要在关机后保留
+  pub battery: bool,
+
+  /// CPU/PPU 时序。iNES 头第 9 字节的电视制式很少有 ROM 正确填写，按 NTSC 处理
+  pub timing: Timing,
+
+  pub console_type: ConsoleType,
+
+  /// NES 2.0 头第 15 字节，默认接上的扩展设备编号，0 表示未指定，1 是标准手柄。
+  /// 编号见 [Default Expansion Device](https://www.nesdev.org/wiki/NES_2.0#Default_Expansion_Device)
+  pub expansion_device: u8,
+}
+
+/// NES 2.0 的 ROM 大小：高 4 位为 $F 时低字节是 `EEEEEEMM`，大小为 2^E × (MM × 2 + 1) 字节，
+/// 否则高 4 位和低字节组成以 `unit` 为单位的 12 位个数
+fn nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+  if msb == 0x0F {
+    let exponent = (lsb >> 2) as u32;
+    let multiplier = (lsb & 0x03) as usize * 2 + 1;
+    return 1usize.checked_shl(exponent).unwrap_or(0).saturating_mul(multiplier);
+  }
+  return (((msb as usize) << 8) | lsb as usize) * unit;
+}
+
+/// NES 2.0 的 RAM 大小：移位数为 0 表示没有，否则是 64 << 移位数 字节
+fn nes2_ram_size(shift: u8) -> usize {
+  return if shift == 0 { 0 } else { 64 << shift };
 }
 
 impl Cartridge {
-  pub fn new(raw: &Vec<u8>) -> Result<Cartridge, String> {
-    if &raw[0..4] != MAGIC_NUMBERS {
-      return Err("File is not in iNES file format".to_string());
+  pub fn new(raw: &[u8]) -> Result<Cartridge, CartridgeError> {
+    if raw.len() < HEADER_SIZE {
+      return Err(CartridgeError::TooShort { length: raw.len() });
     }
-    if (raw[7] >> 2 & 0x03) != 0 {
-      return Err("NES 2.0 format is not supported".to_string());
+    if raw[0..4] != MAGIC_NUMBERS {
+      return Err(CartridgeError::BadMagic);
     }
+    let format = Format::detect(&raw[..HEADER_SIZE]);
+    let flags = Flags6::from_bits_truncate(raw[6]);
 
-    let mapper = (raw[7] & 0xF0) | (raw[6] >> 4);
-
-    let mirroring = match (raw[6] & 0x08 == 0x80, raw[6] & 0x01 == 0x01) {
-      (false, false) => Mirroring::Horizontal,
-      (false, true) => Mirroring::Vertical,
-      (true, _) => Mirroring::FourScreen,
+    let mirroring = if flags.contains(Flags6::FOUR_SCREEN) {
+      Mirroring::FourScreen
+    } else if flags.contains(Flags6::VERTICAL_MIRRORING) {
+      Mirroring::Vertical
+    } else {
+      Mirroring::Horizontal
     };
 
-    let has_trainer = raw[6] & 0x04 == 0x04;
+    let battery = flags.contains(Flags6::BATTERY);
+
+    let mut mapper = (raw[6] >> 4) as u16;
+    let mut submapper = 0;
+    // Size of PRG ROM in 16 KB units, CHR ROM in 8 KB units
+    let mut prg_rom_size = (raw[4] as usize) * 16384;
+    let mut chr_rom_size = (raw[5] as usize) * 8192;
+    let mut prg_ram_size = 8192;
+    let mut prg_nvram_size = 0;
+    let mut chr_ram_size = if chr_rom_size == 0 { 8192 } else { 0 };
+    let mut chr_nvram_size = 0;
+    let mut timing = Timing::Ntsc;
+    let mut console_type = ConsoleType::Nes;
+    let mut expansion_device = 0;
+
+    match format {
+      Format::Archaic => {}
+      Format::INes => {
+        mapper |= (raw[7] & 0xF0) as u16;
+        prg_ram_size = (raw[8].max(1) as usize) * 8192;
+        console_type = match raw[7] & 0x03 {
+          0 => ConsoleType::Nes,
+          1 => ConsoleType::VsSystem { ppu: 0, hardware: 0 },
+          _ => ConsoleType::Playchoice10,
+        };
+      }
+      Format::Nes2 => {
+        mapper |= (raw[7] & 0xF0) as u16 | ((raw[8] & 0x0F) as u16) << 8;
+        submapper = raw[8] >> 4;
+        prg_rom_size = nes2_rom_size(raw[4], raw[9] & 0x0F, 16384);
+        chr_rom_size = nes2_rom_size(raw[5], raw[9] >> 4, 8192);
+        prg_ram_size = nes2_ram_size(raw[10] & 0x0F);
+        prg_nvram_size = nes2_ram_size(raw[10] >> 4);
+        chr_ram_size = nes2_ram_size(raw[11] & 0x0F);
+        chr_nvram_size = nes2_ram_size(raw[11] >> 4);
+        timing = match raw[12] & 0x03 {
+          0 => Timing::Ntsc,
+          1 => Timing::Pal,
+          2 => Timing::MultiRegion,
+          _ => Timing::Dendy,
+        };
+        console_type = match raw[7] & 0x03 {
+          0 => ConsoleType::Nes,
+          1 => ConsoleType::VsSystem { ppu: raw[13] & 0x0F, hardware: raw[13] >> 4 },
+          2 => ConsoleType::Playchoice10,
+          _ => ConsoleType::Extended(raw[13] & 0x0F),
+        };
+        expansion_device = raw[15] & 0x3F;
+      }
+    }
+    // iNES 头无法区分两种 PRG RAM，有电池时都算作 NVRAM
+    if format != Format::Nes2 && battery {
+      prg_nvram_size = prg_ram_size;
+      prg_ram_size = 0;
+    }
 
-    // Size of PRG ROM in 16 KB units
-    let prg_rom_start = 16 + if has_trainer { 512 } else { 0 };
-    let prg_rom_size = (raw[4] as usize) * 16384;
-    let prg_rom = raw[prg_rom_start..(prg_rom_start + prg_rom_size)].to_vec();
+    if prg_rom_size == 0 {
+      return Err(CartridgeError::MissingPrgRom);
+    }
 
-    // Size of CHR ROM in 8 KB units
-    let chr_rom_start = prg_rom_start + prg_rom_size;
-    let chr_rom_size = (raw[5] as usize) * 8192;
-    let chr_rom = raw[chr_rom_start..(chr_rom_start + chr_rom_size)].to_vec();
+    let prg_rom_start = HEADER_SIZE + if flags.contains(Flags6::TRAINER) { TRAINER_SIZE } else { 0 };
+    let chr_rom_start = prg_rom_start.saturating_add(prg_rom_size);
+    let expected = chr_rom_start.saturating_add(chr_rom_size);
+    if raw.len() < expected {
+      return Err(CartridgeError::Truncated { expected, actual: raw.len() });
+    }
+    let prg_rom = raw[prg_rom_start..chr_rom_start].to_vec();
+    let chr_rom = raw[chr_rom_start..expected].to_vec();
 
     return Ok(Cartridge {
+      format,
       mapper,
+      submapper,
       prg_rom,
       chr_rom,
       nametable_mirroring: mirroring,
+      prg_ram_size,
+      prg_nvram_size,
+      chr_ram_size,
+      chr_nvram_size,
+      battery,
+      timing,
+      console_type,
+      expansion_device,
     });
   }
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 pub mod test {
   use super::*;
 
@@ -79,10 +251,11 @@ pub mod test {
     result
   }
 
+  /// 32 KiB PRG ROM、8 KiB CHR ROM 的 NROM 卡带
   pub fn test_rom() -> Cartridge {
     let test_rom = create_rom(TestRom {
       header: vec![
-        0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x31, 00, 00, 00, 00, 00, 00, 00, 00, 00,
+        0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x01, 00, 00, 00, 00, 00, 00, 00, 00, 00,
       ],
       trainer: None,
       pgp_rom: vec![1; 2 * 16384],
@@ -109,6 +282,7 @@ pub mod test {
     assert_eq!(rom.prg_rom, vec!(1; 2 * 16384));
     assert_eq!(rom.mapper, 3);
     assert_eq!(rom.nametable_mirroring, Mirroring::Vertical);
+    assert!(!rom.battery);
   }
 
   #[test]
@@ -146,19 +320,141 @@ pub mod test {
   }
 
   #[test]
-  fn test_nes2_is_not_supported() {
+  fn test_ines_battery_ram_is_nvram() {
     let test_rom = create_rom(TestRom {
       header: vec![
-        0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x31, 0x8, 00, 00, 00, 00, 00, 00, 00, 00,
+        0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x12, 00, 0x02, 00, 00, 00, 00, 00, 00, 00,
       ],
       trainer: None,
       pgp_rom: vec![1; 1 * 16384],
+      chr_rom: vec![],
+    });
+    let rom = Cartridge::new(&test_rom).unwrap();
+    assert_eq!(rom.format, Format::INes);
+    assert_eq!(rom.mapper, 1);
+    assert!(rom.battery);
+    assert_eq!(rom.prg_ram_size, 0);
+    assert_eq!(rom.prg_nvram_size, 2 * 8192);
+    assert_eq!(rom.chr_ram_size, 8192);
+  }
+
+  #[test]
+  fn test_nes2() {
+    let test_rom = create_rom(TestRom {
+      header: vec![
+        0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x51, 0x49, 0x30, 00, 0x70, 0x07, 0x01, 0x21, 00, 0x08,
+      ],
+      trainer: None,
+      pgp_rom: vec![1; 2 * 16384],
       chr_rom: vec![2; 1 * 8192],
     });
-    let rom = Cartridge::new(&test_rom);
-    match rom {
-      Result::Ok(_) => assert!(false, "should not load rom"),
-      Result::Err(str) => assert_eq!(str, "NES 2.0 format is not supported"),
-    }
+    let rom = Cartridge::new(&test_rom).unwrap();
+    assert_eq!(rom.format, Format::Nes2);
+    assert_eq!(rom.mapper, 69);
+    assert_eq!(rom.submapper, 3);
+    assert_eq!(rom.prg_rom.len(), 2 * 16384);
+    assert_eq!(rom.chr_rom.len(), 8192);
+    assert_eq!(rom.prg_ram_size, 0);
+    assert_eq!(rom.prg_nvram_size, 8192);
+    assert_eq!(rom.chr_ram_size, 8192);
+    assert_eq!(rom.chr_nvram_size, 0);
+    assert_eq!(rom.timing, Timing::Pal);
+    assert_eq!(rom.console_type, ConsoleType::VsSystem { ppu: 1, hardware: 2 });
+    assert_eq!(rom.expansion_device, 8);
+  }
+
+  #[test]
+  fn test_nes2_exponent_rom_size() {
+    // 高 4 位为 $F：$0A 是 2^2 × 5 = 20 字节 PRG ROM；CHR 的 12 位个数为 $100
+    let test_rom = create_rom(TestRom {
+      header: vec![
+        0x4E, 0x45, 0x53, 0x1A, 0x0A, 0x00, 0x00, 0x0B, 00, 0x1F, 00, 00, 0x03, 0x04, 00, 00,
+      ],
+      trainer: None,
+      pgp_rom: vec![1; 20],
+      chr_rom: vec![2; 0x100 * 8192],
+    });
+    let rom = Cartridge::new(&test_rom).unwrap();
+    assert_eq!(rom.prg_rom.len(), 20);
+    assert_eq!(rom.chr_rom.len(), 0x100 * 8192);
+    assert_eq!(rom.prg_ram_size, 0);
+    assert_eq!(rom.timing, Timing::Dendy);
+    assert_eq!(rom.console_type, ConsoleType::Extended(4));
+  }
+
+  #[test]
+  fn test_flags() {
+    let header = |flags: u8| vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, flags, 00, 00, 00, 00, 00, 00, 00, 00, 00];
+    let rom = |flags: u8, trainer: Option<Vec<u8>>| {
+      let raw = create_rom(TestRom { header: header(flags), trainer, pgp_rom: vec![1; 16384], chr_rom: vec![2; 8192] });
+      return Cartridge::new(&raw).unwrap();
+    };
+
+    let four_screen = rom(0x08 | 0x01, None);
+    assert_eq!(four_screen.nametable_mirroring, Mirroring::FourScreen);
+    assert!(!four_screen.battery);
+
+    let battery = rom(0x02, None);
+    assert_eq!(battery.nametable_mirroring, Mirroring::Horizontal);
+    assert!(battery.battery);
+
+    // trainer 不属于 PRG ROM
+    let trainer = rom(0x04, Some(vec![0xFF; 512]));
+    assert_eq!(trainer.prg_rom, vec![1; 16384]);
+    assert_eq!(trainer.chr_rom, vec![2; 8192]);
+  }
+
+  #[test]
+  fn test_archaic_header() {
+    // 第 7-15 字节被工具写成了 "DiskDude!"，只取 mapper 编号的低 4 位
+    let mut header = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x41];
+    header.extend(b"DiskDude!");
+    let raw = create_rom(TestRom { header, trainer: None, pgp_rom: vec![1; 16384], chr_rom: vec![2; 8192] });
+    let rom = Cartridge::new(&raw).unwrap();
+    assert_eq!(rom.format, Format::Archaic);
+    assert_eq!(rom.mapper, 4);
+    assert_eq!(rom.console_type, ConsoleType::Nes);
+    assert_eq!(rom.prg_ram_size, 8192);
+
+    // 其他第 12-15 字节不为 0 的 iNES 头也一样
+    let header = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x11, 0x40, 00, 00, 00, 00, 0x20, 0x20, 0x20, 0x20];
+    let raw = create_rom(TestRom { header, trainer: None, pgp_rom: vec![1; 16384], chr_rom: vec![2; 8192] });
+    let rom = Cartridge::new(&raw).unwrap();
+    assert_eq!(rom.format, Format::Archaic);
+    assert_eq!(rom.mapper, 1);
+  }
+
+  #[test]
+  fn test_errors() {
+    let header = |byte6: u8, byte7: u8, byte8: u8| {
+      vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, byte6, byte7, byte8, 00, 00, 00, 00, 00, 00, 00]
+    };
+
+    assert_eq!(Cartridge::new(&[0x4E, 0x45, 0x53]).err(), Some(CartridgeError::TooShort { length: 3 }));
+    assert_eq!(Cartridge::new(&[0; 16]).err(), Some(CartridgeError::BadMagic));
+
+    let mut raw = header(0x00, 0x00, 0x00);
+    raw.extend(vec![0; 0x8000]);
+    let error = Cartridge::new(&raw).err().unwrap();
+    assert_eq!(error, CartridgeError::Truncated { expected: 16 + 0x8000 + 0x2000, actual: 16 + 0x8000 });
+    assert_eq!(error.to_string(), "file is truncated: header declares 40976 bytes, found 32784");
+
+    // 声明了 trainer 时 trainer 也要算进去
+    raw.extend(vec![0; 0x2000]);
+    assert!(Cartridge::new(&raw).is_ok());
+    raw[6] = 0x04;
+    assert_eq!(
+      Cartridge::new(&raw).err(),
+      Some(CartridgeError::Truncated { expected: 16 + 512 + 0xA000, actual: 16 + 0xA000 })
+    );
+
+    // NES 2.0 的 12 位 mapper 编号，尚未实现的 mapper 留到创建总线时报错
+    let mut raw = header(0x50, 0x48, 0x01);
+    raw.extend(vec![0; 0xA000]);
+    assert_eq!(Cartridge::new(&raw).unwrap().mapper, 0x145);
+
+    let mut raw = header(0x00, 0x00, 0x00);
+    raw[4] = 0;
+    assert_eq!(Cartridge::new(&raw).err(), Some(CartridgeError::MissingPrgRom));
   }
 }
@@ -1,10 +1,10 @@
 
 /// CPU 寻址模式
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressingMode {
   /// 绝对寻址，完整的内存位置用作指令的参数。
   ///
-  /// ```
+  /// ```text
   /// STA $C000 ;store the value in the accumulator at memory location $c000
   /// ```
   Absolute,
@@ -16,7 +16,7 @@ pub enum AddressingMode {
 
   /// 在这种模式下，给出一个零页地址，然后将 X 寄存器的值相加。
   ///
-  /// ```
+  /// ```text
   /// LDX #$01   ;X is $01
   /// LDA #$aa   ;A is $aa
   /// STA $a0,X  ;Store the value of A at memory location $a1
@@ -26,7 +26,7 @@ pub enum AddressingMode {
   ///
   /// 如果加法的结果大于单个字节，则地址回绕。例如：
   ///
-  /// ```
+  /// ```text
   /// LDX #$05
   /// STA $FF,X  ;Store the value of A at memory location $04
   /// ```
@@ -37,7 +37,7 @@ pub enum AddressingMode {
 
   /// 类似于 ZeroPageX 或者 ZeroPageY 的绝对寻址版本
   ///
-  /// ```
+  /// ```text
   /// LDX #$01
   /// STA $0200,X ;store the value of A at memory location $0201
   /// ```
@@ -52,7 +52,7 @@ pub enum AddressingMode {
   /// 相对寻址用于分支指令。
   /// 这些指令采用单个字节，用作与下一条指令的偏移量。
   ///
-  /// ```
+  /// ```text
   ///   LDA #$01
   ///   CMP #$02
   ///   BNE not_equal
@@ -74,7 +74,7 @@ pub enum AddressingMode {
   /// 间接寻址使用绝对地址查找其他地址。
   /// 第一个地址给出地址的最低有效字节，下一个字节给出最高有效字节。
   ///
-  /// ```
+  /// ```text
   /// LDA #$01
   /// STA $f0     ;$f0 is $01
   /// LDA #$cc
@@ -85,7 +85,7 @@ pub enum AddressingMode {
 
   /// 这个有点奇怪。这就像零页、X和间接页之间的交叉。基本上，取零页地址，将X寄存器的值添加到其中，然后使用该值查找两字节地址。例如：
   ///
-  /// ```
+  /// ```text
   /// LDX #$01     ;X is $01
   /// LDA #$05
   /// STA $01      ;$01 is $05
@@ -103,7 +103,7 @@ pub enum AddressingMode {
 
   /// 与 IndexedIndirect 相似，不过不是将寄存器添加到地址，而是将零页地址解引用，并将寄存器添加到结果地址。
   ///
-  /// ```
+  /// ```text
   /// LDY #$01
   /// LDA #$03
   /// STA $01
@@ -0,0 +1,44 @@
+/// NTSC 下每帧的 PPU 时钟数（341 点 × 262 条扫描线），CPU 时钟为 PPU 的 1/3
+pub const PPU_DOTS_PER_FRAME: u64 = 341 * 262;
+
+/// 一条已经执行的指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+  /// 指令所在的地址
+  pub address: u16,
+
+  pub opcode: u8,
+
+  pub mnemonic: &'static str,
+}
+
+/// 执行停止的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+  /// 单步执行完成了一条指令
+  Stepped,
+
+  /// 周期预算已经用完
+  BudgetExhausted,
+
+  /// PPU 完成了一帧，进入了 vblank
+  FrameComplete,
+
+  /// CPU 执行了 JAM 指令而停机
+  Jammed,
+}
+
+/// `step` 以及各种 `run_*` 方法的执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunResult {
+  /// 本次调用消耗的周期数，包括响应中断的周期
+  pub cycles: u64,
+
+  /// 本次调用执行的指令条数
+  pub instructions: u64,
+
+  /// 最后执行的一条指令
+  pub last_instruction: Option<Instruction>,
+
+  pub stop_reason: StopReason,
+}
@@ -0,0 +1,52 @@
+use bitflags::bitflags;
+
+/// [CPU interrupts](https://www.nesdev.org/wiki/CPU_interrupts)
+///
+/// | 中断 | 向量地址 | 触发方式 |
+/// |------|----------|----------|
+/// | NMI  | `$FFFA`  | 边沿触发，不受 `I` 标志影响 |
+/// | IRQ  | `$FFFE`  | 电平触发，`I` 标志置位时被屏蔽 |
+/// | BRK  | `$FFFE`  | 软件中断，压栈的状态带有 `B` 标志 |
+pub struct Interrupt {
+  /// 中断向量所在的地址
+  pub vector_address: u16,
+
+  /// 压栈时额外置位的状态位，只有 BRK 会带上 `B`
+  pub b_flag_mask: u8,
+}
+
+pub const NMI: Interrupt = Interrupt {
+  vector_address: 0xFFFA,
+  b_flag_mask: 0b0010_0000,
+};
+
+pub const IRQ: Interrupt = Interrupt {
+  vector_address: 0xFFFE,
+  b_flag_mask: 0b0010_0000,
+};
+
+pub const BRK: Interrupt = Interrupt {
+  vector_address: 0xFFFE,
+  b_flag_mask: 0b0011_0000,
+};
+
+/// 响应 NMI 或 IRQ 需要的周期数
+pub const INTERRUPT_CYCLES: u64 = 7;
+
+bitflags! {
+  /// IRQ 线是“线与”的，任意一个外设拉低都会使 IRQ 有效。
+  /// 每个外设单独记录自己的状态，全部释放后 IRQ 才会失效。
+  pub struct IrqSource: u8 {
+    /// APU 帧计数器
+    const APU_FRAME_COUNTER = 0b0000_0001;
+
+    /// APU DMC 通道
+    const APU_DMC = 0b0000_0010;
+
+    /// 卡带上的 Mapper
+    const MAPPER = 0b0000_0100;
+
+    /// 其他外部设备
+    const EXTERNAL = 0b0000_1000;
+  }
+}
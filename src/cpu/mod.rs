@@ -1,87 +1,253 @@
 pub mod addressing_mode;
+pub mod execution;
+pub mod interrupt;
 pub mod opcodes;
 pub mod register;
 pub mod status_flags;
+pub mod variant;
 
 use self::addressing_mode::AddressingMode;
-use self::opcodes::{Opcode, OPCODES_MAP};
+use self::execution::{Instruction, RunResult, StopReason, PPU_DOTS_PER_FRAME};
+use self::interrupt::{Interrupt, IrqSource, INTERRUPT_CYCLES};
+use self::opcodes::Opcode;
 use self::register::Registers;
 use self::status_flags::Flags;
-use crate::bus::Bus;
-use std::collections::HashMap;
+use self::variant::Variant;
+use crate::memory::Memory;
+use crate::error::EmuError;
 
-pub struct CPU {
-  pub bus: Bus,
+/// ANE/LXA 魔数的默认值，参见 `CPU::magic_constant`
+pub const DEFAULT_MAGIC_CONSTANT: u8 = 0xEE;
+
+/// [OAM DMA](https://www.nesdev.org/wiki/PPU_registers#OAMDMA) 暂停 CPU 的周期数：
+/// 等待写入完成的 1 个周期加上 256 次读写，在奇数周期开始时还要多等 1 个周期对齐
+pub const OAM_DMA_CYCLES: u64 = 513;
+
+/// 判断两个地址是否位于不同的页（高字节不同）
+fn page_crossed(a: u16, b: u16) -> bool {
+  return a & 0xFF00 != b & 0xFF00;
+}
+
+/// 按寻址模式计算操作数的实际地址，`read` 决定以何种方式访问内存。
+///
+/// 返回值的第二项表示索引寻址时是否跨越了页边界（高字节发生变化），
+/// 读类指令在跨页时需要额外消耗 1 个周期。不访问内存的寻址模式返回 `None`。
+fn resolve_address<F>(mode: &AddressingMode, address: u16, x: u8, y: u8, mut read: F) -> Option<(u16, bool)>
+where
+  F: FnMut(u16) -> u8,
+{
+  use AddressingMode::*;
+  let result = match mode {
+    Absolute => (read_u16(&mut read, address), false),
+    AbsoluteX => {
+      let base = read_u16(&mut read, address);
+      let address = base.wrapping_add(x as u16);
+      (address, page_crossed(base, address))
+    }
+    AbsoluteY => {
+      let base = read_u16(&mut read, address);
+      let address = base.wrapping_add(y as u16);
+      (address, page_crossed(base, address))
+    }
+    ZeroPage => (read(address) as u16, false),
+    ZeroPageX => (read(address).wrapping_add(x) as u16, false),
+    ZeroPageY => (read(address).wrapping_add(y) as u16, false),
+    Indirect => {
+      // http://www.6502.org/tutorials/6502opcodes.html#JMP
+      // Indirect 仅适用于 JMP 指令
+      // **AN INDIRECT JUMP MUST NEVER USE A VECTOR BEGINNING ON THE LAST BYTE OF A PAGE**
+      // For example if address $3000 contains $40, $30FF contains $80, and $3100 contains $50,
+      // the result of JMP ($30FF) will be a transfer of control to $4080 rather than $5080 as you intended
+      // i.e. the 6502 took the low byte of the address from $30FF and the high byte from $3000.
+      let indirect_address = read_u16(&mut read, address);
+      if indirect_address & 0x00FF == 0x00FF {
+        let lo = read(indirect_address);
+        let hi = read(indirect_address & 0xFF00);
+        ((hi as u16) << 8 | (lo as u16), false)
+      } else {
+        (read_u16(&mut read, indirect_address), false)
+      }
+    }
+    // !!地址处理和read_u16不同。
+    IndexedIndirect => {
+      let pointer = read(address).wrapping_add(x);
+      let lo = read(pointer as u16);
+      let hi = read(pointer.wrapping_add(1) as u16);
+      (((hi as u16) << 8) | (lo as u16), false)
+    }
+    IndirectIndexed => {
+      let param = read(address);
+      let lo = read(param as u16);
+      let hi = read(param.wrapping_add(1) as u16);
+      let indirect_address = ((hi as u16) << 8) | (lo as u16);
+      let address = indirect_address.wrapping_add(y as u16);
+      (address, page_crossed(indirect_address, address))
+    }
+    _ => return None,
+  };
+  return Some(result);
+}
+
+fn read_u16<F>(read: &mut F, address: u16) -> u16
+where
+  F: FnMut(u16) -> u8,
+{
+  let lo = read(address) as u16;
+  let hi = read(address.wrapping_add(1)) as u16;
+  return (hi << 8) | lo;
+}
+
+pub struct CPU<M: Memory> {
+  pub bus: M,
   pub registers: Registers,
+
+  /// 自上电以来累计执行的 CPU 周期数
+  pub cycles: u64,
+
+  /// NMI 是边沿触发的，触发后锁存到下一条指令开始前处理
+  nmi_pending: bool,
+
+  /// 当前拉低 IRQ 线的外设
+  irq_sources: IrqSource,
+
+  /// 正在执行的指令的地址和操作码，用于给寻址错误补充上下文和 JAM 停在原地
+  current_instruction: (u16, u8),
+
+  /// CPU 型号，默认是 NES 的 2A03，改为 `Variant::Nmos6502` 可启用十进制模式
+  pub variant: Variant,
+
+  /// ANE 和 LXA 使用的“魔数”，真实芯片上取决于批次和温度，默认使用常见的 `$EE`
+  pub magic_constant: u8,
+
+  /// 执行了 JAM 指令，CPU 停止工作直到复位
+  jammed: bool,
 }
 
-impl CPU {
-  pub fn new(bus: Bus) -> Self {
+impl<M: Memory> CPU<M> {
+  /// 以操作码为下标的指令表
+  pub const OPCODES: [Opcode<M>; 256] = opcodes::build_opcode_table();
+
+  pub fn new(bus: M) -> Self {
     return CPU {
       bus,
       registers: Registers::new(),
+      cycles: 0,
+      nmi_pending: false,
+      irq_sources: IrqSource::empty(),
+      current_instruction: (0, 0),
+      variant: Variant::default(),
+      magic_constant: DEFAULT_MAGIC_CONSTANT,
+      jammed: false,
     };
   }
 
-  pub fn get_absolute_address(&self, mode: &AddressingMode, address: u16) -> u16 {
-    use AddressingMode::*;
-    match mode {
-      Absolute => self.bus.read_u16(address),
-      AbsoluteX => self.bus.read_u16(address).wrapping_add(self.registers.x as u16),
-      AbsoluteY => self.bus.read_u16(address).wrapping_add(self.registers.y as u16),
-      ZeroPage => self.bus.read(address) as u16,
-      ZeroPageX => self.bus.read(address).wrapping_add(self.registers.x) as u16,
-      ZeroPageY => self.bus.read(address).wrapping_add(self.registers.y) as u16,
-      Indirect => {
-        // http://www.6502.org/tutorials/6502opcodes.html#JMP
-        // Indirect 仅适用于 JMP 指令
-        // **AN INDIRECT JUMP MUST NEVER USE A VECTOR BEGINNING ON THE LAST BYTE OF A PAGE**
-        // For example if address $3000 contains $40, $30FF contains $80, and $3100 contains $50,
-        // the result of JMP ($30FF) will be a transfer of control to $4080 rather than $5080 as you intended
-        // i.e. the 6502 took the low byte of the address from $30FF and the high byte from $3000.
-        let indirect_address = self.bus.read_u16(address);
-        if indirect_address & 0x00FF == 0x00FF {
-          let lo = self.bus.read(indirect_address);
-          let hi = self.bus.read(indirect_address & 0xFF00);
-          return (hi as u16) << 8 | (lo as u16);
-        } else {
-          return self.bus.read_u16(indirect_address);
-        }
-      }
-      // !!地址处理和read_u16不同。
-      IndexedIndirect => {
-        let pointer = self.bus.read(address).wrapping_add(self.registers.x);
-        let lo = self.bus.read(pointer as u16);
-        let hi = self.bus.read(pointer.wrapping_add(1) as u16);
-        return ((hi as u16) << 8) | (lo as u16);
-      }
-      IndirectIndexed => {
-        let param = self.bus.read(address);
-        let lo = self.bus.read(param as u16);
-        let hi = self.bus.read(param.wrapping_add(1) as u16);
-        let indirect_address = ((hi as u16) << 8) | (lo as u16);
-        return indirect_address.wrapping_add(self.registers.y as u16);
-      }
-      _ => panic!("addressing mode {:?} is not support", mode),
+  /// CPU 是否因为 JAM 指令而停机，此时 PC 仍指向该 JAM 指令
+  pub fn is_jammed(&self) -> bool {
+    return self.jammed;
+  }
+
+  /// 产生一次 NMI 边沿（例如 PPU 进入 vblank），在当前指令结束后响应
+  pub fn trigger_nmi(&mut self) {
+    self.nmi_pending = true;
+  }
+
+  /// 设置某个外设的 IRQ 电平，`asserted` 为 `true` 表示拉低 IRQ 线
+  pub fn set_irq(&mut self, source: IrqSource, asserted: bool) {
+    self.irq_sources.set(source, asserted);
+  }
+
+  /// IRQ 线当前是否有效，包括总线上的外设
+  pub fn irq_asserted(&self) -> bool {
+    return !(self.irq_sources | self.bus.irq()).is_empty();
+  }
+
+  /// 压入 PC 和状态寄存器，设置 `I` 标志并跳转到中断向量
+  fn interrupt(&mut self, interrupt: &Interrupt) {
+    self.stack_push_u16(self.registers.program_counter);
+    let mut status = self.registers.status;
+    status.remove(Flags::B);
+    status.insert(Flags::from_bits_truncate(interrupt.b_flag_mask));
+    self.stack_push(status.bits());
+
+    self.registers.status.insert(Flags::I);
+    self.registers.program_counter = self.read_u16(interrupt.vector_address);
+  }
+
+  /// 在指令边界检查并响应挂起的中断，NMI 优先于 IRQ
+  fn poll_interrupts(&mut self) {
+    if self.bus.poll_nmi() {
+      self.nmi_pending = true;
+    }
+
+    if self.nmi_pending {
+      self.nmi_pending = false;
+      self.interrupt(&interrupt::NMI);
+      self.cycles += INTERRUPT_CYCLES;
+      self.bus.tick(INTERRUPT_CYCLES);
+    } else if self.irq_asserted() && !self.registers.status.contains(Flags::I) {
+      self.interrupt(&interrupt::IRQ);
+      self.cycles += INTERRUPT_CYCLES;
+      self.bus.tick(INTERRUPT_CYCLES);
+    }
+  }
+
+  fn read(&mut self, address: u16) -> u8 {
+    return self.bus.read(address);
+  }
+
+  fn read_u16(&mut self, address: u16) -> u16 {
+    return self.bus.read_u16(address);
+  }
+
+  fn write(&mut self, address: u16, data: u8) {
+    self.bus.write(address, data);
+  }
+
+  /// 计算 `address` 处指令操作数的实际地址，只通过 `peek` 访问内存，不会产生副作用，
+  /// 供 trace 和调试器使用。
+  ///
+  /// 返回值的第二项表示索引寻址时是否跨越了页边界。
+  pub fn get_absolute_address(&self, mode: &AddressingMode, address: u16) -> Result<(u16, bool), EmuError> {
+    let (x, y) = (self.registers.x, self.registers.y);
+    let result = resolve_address(mode, address, x, y, |address| self.bus.peek(address));
+    return result.ok_or_else(|| {
+      let (pc, opcode) = self.current_instruction;
+      EmuError::UnsupportedAddressingMode { pc, opcode, mode: *mode }
+    });
+  }
+
+  /// 执行指令时计算操作数地址，对内存的访问都是真正的总线读取
+  fn get_operand_address(&mut self, mode: &AddressingMode) -> (u16, bool) {
+    let address = self.registers.program_counter;
+    if let AddressingMode::Immediate = mode {
+      return (address, false);
     }
+    let (x, y) = (self.registers.x, self.registers.y);
+    let result = resolve_address(mode, address, x, y, |address| self.bus.read(address));
+    // 指令表里访问内存的指令都使用有操作数地址的寻址模式
+    return result.unwrap_or_else(|| panic!("addressing mode {:?} has no operand address", mode));
   }
 
-  fn get_operand_address(&self, mode: &AddressingMode) -> u16 {
-    match mode {
-      AddressingMode::Immediate => self.registers.program_counter,
-      _ => self.get_absolute_address(mode, self.registers.program_counter),
+  /// 读取操作数，索引寻址跨页时追加 1 个周期。
+  ///
+  /// 仅用于读类指令，写入和读-改-写指令的周期数固定，不受跨页影响。
+  fn read_operand(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, page_cross) = self.get_operand_address(mode);
+    if page_cross {
+      self.cycles += 1;
     }
+    return self.read(address);
   }
 
   /// LIFO, top-down, 8 bit range, 0x0100 - 0x01FF
   fn stack_push(&mut self, data: u8) {
-    self.bus.write(0x0100 + (self.registers.stack_pointer as u16), data);
+    self.write(0x0100 + (self.registers.stack_pointer as u16), data);
     self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(1);
   }
 
   fn stack_pop(&mut self) -> u8 {
     self.registers.stack_pointer = self.registers.stack_pointer.wrapping_add(1);
-    return self.bus.read(0x0100 + (self.registers.stack_pointer as u16));
+    return self.read(0x0100 + (self.registers.stack_pointer as u16));
   }
 
   fn stack_push_u16(&mut self, data: u16) {
@@ -97,15 +263,20 @@ impl CPU {
     return hi << 8 | lo;
   }
 
+  /// 把程序写入 `$0600`，并将复位向量指向它
   pub fn load(&mut self, program: Vec<u8>) {
-    for i in 0..(program.len() as u16) {
-      self.bus.write(0x0600 + i, program[i as usize]);
-    }
-    self.bus.write_u16(0xFFFC, 0x0600);
+    return self.load_at(0x0600, &program);
+  }
 
-    // TODO
-    // self.bus.memory[0x8000..(0x8000 + program.len())].copy_from_slice(&program[..]);
-    // self.bus.write_u16(0xFFFC, 0x8000);
+  /// 把程序写入 `address`，并将复位向量指向它。
+  ///
+  /// 写入经过总线，所以 NES 的 `Bus` 上只能写到 RAM 里，而且无法修改卡带中的复位向量；
+  /// 需要任意地址时使用 `FlatMemory`。
+  pub fn load_at(&mut self, address: u16, program: &[u8]) {
+    for (i, byte) in program.iter().enumerate() {
+      self.write(address.wrapping_add(i as u16), *byte);
+    }
+    self.bus.write_u16(0xFFFC, address);
   }
 
   /// NES 平台有一个特殊的机制来标记 CPU 应该从哪里开始执行。
@@ -113,247 +284,155 @@ impl CPU {
   ///
   /// - 重置状态（寄存器和标志）
   /// - 将 `program_counter` 寄存器设置为存储在 `0xFFFC` 的 16 位地址
+  ///
+  /// 复位序列本身需要 7 个周期。
   pub fn reset(&mut self) {
-    self.registers.reset(self.bus.read_u16(0xFFFC));
+    self.jammed = false;
+    let program_counter = self.read_u16(0xFFFC);
+    self.registers.reset(program_counter);
+    self.cycles += 7;
+    self.bus.tick(7);
   }
 
   pub fn load_and_run(&mut self, program: Vec<u8>) {
     self.load(program);
     self.reset();
-    self.run();
+    return self.run();
   }
 
   pub fn run(&mut self) {
-    self.run_with_callback(|_| {});
+    return self.run_with_callback(|_| {});
   }
 
+  /// 循环执行指令，每条指令执行前调用 `callback`，执行完 BRK 或者 CPU 停机后返回。
   pub fn run_with_callback<C>(&mut self, mut callback: C)
   where
-    C: FnMut(&mut CPU),
+    C: FnMut(&mut Self),
   {
-    let ref opcodes: HashMap<u8, &'static Opcode> = *OPCODES_MAP;
-
     loop {
+      self.poll_interrupts();
       callback(self);
-      let code = self.bus.read(self.registers.program_counter);
-
-      self.registers.program_counter += 1;
-      let program_counter_state = self.registers.program_counter;
-
-      let opcode = opcodes
-        .get(&code)
-        .expect(&format!("Opcode {:x} is not recognized", code));
-
-      let mode = &opcode.mode;
-
-      match code {
-        // Transfer Instructions
-        // LDA
-        0xA9 | 0xA5 | 0xB5 | 0xAD | 0xBD | 0xB9 | 0xA1 | 0xB1 => self.load_accumulator_with_memory(mode),
-        // LDX
-        0xA2 | 0xA6 | 0xB6 | 0xAE | 0xBE => self.load_index_x_with_memory(mode),
-        // LDY
-        0xA0 | 0xA4 | 0xB4 | 0xAC | 0xBC => self.load_index_y_with_memory(mode),
-        // STA
-        0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91 => self.store_accumulator_in_memory(mode),
-        // STX
-        0x86 | 0x96 | 0x8E => self.store_index_x_in_memory(mode),
-        // STY
-        0x84 | 0x94 | 0x8C => self.store_index_y_in_memory(mode),
-        // TAX
-        0xAA => self.transfer_accumulator_to_index_x(),
-        // TAY
-        0xA8 => self.transfer_accumulator_to_index_y(),
-        // TSX
-        0xBA => self.transfer_stack_pointer_to_index_x(),
-        // TXA
-        0x8A => self.transfer_index_x_to_accumulator(),
-        // TXS
-        0x9A => self.transfer_index_x_to_stack_register(),
-        // TYA
-        0x98 => self.transfer_index_y_to_accumulator(),
-
-        // Stack Instructions
-        // PHA
-        0x48 => self.push_accumulator_on_stack(),
-        // PHP
-        0x08 => self.push_processor_status_on_stack(),
-        // PLA
-        0x68 => self.pull_accumulator_from_stack(),
-        // PLP
-        0x28 => self.pull_processor_status_from_stack(),
-
-        // Decrements & Increments
-        // DEC
-        0xC6 | 0xD6 | 0xCE | 0xDE => self.decrement_memory_by_one(mode),
-        // DEX
-        0xCA => self.decrement_index_x_by_one(),
-        // DEY
-        0x88 => self.decrement_index_y_by_one(),
-        // INC
-        0xE6 | 0xF6 | 0xEE | 0xFE => {
-          self.increment_memory_by_one(mode);
-        }
-        // INX
-        0xE8 => self.increment_index_x_by_one(),
-        // INY
-        0xC8 => self.increment_index_y_by_one(),
-
-        // Arithmetic Operations
-        // ADC
-        0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71 => self.add_memory_to_accumulator_with_carry(mode),
-        // SBC
-        0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1 => {
-          self.subtract_memory_from_accumulator_with_borrow(mode)
-        }
-
-        // Logical Operations
-        // AND
-        0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31 => self.and_memory_with_accumulator(mode),
-        // EOR
-        0x49 | 0x45 | 0x55 | 0x4D | 0x5D | 0x59 | 0x41 | 0x51 => self.exclusive_or_memory_with_accumulator(mode),
-        // ORA
-        0x09 | 0x05 | 0x15 | 0x0D | 0x1D | 0x19 | 0x01 | 0x11 => self.or_memory_with_accumulator(mode),
-
-        // Shift & Rotate Instructions
-        // ASL
-        0x0A => self.shift_left_one_bit_accumulator(),
-        0x06 | 0x16 | 0x0E | 0x1E => {
-          self.shift_left_one_bit_memory(mode);
-        },
-        // LSR
-        0x4A => self.shift_one_bit_right_accumulator(),
-        0x46 | 0x56 | 0x4E | 0x5E => {
-          self.shift_one_bit_right_memory(mode);
-        },
-        // ROL
-        0x2A => self.rotate_one_bit_left_accumulator(),
-        0x26 | 0x36 | 0x2E | 0x3E => {
-          self.rotate_one_bit_left_memory(mode);
-        },
-        // ROR
-        0x6A => self.rotate_one_bit_right_accumulator(),
-        0x66 | 0x76 | 0x6E | 0x7E => {
-          self.rotate_one_bit_right_memory(mode);
-        },
-
-        // Flag Instructions
-        // CLC
-        0x18 => self.clear_carry_flag(),
-        // CLD
-        0xD8 => self.clear_decimal_mode(),
-        // CLI
-        0x58 => self.clear_interrupt_disable_bit(),
-        // CLV
-        0xB8 => self.clear_overflow_flag(),
-        // SEC
-        0x38 => self.set_carry_flag(),
-        // SED
-        0xF8 => self.set_decimal_mode(),
-        // SEI
-        0x78 => self.set_interrupt_disable_bit(),
-
-        // Comparisons
-        // CMP
-        0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1 => self.compare_memory_with_accumulator(mode),
-        // CPX
-        0xE0 | 0xE4 | 0xEC => self.compare_memory_and_index_x(mode),
-        // CPY
-        0xC0 | 0xC4 | 0xCC => self.compare_memory_and_index_y(mode),
-
-        // Conditional Branch Instructions
-        // BCC
-        0x90 => self.branch_on_carry_clear(),
-        // BCS
-        0xB0 => self.branch_on_carry_set(),
-        // BEQ
-        0xF0 => self.branch_on_result_zero(),
-        // BMI
-        0x30 => self.branch_on_result_minus(),
-        // BNE
-        0xD0 => self.branch_on_result_not_zero(),
-        // BPL
-        0x10 => self.branch_on_result_plus(),
-        // BVC
-        0x50 => self.branch_on_overflow_clear(),
-        // BVS
-        0x70 => self.branch_on_overflow_set(),
-
-        // Jumps & Subroutines
-        // JMP
-        0x4C | 0x6C => self.jump_to_new_location(mode),
-        // JSR
-        0x20 => self.jump_to_new_location_saving_return_address(),
-        // RTS
-        0x60 => self.return_from_subroutine(),
-
-        // Interrupts
-        // BRK
-        // 0x00 => self.force_break(),
-        0x00 => return,
-        // RTI
-        0x40 => self.return_from_interrupt(),
-
-        // Other
-        // BIT
-        0x24 | 0x2C => self.test_bits_in_memory_with_accumulator(mode),
-        // NOP
-        0xEA => {}
-
-        // "Illegal" Opcodes and Undocumented Instructions
-        // ALR
-        0x4B => self.alr(mode),
-        // ANC, ANC2
-        0x0B | 0x2B => self.anc(mode),
-        // ANE, AXX
-        0x8B => self.ane_xaa(mode),
-        // ARR
-        // 0x6B => self.arr(mode),
-        // DCP, DCM
-        0xC7 | 0xD7 | 0xCF | 0xDF | 0xDB | 0xC3 | 0xD3 => self.dcp_dcm(mode),
-        // ISC, ISB, INS
-        0xE7 | 0xF7 | 0xEF | 0xFF | 0xFB | 0xE3 | 0xF3 => self.isc_isb_ins(mode),
-        // LAS, LAR
-        0xBB => self.las_lar(mode),
-        // LAX
-        0xA7 | 0xB7 | 0xAF | 0xBF | 0xA3 | 0xB3 => self.lax(mode),
-        // RLA
-        0x27 | 0x37 | 0x2F | 0x3F | 0x3B | 0x23 | 0x33 => self.rla(mode),
-        // RRA
-        0x67 | 0x77 | 0x6F | 0x7F | 0x7B | 0x63 | 0x73 => self.rra(mode),
-        // SAX, AXS, AAX
-        0x87 | 0x97 | 0x8F | 0x83 => self.sax_axs_aax(mode),
-        // SLO, ASO
-        0x07 | 0x17 | 0x0F | 0x1F | 0x1B | 0x03 | 0x13 => self.slo_aso(mode),
-        // SRE, LSE
-        0x47 | 0x57 | 0x4F | 0x5F | 0x5B | 0x43 | 0x53 => self.sre_lse(mode),
-        // USBC
-        0xEB => self.subtract_memory_from_accumulator_with_borrow(mode),
-        // NOPs
-        0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => {}
-        0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 => {}
-        0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74 | 0xD4 | 0xF4 | 0x0C => {}
-        0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => {}
-        _ => {
-          panic!("opcode {:02X} not support", code);
-        }
+      let instruction = self.execute_instruction();
+      if instruction.opcode == 0x00 || self.jammed {
+        return;
       }
+    }
+  }
+
+  /// 单步执行：先响应挂起的中断，再执行一条指令。
+  ///
+  /// CPU 停机后不再执行任何指令，直接返回 `StopReason::Jammed`。
+  pub fn step(&mut self) -> RunResult {
+    if self.jammed {
+      return RunResult {
+        cycles: 0,
+        instructions: 0,
+        last_instruction: None,
+        stop_reason: StopReason::Jammed,
+      };
+    }
+
+    let start = self.cycles;
+    self.poll_interrupts();
+    let instruction = self.execute_instruction();
+    return RunResult {
+      cycles: self.cycles - start,
+      instructions: 1,
+      last_instruction: Some(instruction),
+      stop_reason: StopReason::Stepped,
+    };
+  }
+
+  /// 执行指令直到消耗掉至少 `budget` 个周期。
+  ///
+  /// 指令不会被拆分，所以实际消耗的周期数可能略多于预算。
+  pub fn run_for_cycles(&mut self, budget: u64) -> RunResult {
+    let end = self.cycles + budget;
+    return self.run_until(StopReason::BudgetExhausted, |cpu| cpu.cycles >= end);
+  }
+
+  /// 执行到 PPU 完成当前帧、进入 vblank 为止，此时 PPU 的画面是完整的。
+  ///
+  /// 没有 PPU 的内存按 NTSC 的 PPU 时钟（CPU 周期 × 3）划分帧。
+  pub fn run_frame(&mut self) -> RunResult {
+    let frame = self.frame_count();
+    return self.run_until(StopReason::FrameComplete, |cpu| cpu.frame_count() > frame);
+  }
 
-      if program_counter_state == self.registers.program_counter {
-        self.registers.program_counter += (opcode.length - 1) as u16;
+  fn frame_count(&self) -> u64 {
+    return self.bus.frame_count().unwrap_or(self.cycles * 3 / PPU_DOTS_PER_FRAME);
+  }
+
+  fn run_until<F>(&mut self, reason: StopReason, done: F) -> RunResult
+  where
+    F: Fn(&Self) -> bool,
+  {
+    let start = self.cycles;
+    let mut result = RunResult {
+      cycles: 0,
+      instructions: 0,
+      last_instruction: None,
+      stop_reason: reason,
+    };
+    while !done(self) {
+      let step = self.step();
+      if step.stop_reason == StopReason::Jammed {
+        result.stop_reason = StopReason::Jammed;
+        break;
       }
+      result.instructions += 1;
+      result.last_instruction = step.last_instruction;
+    }
+    result.cycles = self.cycles - start;
+    return result;
+  }
+
+  /// 取指、译码并执行一条指令
+  fn execute_instruction(&mut self) -> Instruction {
+    let start = self.cycles;
+    let address = self.registers.program_counter;
+    let code = self.read(address);
+    self.current_instruction = (address, code);
+
+    self.registers.program_counter = self.registers.program_counter.wrapping_add(1);
+    let program_counter_state = self.registers.program_counter;
+
+    let opcode = &Self::OPCODES[code as usize];
+    // 读写操作数一般发生在指令的最后一个周期，先让总线走到那里，
+    // PPU 等设备才能在接近真实的时刻看到这次访问
+    let lead = opcode.cycles as u64 - 1;
+    self.bus.tick(lead);
+    (opcode.handler)(self, &opcode.mode);
+
+    if program_counter_state == self.registers.program_counter {
+      self.registers.program_counter = self.registers.program_counter.wrapping_add((opcode.length - 1) as u16);
+    }
+
+    // 基础周期数，跨页和分支的额外周期已在指令执行时累加
+    self.cycles += opcode.cycles as u64;
+    self.bus.tick(self.cycles - start - lead);
+
+    if self.bus.poll_dma() {
+      let stall = OAM_DMA_CYCLES + self.cycles % 2;
+      self.cycles += stall;
+      self.bus.tick(stall);
     }
+
+    return Instruction {
+      address,
+      opcode: code,
+      mnemonic: opcode.mnemonic,
+    };
   }
 }
 
 /// impl for instructions
-impl CPU {
-  /// Transfer Instructions
+impl<M: Memory> CPU<M> {
+  // Transfer Instructions
 
   /// LDA
   fn load_accumulator_with_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
 
     self.registers.a = data;
     self.registers.set_nz_flags(self.registers.a);
@@ -361,99 +440,97 @@ impl CPU {
 
   /// LDX
   fn load_index_x_with_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let param = self.bus.read(address);
+    let param = self.read_operand(mode);
     self.registers.x = param;
     self.registers.set_nz_flags(self.registers.x);
   }
 
   /// LDY
   fn load_index_y_with_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let param = self.bus.read(address);
+    let param = self.read_operand(mode);
     self.registers.y = param;
     self.registers.set_nz_flags(self.registers.y);
   }
 
   /// STA
   fn store_accumulator_in_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, self.registers.a);
+    let (address, _) = self.get_operand_address(mode);
+    self.write(address, self.registers.a);
   }
 
   /// STX
   fn store_index_x_in_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, self.registers.x);
+    let (address, _) = self.get_operand_address(mode);
+    self.write(address, self.registers.x);
   }
 
   /// STY
   fn store_index_y_in_memory(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, self.registers.y);
+    let (address, _) = self.get_operand_address(mode);
+    self.write(address, self.registers.y);
   }
 
   /// TAX
-  fn transfer_accumulator_to_index_x(&mut self) {
+  fn transfer_accumulator_to_index_x(&mut self, _mode: &AddressingMode) {
     self.registers.x = self.registers.a;
     self.registers.set_nz_flags(self.registers.x);
   }
 
   /// TAY
-  fn transfer_accumulator_to_index_y(&mut self) {
+  fn transfer_accumulator_to_index_y(&mut self, _mode: &AddressingMode) {
     self.registers.y = self.registers.a;
     self.registers.set_nz_flags(self.registers.y);
   }
 
   /// TSX
-  fn transfer_stack_pointer_to_index_x(&mut self) {
+  fn transfer_stack_pointer_to_index_x(&mut self, _mode: &AddressingMode) {
     self.registers.x = self.registers.stack_pointer;
     self.registers.set_nz_flags(self.registers.x);
   }
 
   /// TXA
-  fn transfer_index_x_to_accumulator(&mut self) {
+  fn transfer_index_x_to_accumulator(&mut self, _mode: &AddressingMode) {
     self.registers.a = self.registers.x;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// TXS
-  fn transfer_index_x_to_stack_register(&mut self) {
+  fn transfer_index_x_to_stack_register(&mut self, _mode: &AddressingMode) {
     self.registers.stack_pointer = self.registers.x;
   }
 
   /// TYA
-  fn transfer_index_y_to_accumulator(&mut self) {
+  fn transfer_index_y_to_accumulator(&mut self, _mode: &AddressingMode) {
     self.registers.a = self.registers.y;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// Stack Instructions
   /// PHA
-  fn push_accumulator_on_stack(&mut self) {
+  fn push_accumulator_on_stack(&mut self, _mode: &AddressingMode) {
     self.stack_push(self.registers.a);
   }
 
   /// PHP
   ///
   /// - [the B flag](https://www.nesdev.org/wiki/Status_flags#The_B_flag)
-  fn push_processor_status_on_stack(&mut self) {
-    let mut status = self.registers.status.clone();
+  fn push_processor_status_on_stack(&mut self, _mode: &AddressingMode) {
+    let mut status = self.registers.status;
     status.insert(Flags::B);
     status.insert(Flags::U);
     self.stack_push(status.bits());
   }
 
   /// PLA
-  fn pull_accumulator_from_stack(&mut self) {
+  fn pull_accumulator_from_stack(&mut self, _mode: &AddressingMode) {
     self.registers.a = self.stack_pop();
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// PLP
-  fn pull_processor_status_from_stack(&mut self) {
+  fn pull_processor_status_from_stack(&mut self, _mode: &AddressingMode) {
     let data = self.stack_pop();
-    self.registers.status = Flags::from_bits(data).expect(&format!("Status {:x} is not valid", data));
+    self.registers.status = Flags::from_bits_truncate(data);
     self.registers.status.remove(Flags::B);
     self.registers.status.insert(Flags::U);
   }
@@ -461,43 +538,48 @@ impl CPU {
   /// Decrements & Increments
   /// DEC
   fn decrement_memory_by_one(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     data = data.wrapping_sub(1);
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
   }
 
   /// DEX
-  fn decrement_index_x_by_one(&mut self) {
+  fn decrement_index_x_by_one(&mut self, _mode: &AddressingMode) {
     self.registers.x = self.registers.x.wrapping_sub(1);
     self.registers.set_nz_flags(self.registers.x);
   }
 
   /// DEY
-  fn decrement_index_y_by_one(&mut self) {
+  fn decrement_index_y_by_one(&mut self, _mode: &AddressingMode) {
     self.registers.y = self.registers.y.wrapping_sub(1);
     self.registers.set_nz_flags(self.registers.y);
   }
 
   /// INC
-  fn increment_memory_by_one(&mut self, mode: &AddressingMode) -> u8 {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+  fn increment_memory_by_one(&mut self, mode: &AddressingMode) {
+    self.increment_memory(mode);
+  }
+
+  /// INC 并返回写回内存的值，供 ISC 复用
+  fn increment_memory(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     data = data.wrapping_add(1);
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
     return data;
   }
 
   /// INX
-  fn increment_index_x_by_one(&mut self) {
+  fn increment_index_x_by_one(&mut self, _mode: &AddressingMode) {
     self.registers.x = self.registers.x.wrapping_add(1);
     self.registers.set_nz_flags(self.registers.x);
   }
 
   /// INY
-  fn increment_index_y_by_one(&mut self) {
+  fn increment_index_y_by_one(&mut self, _mode: &AddressingMode) {
     self.registers.y = self.registers.y.wrapping_add(1);
     self.registers.set_nz_flags(self.registers.y);
   }
@@ -505,43 +587,53 @@ impl CPU {
   /// Arithmetic Operations
   /// ADC
   fn add_memory_to_accumulator_with_carry(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
-    self.registers.add_to_a(data);
+    let data = self.read_operand(mode);
+    self.add_with_carry(data);
   }
 
   /// SBC
-  /// `A - B = A + (-B)`, `-B = !B + 1`
   fn subtract_memory_from_accumulator_with_borrow(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
-    // WHY
-    self
-      .registers
-      .add_to_a((data as i8).wrapping_neg().wrapping_sub(1) as u8);
+    let data = self.read_operand(mode);
+    self.subtract_with_borrow(data);
+  }
+
+  /// ADC 的运算部分，RRA 也会用到
+  fn add_with_carry(&mut self, data: u8) {
+    if self.variant.has_decimal_mode() && self.registers.status.contains(Flags::D) {
+      self.registers.add_to_a_decimal(data);
+    } else {
+      self.registers.add_to_a(data);
+    }
+  }
+
+  /// SBC 的运算部分，ISB 也会用到。
+  /// `A - B = A + (-B)`, `-B = !B + 1`
+  fn subtract_with_borrow(&mut self, data: u8) {
+    if self.variant.has_decimal_mode() && self.registers.status.contains(Flags::D) {
+      self.registers.subtract_from_a_decimal(data);
+    } else {
+      self.registers.add_to_a((data as i8).wrapping_neg().wrapping_sub(1) as u8);
+    }
   }
 
   /// Logical Operations
   /// AND
   fn and_memory_with_accumulator(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = self.registers.a & data;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// EOR
   fn exclusive_or_memory_with_accumulator(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = self.registers.a ^ data;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// ORA
   fn or_memory_with_accumulator(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = self.registers.a | data;
     self.registers.set_nz_flags(self.registers.a);
   }
@@ -551,56 +643,71 @@ impl CPU {
   /// All shift and rotate instructions preserve the bit shifted out in the carry flag.
   ///
   /// ASL
-  fn shift_left_one_bit_memory(&mut self, mode: &AddressingMode) -> u8 {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+  fn shift_left_one_bit_memory(&mut self, mode: &AddressingMode) {
+    self.shift_left_memory(mode);
+  }
+
+  /// ASL 并返回写回内存的值，供 SLO 复用
+  fn shift_left_memory(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
 
     self.registers.status.set(Flags::C, data & 0x80 == 0x80);
     data = data << 1;
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
     return data;
   }
 
   /// ASL accumulator
-  fn shift_left_one_bit_accumulator(&mut self) {
+  fn shift_left_one_bit_accumulator(&mut self, _mode: &AddressingMode) {
     self.registers.status.set(Flags::C, self.registers.a & 0x80 == 0x80);
     self.registers.a = self.registers.a << 1;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// LSR
-  fn shift_one_bit_right_memory(&mut self, mode: &AddressingMode) -> u8 {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+  fn shift_one_bit_right_memory(&mut self, mode: &AddressingMode) {
+    self.shift_right_memory(mode);
+  }
+
+  /// LSR 并返回写回内存的值，供 SRE 复用
+  fn shift_right_memory(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     self.registers.status.set(Flags::C, data & 0x01 == 1);
     data = data >> 1;
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
     return data;
   }
 
   /// LSR accumulator
-  fn shift_one_bit_right_accumulator(&mut self) {
+  fn shift_one_bit_right_accumulator(&mut self, _mode: &AddressingMode) {
     self.registers.status.set(Flags::C, self.registers.a & 0x01 == 1);
     self.registers.a = self.registers.a >> 1;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   /// ROL
-  fn rotate_one_bit_left_memory(&mut self, mode: &AddressingMode) -> u8 {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+  fn rotate_one_bit_left_memory(&mut self, mode: &AddressingMode) {
+    self.rotate_left_memory(mode);
+  }
+
+  /// ROL 并返回写回内存的值，供 RLA 复用
+  fn rotate_left_memory(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     let carry = self.registers.status.contains(Flags::C);
     self.registers.status.set(Flags::C, data & 0x80 == 0x80);
     data = (data << 1) | (if carry { 0x01 } else { 0x00 });
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
     return data;
   }
 
   /// ROL accumulator
-  fn rotate_one_bit_left_accumulator(&mut self) {
+  fn rotate_one_bit_left_accumulator(&mut self, _mode: &AddressingMode) {
     let carry = self.registers.status.contains(Flags::C);
     self.registers.status.set(Flags::C, self.registers.a & 0x80 == 0x80);
     self.registers.a = (self.registers.a << 1) | (if carry { 0x01 } else { 0x00 });
@@ -608,20 +715,25 @@ impl CPU {
   }
 
   /// ROR
-  fn rotate_one_bit_right_memory(&mut self, mode: &AddressingMode) -> u8 {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+  fn rotate_one_bit_right_memory(&mut self, mode: &AddressingMode) {
+    self.rotate_right_memory(mode);
+  }
+
+  /// ROR 并返回写回内存的值，供 RRA 复用
+  fn rotate_right_memory(&mut self, mode: &AddressingMode) -> u8 {
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     let carry = self.registers.status.contains(Flags::C);
     self.registers.status.set(Flags::C, data & 0x01 == 0x01);
 
     data = (data >> 1) | (if carry { 0x80 } else { 0x00 });
-    self.bus.write(address, data);
+    self.write(address, data);
     self.registers.set_nz_flags(data);
     return data;
   }
 
   /// ROR accumulator
-  fn rotate_one_bit_right_accumulator(&mut self) {
+  fn rotate_one_bit_right_accumulator(&mut self, _mode: &AddressingMode) {
     let carry = self.registers.status.contains(Flags::C);
     self.registers.status.set(Flags::C, self.registers.a & 0x01 == 0x01);
     self.registers.a = (self.registers.a >> 1) | (if carry { 0x80 } else { 0x00 });
@@ -630,37 +742,37 @@ impl CPU {
 
   /// Flag Instructions
   /// CLC
-  fn clear_carry_flag(&mut self) {
+  fn clear_carry_flag(&mut self, _mode: &AddressingMode) {
     self.registers.status.remove(Flags::C);
   }
 
   /// CLD
-  fn clear_decimal_mode(&mut self) {
+  fn clear_decimal_mode(&mut self, _mode: &AddressingMode) {
     self.registers.status.remove(Flags::D);
   }
 
   /// CLI
-  fn clear_interrupt_disable_bit(&mut self) {
+  fn clear_interrupt_disable_bit(&mut self, _mode: &AddressingMode) {
     self.registers.status.remove(Flags::I);
   }
 
   /// CLV
-  fn clear_overflow_flag(&mut self) {
+  fn clear_overflow_flag(&mut self, _mode: &AddressingMode) {
     self.registers.status.remove(Flags::V);
   }
 
   /// SEC
-  fn set_carry_flag(&mut self) {
+  fn set_carry_flag(&mut self, _mode: &AddressingMode) {
     self.registers.status.insert(Flags::C);
   }
 
   /// SED
-  fn set_decimal_mode(&mut self) {
+  fn set_decimal_mode(&mut self, _mode: &AddressingMode) {
     self.registers.status.insert(Flags::D);
   }
 
   /// SEI
-  fn set_interrupt_disable_bit(&mut self) {
+  fn set_interrupt_disable_bit(&mut self, _mode: &AddressingMode) {
     self.registers.status.insert(Flags::I);
   }
 
@@ -670,14 +782,13 @@ impl CPU {
   /// the Zero, Carry and Negative flags.
   /// (See the branch instructions below for how to evaluate flags.)
   ///
-  /// | Relation R − Op    | Z | C |	N                 |
+  /// | Relation R − Op    | Z | C | N                  |
   /// |--------------------|---|---|--------------------|
   /// | Register < Operand | 0 | 0 | sign bit of result |
   /// | Register = Operand | 1 | 1 | 0                  |
   /// | Register > Operand | 0 | 1 | sign bit of result |
   fn compare_memory_with(&mut self, mode: &AddressingMode, rv: u8) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.status.set(Flags::C, rv >= data);
     self.registers.set_nz_flags(rv.wrapping_sub(data));
   }
@@ -701,96 +812,100 @@ impl CPU {
   ///
   /// Branch targets are relative, signed 8-bit address offsets.
   /// (An offset of #0 corresponds to the immedately following address — or a rather odd and expensive NOP.)
+  ///
+  /// 分支成立时额外消耗 1 个周期，若跳转目标与下一条指令不在同一页，再额外消耗 1 个周期。
   fn branch(&mut self, condition: bool) {
     if condition {
-      let offset = self.bus.read(self.registers.program_counter) as i8;
-      self.registers.program_counter = self
-        .registers
-        .program_counter
-        .wrapping_add(1)
-        .wrapping_add(offset as u16);
+      let offset = self.read(self.registers.program_counter) as i8;
+      let next = self.registers.program_counter.wrapping_add(1);
+      let target = next.wrapping_add(offset as u16);
+
+      self.cycles += 1;
+      if page_crossed(next, target) {
+        self.cycles += 1;
+      }
+      self.registers.program_counter = target;
     }
   }
 
   /// BCC
-  fn branch_on_carry_clear(&mut self) {
+  fn branch_on_carry_clear(&mut self, _mode: &AddressingMode) {
     self.branch(!self.registers.status.contains(Flags::C));
   }
 
   /// BCS
-  fn branch_on_carry_set(&mut self) {
+  fn branch_on_carry_set(&mut self, _mode: &AddressingMode) {
     self.branch(self.registers.status.contains(Flags::C));
   }
 
   /// BEQ
-  fn branch_on_result_zero(&mut self) {
+  fn branch_on_result_zero(&mut self, _mode: &AddressingMode) {
     self.branch(self.registers.status.contains(Flags::Z));
   }
 
   /// BMI
-  fn branch_on_result_minus(&mut self) {
+  fn branch_on_result_minus(&mut self, _mode: &AddressingMode) {
     self.branch(self.registers.status.contains(Flags::N));
   }
 
   /// BNE
-  fn branch_on_result_not_zero(&mut self) {
+  fn branch_on_result_not_zero(&mut self, _mode: &AddressingMode) {
     self.branch(!self.registers.status.contains(Flags::Z));
   }
 
   /// BPL
-  fn branch_on_result_plus(&mut self) {
+  fn branch_on_result_plus(&mut self, _mode: &AddressingMode) {
     self.branch(!self.registers.status.contains(Flags::N));
   }
 
   /// BVC
-  fn branch_on_overflow_clear(&mut self) {
+  fn branch_on_overflow_clear(&mut self, _mode: &AddressingMode) {
     self.branch(!self.registers.status.contains(Flags::V));
   }
 
   /// BVS
-  fn branch_on_overflow_set(&mut self) {
+  fn branch_on_overflow_set(&mut self, _mode: &AddressingMode) {
     self.branch(self.registers.status.contains(Flags::V));
   }
 
-  /// Jumps & Subroutines
-  ///
-  /// JSR and RTS affect the stack as the return address is pushed onto or pulled from the stack, respectively.
-  /// (JSR will first push the high-byte of the return address [PC+2] onto the stack, then the low-byte.
-  /// The stack will then contain, seen from the bottom or from the most recently added byte, [PC+2]-L [PC+2]-H.)
+  // Jumps & Subroutines
+  //
+  // JSR and RTS affect the stack as the return address is pushed onto or pulled from the stack, respectively.
+  // (JSR will first push the high-byte of the return address [PC+2] onto the stack, then the low-byte.
+  // The stack will then contain, seen from the bottom or from the most recently added byte, [PC+2]-L [PC+2]-H.)
 
   /// JMP
   fn jump_to_new_location(&mut self, mode: &AddressingMode) {
-    self.registers.program_counter = self.get_operand_address(mode);
+    let (address, _) = self.get_operand_address(mode);
+    self.registers.program_counter = address;
   }
 
   /// JSR
-  fn jump_to_new_location_saving_return_address(&mut self) {
+  fn jump_to_new_location_saving_return_address(&mut self, _mode: &AddressingMode) {
     // TODO why -1
-    self.stack_push_u16(self.registers.program_counter + 2 - 1);
-    let address = self.get_operand_address(&AddressingMode::Absolute);
+    self.stack_push_u16(self.registers.program_counter.wrapping_add(2 - 1));
+    let (address, _) = self.get_operand_address(&AddressingMode::Absolute);
     self.registers.program_counter = address;
   }
 
   /// RTS
-  fn return_from_subroutine(&mut self) {
-    self.registers.program_counter = self.stack_pop_u16() + 1;
+  fn return_from_subroutine(&mut self, _mode: &AddressingMode) {
+    self.registers.program_counter = self.stack_pop_u16().wrapping_add(1);
   }
 
   /// Interrupts
   /// BRK
-  /// TODO
-  // fn force_break(&mut self) {
-  //   self.stack_push_u16(self.registers.program_counter.wrapping_add(2));
-  //   let mut status = self.registers.status.clone();
-  //   status.insert(Flags::B);
-  //   status.insert(Flags::U);
-  //   self.stack_push(status.bits());
-  // }
+  ///
+  /// BRK 后面跟着一个填充字节，压栈的返回地址是 BRK 地址 + 2。
+  fn force_break(&mut self, _mode: &AddressingMode) {
+    self.registers.program_counter = self.registers.program_counter.wrapping_add(1);
+    return self.interrupt(&interrupt::BRK);
+  }
 
   /// RTI
-  fn return_from_interrupt(&mut self) {
+  fn return_from_interrupt(&mut self, _mode: &AddressingMode) {
     let status = self.stack_pop();
-    self.registers.status = Flags::from_bits(status).expect(&format!("Status {:x} is not valid", status));
+    self.registers.status = Flags::from_bits_truncate(status);
     self.registers.status.remove(Flags::B);
     self.registers.status.insert(Flags::U);
     self.registers.program_counter = self.stack_pop_u16();
@@ -799,8 +914,7 @@ impl CPU {
   /// Other
   /// BIT
   fn test_bits_in_memory_with_accumulator(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.status.set(Flags::Z, self.registers.a & data == 0);
     self.registers.status.set(Flags::N, data & 0x80 == 0x80);
     self.registers.status.set(Flags::V, data & 0x40 == 0x40);
@@ -808,18 +922,16 @@ impl CPU {
 }
 
 /// impl for illegal opcodes and undocumented instructions
-impl CPU {
+impl<M: Memory> CPU<M> {
   fn alr(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = self.registers.a & data;
     // self.registers.set_nz_flags(self.registers.a);
-    self.shift_one_bit_right_accumulator();
+    self.shift_one_bit_right_accumulator(mode);
   }
 
   fn anc(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = self.registers.a & data;
     self.registers.set_nz_flags(self.registers.a);
     self.registers.status.set(Flags::C, self.registers.status.contains(Flags::N));
@@ -831,30 +943,29 @@ impl CPU {
   /// and maybe other factors, as well.
   /// In order to eliminate these uncertainties from the equation,
   /// use either 0 as the operand or a value of $FF in the accumulator.
+  ///
+  /// 这个常量由 `magic_constant` 配置。
   fn ane_xaa(&mut self, mode: &AddressingMode) {
-    self.registers.a = self.registers.a | 0 & self.registers.x;
-
-    self.registers.a = self.registers.x;
-    // self.registers.set_nz_flags(self.registers.a);
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
-    self.registers.a = self.registers.a & data;
+    let data = self.read_operand(mode);
+    self.registers.a = (self.registers.a | self.magic_constant) & self.registers.x & data;
     self.registers.set_nz_flags(self.registers.a);
   }
 
-  // fn arr(&mut self, mode: &AddressingMode) {
-  //   let address = self.get_operand_address(mode);
-  //   let data = self.bus.read(address);
-  //   self.registers.a = self.registers.a & data;
-  //   self.registers.set_nz_flags(self.registers.a);
-  //   self.rotate_one_bit_right_accumulator();
-  // }
+  /// AND 之后循环右移 A，`C` 取结果的第 6 位，`V` 取第 6 位与第 5 位的异或
+  fn arr(&mut self, mode: &AddressingMode) {
+    let data = self.read_operand(mode);
+    let carry = self.registers.status.contains(Flags::C) as u8;
+    self.registers.a = ((self.registers.a & data) >> 1) | (carry << 7);
+    self.registers.set_nz_flags(self.registers.a);
+    self.registers.status.set(Flags::C, self.registers.a & 0x40 == 0x40);
+    self.registers.status.set(Flags::V, ((self.registers.a >> 6) ^ (self.registers.a >> 5)) & 0x01 == 0x01);
+  }
 
   fn dcp_dcm(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+    let (address, _) = self.get_operand_address(mode);
+    let mut data = self.read(address);
     data = data.wrapping_sub(1);
-    self.bus.write(address, data);
+    self.write(address, data);
     // self.registers.set_nz_flags(data);
 
     self.registers.status.set(Flags::C, self.registers.a >= data);
@@ -862,13 +973,12 @@ impl CPU {
   }
 
   fn isc_isb_ins(&mut self, mode: &AddressingMode) {
-    let data = self.increment_memory_by_one(mode);
-    self.registers.add_to_a((data as i8).wrapping_neg().wrapping_sub(1) as u8);
+    let data = self.increment_memory(mode);
+    self.subtract_with_borrow(data);
   }
 
   fn las_lar(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let mut data = self.bus.read(address);
+    let mut data = self.read_operand(mode);
     data = self.registers.stack_pointer & data;
     self.registers.a = data;
     self.registers.x = data;
@@ -876,45 +986,104 @@ impl CPU {
     self.registers.set_nz_flags(data);
   }
 
+  /// 与 ANE 一样受 `magic_constant` 影响：`A = X = (A | CONST) & operand`
+  fn lxa(&mut self, mode: &AddressingMode) {
+    let data = self.read_operand(mode);
+    self.registers.a = (self.registers.a | self.magic_constant) & data;
+    self.registers.x = self.registers.a;
+    self.registers.set_nz_flags(self.registers.a);
+  }
+
   fn lax(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
-    let data = self.bus.read(address);
+    let data = self.read_operand(mode);
     self.registers.a = data;
     self.registers.x = self.registers.a;
     self.registers.set_nz_flags(self.registers.x);
   }
 
   fn rla(&mut self, mode: &AddressingMode) {
-    let data = self.rotate_one_bit_left_memory(mode);
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, data);
+    let data = self.rotate_left_memory(mode);
     self.registers.a = self.registers.a & data;
     self.registers.set_nz_flags(self.registers.a);
   }
 
   fn rra(&mut self, mode: &AddressingMode) {
-    let data = self.rotate_one_bit_right_memory(mode);
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, data);
-    self.registers.add_to_a(data);
+    let data = self.rotate_right_memory(mode);
+    self.add_with_carry(data);
   }
 
   fn sax_axs_aax(&mut self, mode: &AddressingMode) {
-    let address = self.get_operand_address(mode);
+    let (address, _) = self.get_operand_address(mode);
     let data = self.registers.a & self.registers.x;
-    self.bus.write(address, data);
+    self.write(address, data);
+  }
+
+  /// `X = (A & X) - operand`，与 CMP 一样设置 `C`，不受借位影响
+  fn sbx_axs(&mut self, mode: &AddressingMode) {
+    let data = self.read_operand(mode);
+    let value = self.registers.a & self.registers.x;
+    self.registers.status.set(Flags::C, value >= data);
+    self.registers.x = value.wrapping_sub(data);
+    self.registers.set_nz_flags(self.registers.x);
+  }
+
+  /// SHA、SHX、SHY 和 TAS 写入 `value & (基址高字节 + 1)`。
+  /// 索引跨页时，这个值同时替换掉目标地址的高字节。
+  fn store_and_high_byte(&mut self, mode: &AddressingMode, value: u8) {
+    let (address, crossed) = self.get_operand_address(mode);
+    // 跨页时目标地址的高字节已经是基址高字节 + 1
+    let high = (address >> 8) as u8;
+    let high = if crossed { high } else { high.wrapping_add(1) };
+    let data = value & high;
+    let address = if crossed {
+      ((data as u16) << 8) | (address & 0x00FF)
+    } else {
+      address
+    };
+    return self.write(address, data);
+  }
+
+  fn sha_ahx(&mut self, mode: &AddressingMode) {
+    return self.store_and_high_byte(mode, self.registers.a & self.registers.x);
+  }
+
+  fn shx_sxa(&mut self, mode: &AddressingMode) {
+    return self.store_and_high_byte(mode, self.registers.x);
+  }
+
+  fn shy_sya(&mut self, mode: &AddressingMode) {
+    return self.store_and_high_byte(mode, self.registers.y);
+  }
+
+  /// `SP = A & X`，然后像 SHA 一样写入 `SP & (H + 1)`
+  fn tas_shs(&mut self, mode: &AddressingMode) {
+    self.registers.stack_pointer = self.registers.a & self.registers.x;
+    return self.store_and_high_byte(mode, self.registers.stack_pointer);
+  }
+
+  /// JAM (KIL, HLT)：CPU 锁死，只有复位才能恢复。
+  /// PC 停留在这条指令上，方便检查停机时的状态。
+  fn jam(&mut self, _mode: &AddressingMode) {
+    self.jammed = true;
+    self.registers.program_counter = self.current_instruction.0;
   }
 
   fn slo_aso(&mut self, mode: &AddressingMode) {
-    let data = self.shift_left_one_bit_memory(mode);
+    let data = self.shift_left_memory(mode);
     self.registers.a = self.registers.a | data;
     self.registers.set_nz_flags(self.registers.a);
   }
 
+  fn no_operation(&mut self, _mode: &AddressingMode) {
+  }
+
+  /// 带操作数的 NOP 同样会读取内存，绝对 X 寻址跨页时也会多消耗 1 个周期
+  fn nop_read(&mut self, mode: &AddressingMode) {
+    self.read_operand(mode);
+  }
+
   fn sre_lse(&mut self, mode: &AddressingMode) {
-    let data = self.shift_one_bit_right_memory(mode);
-    let address = self.get_operand_address(mode);
-    self.bus.write(address, data);
+    let data = self.shift_right_memory(mode);
     self.registers.a = self.registers.a ^ data;
     self.registers.set_nz_flags(self.registers.a);
   }
@@ -922,35 +1091,443 @@ impl CPU {
 
 #[cfg(test)]
 mod test {
-  // use super::*;
+  use super::*;
+  use crate::bus::Bus;
+  use crate::cartridge::test::test_rom;
+  use crate::memory::flat::FlatMemory;
   // use super::status_flags::*;
 
-  // #[test]
-  // fn test_0xa9_lda_immidiate_load_data() {
-  //   let mut cpu = CPU::new();
-  //   cpu.load_and_run(vec![0xA9, 0x05, 0x00]);
-  //   assert_eq!(cpu.registers.a, 0x05);
-  // }
-
-  // #[test]
-  // fn test_0xa9_lda_zero_flag() {
-  //   let mut cpu = CPU::new();
-  //   cpu.load_and_run(vec![0xA9, 0x00, 0x00]);
-  //   assert!(cpu.registers.status.contains(Flags::Z));
-  // }
-
-  // #[test]
-  // fn test_inx_increment_index_x_by_one() {
-  //   let mut cpu = CPU::new();
-  //   cpu.load_and_run(vec![0xe8, 0xe8, 0x00]);
-  //   assert_eq!(cpu.registers.x, 2);
-  // }
-
-  // #[test]
-  // fn test_5_ops_working_together() {
-  //   let mut cpu = CPU::new();
-  //   cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
-
-  //   assert_eq!(cpu.registers.x, 0xc1);
-  // }
+  /// 把 `program` 写入 `origin` 并将 PC 指向它，测试卡带的中断向量均为 `$0101`
+  fn test_cpu(origin: u16, program: &[u8]) -> CPU<Bus> {
+    let mut bus = Bus::new(test_rom()).unwrap();
+    for (i, byte) in program.iter().enumerate() {
+      bus.write(origin + i as u16, *byte);
+    }
+    let mut cpu = CPU::new(bus);
+    cpu.registers.reset(origin);
+    return cpu;
+  }
+
+  /// 从 `origin` 开始执行 `program`，返回每条指令执行前的周期计数
+  fn run_cycles(origin: u16, program: &[u8], setup: impl FnOnce(&mut CPU<Bus>)) -> Vec<u64> {
+    let mut cpu = test_cpu(origin, program);
+    setup(&mut cpu);
+
+    let mut cycles = vec![];
+    cpu.run_with_callback(|cpu| cycles.push(cpu.cycles));
+    return cycles;
+  }
+
+  #[test]
+  fn test_cycles_page_cross_penalty() {
+    // LDA $02F0,X ; LDA $02F0,X ; STA $02F0,X
+    let program = [0xBD, 0xF0, 0x02, 0xBD, 0xF0, 0x02, 0x9D, 0xF0, 0x02, 0x00];
+    let cycles = run_cycles(0x64, &program, |cpu| cpu.registers.x = 0x20);
+    // 读指令跨页 4 + 1，写指令固定 5
+    assert_eq!(cycles, vec![0, 5, 10, 15]);
+
+    let cycles = run_cycles(0x64, &program, |cpu| cpu.registers.x = 0x01);
+    assert_eq!(cycles, vec![0, 4, 8, 13]);
+  }
+
+  #[test]
+  fn test_cycles_branch_penalty() {
+    // BNE +0 (not taken) ; BEQ +0 (taken) ; NOP
+    let cycles = run_cycles(0x64, &[0xD0, 0x00, 0xF0, 0x00, 0xEA, 0x00], |cpu| {
+      cpu.registers.status.insert(Flags::Z);
+    });
+    assert_eq!(cycles, vec![0, 2, 5, 7]);
+
+    // BEQ +$10 从 $00F2 跨页到 $0102 的 BRK
+    let cycles = run_cycles(0xF0, &[0xF0, 0x10], |cpu| {
+      cpu.registers.status.insert(Flags::Z);
+    });
+    assert_eq!(cycles, vec![0, 4]);
+  }
+
+  #[test]
+  fn test_brk_pushes_return_address_and_status() {
+    let mut cpu = test_cpu(0x64, &[0x00, 0xFF]);
+    cpu.run();
+
+    assert_eq!(cpu.registers.program_counter, 0x0101);
+    assert!(cpu.registers.status.contains(Flags::I));
+    assert_eq!(cpu.registers.stack_pointer, 0xFA);
+    // PC + 2，状态带有 B 和 U
+    assert_eq!(cpu.bus.read_u16(0x01FC), 0x0066);
+    assert_eq!(cpu.bus.read(0x01FB), 0x34);
+    assert_eq!(cpu.cycles, 7);
+  }
+
+  #[test]
+  fn test_nmi_is_serviced_before_next_instruction() {
+    // $0101 处是 BRK，用来结束运行
+    let mut cpu = test_cpu(0x64, &[0xEA]);
+    cpu.registers.status.insert(Flags::I);
+    cpu.trigger_nmi();
+
+    let mut pcs = vec![];
+    cpu.run_with_callback(|cpu| pcs.push(cpu.registers.program_counter));
+
+    assert_eq!(pcs, vec![0x0101]);
+    assert_eq!(cpu.bus.read_u16(0x01FC), 0x0064);
+    // 硬件中断压栈的状态不带 B
+    assert_eq!(cpu.bus.read(0x01FB), 0x24);
+  }
+
+  #[test]
+  fn test_irq_is_level_triggered_and_masked_by_i() {
+    // CLI ; NOP
+    let mut cpu = test_cpu(0x64, &[0x58, 0xEA]);
+    cpu.registers.status.insert(Flags::I);
+    cpu.set_irq(IrqSource::MAPPER, true);
+    cpu.set_irq(IrqSource::APU_FRAME_COUNTER, true);
+    cpu.set_irq(IrqSource::MAPPER, false);
+    assert!(cpu.irq_asserted());
+
+    let mut pcs = vec![];
+    cpu.run_with_callback(|cpu| {
+      pcs.push(cpu.registers.program_counter);
+      // 进入中断服务程序后设备才释放 IRQ 线，避免重复进入
+      if cpu.registers.program_counter == 0x0101 {
+        cpu.set_irq(IrqSource::APU_FRAME_COUNTER, false);
+      }
+    });
+
+    // CLI 之前 IRQ 被屏蔽，CLI 之后立即响应
+    assert_eq!(pcs, vec![0x0064, 0x0101]);
+    assert_eq!(cpu.bus.read_u16(0x01FC), 0x0065);
+  }
+
+  #[test]
+  fn test_step_executes_one_instruction() {
+    // LDX #$01 ; DEX
+    let mut cpu = test_cpu(0x64, &[0xA2, 0x01, 0xCA]);
+
+    let result = cpu.step();
+    assert_eq!(result.cycles, 2);
+    assert_eq!(result.instructions, 1);
+    assert_eq!(result.stop_reason, StopReason::Stepped);
+    assert_eq!(
+      result.last_instruction,
+      Some(Instruction { address: 0x64, opcode: 0xA2, mnemonic: "LDX" })
+    );
+    assert_eq!(cpu.registers.x, 1);
+    assert_eq!(cpu.registers.program_counter, 0x66);
+
+    cpu.trigger_nmi();
+    // 中断的 7 个周期 + $0101 处 BRK 的 7 个周期
+    let result = cpu.step();
+    assert_eq!(result.cycles, 14);
+    assert_eq!(result.last_instruction.map(|i| i.address), Some(0x0101));
+  }
+
+  #[test]
+  fn test_run_for_cycles_stops_on_instruction_boundary() {
+    // loop: INX ; JMP loop
+    let mut cpu = test_cpu(0x64, &[0xE8, 0x4C, 0x64, 0x00]);
+
+    let result = cpu.run_for_cycles(10);
+    assert_eq!(result.stop_reason, StopReason::BudgetExhausted);
+    // INX(2) JMP(3) INX(2) JMP(3)
+    assert_eq!(result.cycles, 10);
+    assert_eq!(result.instructions, 4);
+    assert_eq!(cpu.registers.x, 2);
+
+    let result = cpu.run_for_cycles(1);
+    assert_eq!(result.cycles, 2);
+    assert_eq!(result.last_instruction.map(|i| i.mnemonic), Some("INX"));
+  }
+
+  #[test]
+  fn test_run_frame() {
+    let mut cpu = test_cpu(0x64, &[0xE8, 0x4C, 0x64, 0x00]);
+
+    // 停在 PPU 进入 vblank 之后
+    let result = cpu.run_frame();
+    assert_eq!(result.stop_reason, StopReason::FrameComplete);
+    assert_eq!(cpu.bus.ppu.frame_count(), 1);
+    assert_ne!(cpu.bus.ppu.peek_register(0x2002) & 0x80, 0);
+    assert!(cpu.cycles * 3 < PPU_DOTS_PER_FRAME);
+
+    let first = cpu.cycles;
+    cpu.run_frame();
+    assert_eq!(cpu.bus.ppu.frame_count(), 2);
+    assert!((cpu.cycles - first) * 3 <= PPU_DOTS_PER_FRAME + 9);
+    assert!((cpu.cycles - first) * 3 >= PPU_DOTS_PER_FRAME - 9);
+
+    // 没有 PPU 时按 PPU 时钟划分
+    let mut memory = FlatMemory::new();
+    memory.load(0x0600, &[0xE8, 0x4C, 0x00, 0x06]);
+    let mut cpu = CPU::new(memory);
+    cpu.registers.reset(0x0600);
+    cpu.run_frame();
+    assert!(cpu.cycles * 3 >= PPU_DOTS_PER_FRAME);
+    assert!((cpu.cycles - 3) * 3 < PPU_DOTS_PER_FRAME);
+  }
+
+  #[test]
+  fn test_decimal_mode_depends_on_variant() {
+    // SED ; CLC ; LDA #$09 ; ADC #$01
+    let program = [0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01, 0x00];
+
+    let mut cpu = test_cpu(0x64, &program);
+    cpu.run();
+    assert_eq!(cpu.registers.a, 0x0A);
+
+    let mut cpu = test_cpu(0x64, &program);
+    cpu.variant = Variant::Nmos6502;
+    cpu.run();
+    assert_eq!(cpu.registers.a, 0x10);
+  }
+
+  #[test]
+  fn test_jam_halts_cpu_until_reset() {
+    // NOP ; JAM
+    let mut cpu = test_cpu(0x64, &[0xEA, 0x02]);
+
+    let result = cpu.run_for_cycles(100);
+    assert_eq!(result.stop_reason, StopReason::Jammed);
+    assert_eq!(result.instructions, 2);
+    assert_eq!(result.last_instruction.map(|i| i.mnemonic), Some("*JAM"));
+    assert!(cpu.is_jammed());
+    assert_eq!(cpu.registers.program_counter, 0x65);
+
+    // 停机后中断也不会被响应
+    cpu.trigger_nmi();
+    let result = cpu.step();
+    assert_eq!(result.stop_reason, StopReason::Jammed);
+    assert_eq!(result.cycles, 0);
+    assert_eq!(cpu.registers.program_counter, 0x65);
+
+    cpu.reset();
+    assert!(!cpu.is_jammed());
+  }
+
+  #[test]
+  fn test_arr_sets_carry_and_overflow_from_result() {
+    // ARR #$FF
+    let mut cpu = test_cpu(0x64, &[0x6B, 0xFF]);
+    cpu.registers.a = 0xC0;
+    cpu.registers.status.insert(Flags::C);
+    cpu.step();
+
+    // (0xC0 >> 1) | 0x80 = 0xE0，第 6 位为 1，第 5 位为 1
+    assert_eq!(cpu.registers.a, 0xE0);
+    assert!(cpu.registers.status.contains(Flags::C));
+    assert!(!cpu.registers.status.contains(Flags::V));
+    assert!(cpu.registers.status.contains(Flags::N));
+
+    let mut cpu = test_cpu(0x64, &[0x6B, 0xFF]);
+    cpu.registers.a = 0x80;
+    cpu.step();
+    assert_eq!(cpu.registers.a, 0x40);
+    assert!(cpu.registers.status.contains(Flags::C));
+    assert!(cpu.registers.status.contains(Flags::V));
+  }
+
+  #[test]
+  fn test_sbx_subtracts_without_borrow() {
+    // SBX #$05
+    let mut cpu = test_cpu(0x64, &[0xCB, 0x05]);
+    cpu.registers.a = 0x0F;
+    cpu.registers.x = 0x3C;
+    cpu.step();
+
+    assert_eq!(cpu.registers.x, 0x07);
+    assert!(cpu.registers.status.contains(Flags::C));
+    assert_eq!(cpu.registers.a, 0x0F);
+  }
+
+  #[test]
+  fn test_magic_constant_is_configurable() {
+    // LXA #$F0 ; ANE #$FF
+    let program = [0xAB, 0xF0, 0x8B, 0xFF];
+    let mut cpu = test_cpu(0x64, &program);
+    cpu.registers.a = 0x01;
+    cpu.step();
+    assert_eq!(cpu.registers.a, 0xE0);
+    assert_eq!(cpu.registers.x, 0xE0);
+
+    let mut cpu = test_cpu(0x64, &program);
+    cpu.magic_constant = 0xFF;
+    cpu.registers.a = 0x01;
+    cpu.step();
+    assert_eq!(cpu.registers.x, 0xF0);
+    cpu.registers.x = 0x3C;
+    cpu.step();
+    assert_eq!(cpu.registers.a, 0x3C);
+  }
+
+  #[test]
+  fn test_shx_ands_with_high_byte_and_corrupts_address_on_page_cross() {
+    // SHX $0110,Y
+    let mut cpu = test_cpu(0x64, &[0x9E, 0x10, 0x01]);
+    cpu.registers.x = 0xFF;
+    cpu.registers.y = 0x01;
+    cpu.step();
+    assert_eq!(cpu.bus.read(0x0111), 0x02);
+
+    // SHX $03F0,Y 跨页，写入的值 $01 & $04 = $00 同时成为地址的高字节
+    let mut cpu = test_cpu(0x64, &[0x9E, 0xF0, 0x03]);
+    cpu.bus.write(0x0010, 0xFF);
+    cpu.bus.write(0x0410, 0xFF);
+    cpu.registers.x = 0x01;
+    cpu.registers.y = 0x20;
+    cpu.step();
+    assert_eq!(cpu.bus.read(0x0010), 0x00);
+    assert_eq!(cpu.bus.read(0x0410), 0xFF);
+  }
+
+  #[test]
+  fn test_tas_sets_stack_pointer() {
+    // TAS $0100,Y
+    let mut cpu = test_cpu(0x64, &[0x9B, 0x00, 0x01]);
+    cpu.registers.a = 0xF3;
+    cpu.registers.x = 0x3F;
+    cpu.registers.y = 0x10;
+    cpu.step();
+
+    assert_eq!(cpu.registers.stack_pointer, 0x33);
+    assert_eq!(cpu.bus.read(0x0110), 0x02);
+  }
+
+  /// 记录每一次写入的内存
+  struct CountingMemory {
+    memory: FlatMemory,
+    writes: Vec<(u16, u8)>,
+  }
+
+  impl Memory for CountingMemory {
+    fn peek(&self, address: u16) -> u8 {
+      return self.memory.peek(address);
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+      self.writes.push((address, data));
+      return self.memory.write(address, data);
+    }
+  }
+
+  #[test]
+  fn test_illegal_read_modify_write_writes_once() {
+    // SLO、RLA、SRE、RRA、ISC 的零页寻址，操作数 $10 = $81
+    for (opcode, result) in [(0x07, 0x02), (0x27, 0x02), (0x47, 0x40), (0x67, 0x40), (0xE7, 0x82)] {
+      let mut memory = FlatMemory::new();
+      memory.load(0x0600, &[opcode, 0x10, 0x02]);
+      memory.load(0x0010, &[0x81]);
+
+      let mut cpu = CPU::new(CountingMemory { memory, writes: vec![] });
+      cpu.registers.reset(0x0600);
+      cpu.run_for_cycles(100);
+
+      assert_eq!(cpu.bus.writes, vec![(0x0010, result)], "opcode ${:02X}", opcode);
+    }
+  }
+
+  #[test]
+  fn test_0xa9_lda_immidiate_load_data() {
+    let mut cpu = CPU::new(FlatMemory::new());
+    cpu.load_and_run(vec![0xA9, 0x05, 0x00]);
+    assert_eq!(cpu.registers.a, 0x05);
+  }
+
+  #[test]
+  fn test_0xa9_lda_zero_flag() {
+    let mut cpu = CPU::new(FlatMemory::new());
+    cpu.load_and_run(vec![0xA9, 0x00, 0x00]);
+    assert!(cpu.registers.status.contains(Flags::Z));
+  }
+
+  #[test]
+  fn test_inx_increment_index_x_by_one() {
+    let mut cpu = CPU::new(FlatMemory::new());
+    cpu.load_and_run(vec![0xe8, 0xe8, 0x00]);
+    assert_eq!(cpu.registers.x, 2);
+  }
+
+  #[test]
+  fn test_5_ops_working_together() {
+    let mut cpu = CPU::new(FlatMemory::new());
+    cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
+
+    assert_eq!(cpu.registers.x, 0xc1);
+  }
+
+  #[test]
+  fn test_load_at_sets_reset_vector() {
+    // 程序跨过 $8000，在 NES 总线上这是卡带 ROM
+    let mut cpu = CPU::new(FlatMemory::new());
+    cpu.load_at(0x7FFE, &[0xA9, 0x42, 0xAA, 0x00]);
+    cpu.reset();
+    assert_eq!(cpu.registers.program_counter, 0x7FFE);
+
+    cpu.run();
+    assert_eq!(cpu.registers.x, 0x42);
+  }
+
+  #[test]
+  fn test_oam_dma_stalls_cpu() {
+    // LDA #$02; STA $4014，DMA 在第 6 个周期（偶数）开始
+    let mut cpu = test_cpu(0x0600, &[0xA9, 0x02, 0x8D, 0x14, 0x40]);
+    cpu.step();
+    let result = cpu.step();
+    assert_eq!(result.cycles, 4 + 513);
+    assert_eq!(cpu.cycles, 2 + 4 + 513);
+    assert_eq!(cpu.bus.ppu.dot as u64 + cpu.bus.ppu.scanline as u64 * 341, cpu.cycles * 3);
+
+    // LDA $00; STA $4014，DMA 在第 7 个周期（奇数）开始，多等 1 个周期
+    let mut cpu = test_cpu(0x0600, &[0xA5, 0x00, 0x8D, 0x14, 0x40]);
+    cpu.step();
+    assert_eq!(cpu.step().cycles, 4 + 514);
+  }
+
+  #[test]
+  fn test_ppu_sees_register_read_on_last_cycle() {
+    // LDA $2002，读取发生在第 4 个周期，即 9 个 PPU 时钟之后
+    let mut cpu = test_cpu(0x0600, &[0xAD, 0x02, 0x20]);
+    (cpu.bus.ppu.scanline, cpu.bus.ppu.dot) = (240, 334);
+    cpu.step();
+    assert_eq!(cpu.registers.a & 0x80, 0x80);
+    assert_eq!((cpu.bus.ppu.scanline, cpu.bus.ppu.dot), (241, 5));
+
+    // 早一个时钟读取，读到的标志是 0，并且本帧不再设置
+    let mut cpu = test_cpu(0x0600, &[0xAD, 0x02, 0x20]);
+    (cpu.bus.ppu.scanline, cpu.bus.ppu.dot) = (240, 333);
+    cpu.step();
+    assert_eq!(cpu.registers.a & 0x80, 0);
+    assert_eq!(cpu.bus.ppu.peek_register(0x2002) & 0x80, 0);
+  }
+
+  #[test]
+  fn test_run_raw_image_with_custom_vectors() {
+    let mut memory = FlatMemory::new();
+    // $C000: LDX #$00 ; loop: INX ; CPX #$05 ; BNE loop ; JAM
+    memory.load(0xC000, &[0xA2, 0x00, 0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0x02]);
+    memory.set_reset_vector(0xC000);
+
+    let mut cpu = CPU::new(memory);
+    cpu.reset();
+    let result = cpu.run_for_cycles(1000);
+
+    assert_eq!(result.stop_reason, StopReason::Jammed);
+    assert_eq!(cpu.registers.x, 0x05);
+    assert_eq!(cpu.registers.program_counter, 0xC007);
+  }
+
+  #[test]
+  fn test_program_counter_wraps_around_address_space() {
+    for origin in [0xFFFE, 0xFFFF] {
+      let mut memory = FlatMemory::new();
+      // LDX #$05 ; INX ; JAM，操作数或者下一条指令回绕到 $0000
+      memory.load(origin, &[0xA2, 0x05, 0xE8, 0x02]);
+
+      let mut cpu = CPU::new(memory);
+      cpu.registers.reset(origin);
+      let result = cpu.run_for_cycles(100);
+
+      assert_eq!(result.stop_reason, StopReason::Jammed);
+      assert_eq!(cpu.registers.x, 0x06);
+    }
+  }
 }
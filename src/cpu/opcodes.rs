@@ -1,300 +1,344 @@
-use lazy_static::lazy_static;
-use std::collections::HashMap;
 use super::addressing_mode::AddressingMode;
+use super::CPU;
+use crate::memory::Memory;
 
-pub struct Opcode {
+/// 指令的执行函数，所有指令共用同一个签名，方便直接放进查找表里
+pub type Handler<M> = fn(&mut CPU<M>, &AddressingMode);
+
+pub struct Opcode<M: Memory> {
   pub code: u8,
   pub mnemonic: &'static str,
   pub length: u8,
   pub cycles: u8,
   pub mode: AddressingMode,
+  pub handler: Handler<M>,
+}
+
+// 手动实现，`derive` 会要求 `M` 本身也是 `Copy`
+impl<M: Memory> Clone for Opcode<M> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<M: Memory> Copy for Opcode<M> {}
+
+impl<M: Memory> Opcode<M> {
+  const fn new(code: u8, mnemonic: &'static str, length: u8, cycles: u8, mode: AddressingMode, handler: Handler<M>) -> Self {
+    return Opcode { code, mnemonic, length, cycles, mode, handler };
+  }
 }
 
-impl Opcode {
-  fn new(code: u8, mnemonic: &'static str, length: u8, cycles: u8, mode: AddressingMode) -> Self {
-    return Opcode { code, mnemonic, length, cycles, mode };
+/// 构造以操作码为下标的指令表，6502 的 256 个操作码全部都有定义。
+///
+/// 执行函数依赖具体的内存类型，所以每种 `M` 各有一张表，见 `CPU::OPCODES`。
+pub const fn build_opcode_table<M: Memory>() -> [Opcode<M>; 256] {
+  let mut table = defined_opcodes::<M>();
+  // 按操作码排序，排好之后每个位置的操作码必须等于下标，重复或者遗漏的定义在编译时报错
+  let mut index = 0;
+  while index < 256 {
+    let mut smallest = index;
+    let mut other = index + 1;
+    while other < 256 {
+      if table[other].code < table[smallest].code {
+        smallest = other;
+      }
+      other += 1;
+    }
+    let opcode = table[smallest];
+    table[smallest] = table[index];
+    table[index] = opcode;
+    if opcode.code as usize != index {
+      panic!("opcode table must define every opcode exactly once");
+    }
+    index += 1;
   }
+  return table;
 }
 
-lazy_static! {
-  pub static ref CPU_OPCODES: Vec<Opcode> = vec![
+const fn defined_opcodes<M: Memory>() -> [Opcode<M>; 256] {
+  return [
     // Transfer Instructions
-    Opcode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBD, "LDA", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0xB9, "LDA", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xA1, "LDA", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0xB1, "LDA", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBE, "LDX", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBC, "LDY", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX),
-    Opcode::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY),
-    Opcode::new(0x81, "STA", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0x91, "STA", 2, 6, AddressingMode::IndirectIndexed),
-    Opcode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xAA, "TAX", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xA8, "TAY", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xBA, "TSX", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x8A, "TXA", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x9A, "TXS", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x98, "TYA", 1, 2, AddressingMode::Implicit),
+    Opcode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate, CPU::load_accumulator_with_memory),
+    Opcode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage, CPU::load_accumulator_with_memory),
+    Opcode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX, CPU::load_accumulator_with_memory),
+    Opcode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute, CPU::load_accumulator_with_memory),
+    Opcode::new(0xBD, "LDA", 3, 4, AddressingMode::AbsoluteX, CPU::load_accumulator_with_memory),
+    Opcode::new(0xB9, "LDA", 3, 4, AddressingMode::AbsoluteY, CPU::load_accumulator_with_memory),
+    Opcode::new(0xA1, "LDA", 2, 6, AddressingMode::IndexedIndirect, CPU::load_accumulator_with_memory),
+    Opcode::new(0xB1, "LDA", 2, 5, AddressingMode::IndirectIndexed, CPU::load_accumulator_with_memory),
+    Opcode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate, CPU::load_index_x_with_memory),
+    Opcode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage, CPU::load_index_x_with_memory),
+    Opcode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY, CPU::load_index_x_with_memory),
+    Opcode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute, CPU::load_index_x_with_memory),
+    Opcode::new(0xBE, "LDX", 3, 4, AddressingMode::AbsoluteY, CPU::load_index_x_with_memory),
+    Opcode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate, CPU::load_index_y_with_memory),
+    Opcode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage, CPU::load_index_y_with_memory),
+    Opcode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX, CPU::load_index_y_with_memory),
+    Opcode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute, CPU::load_index_y_with_memory),
+    Opcode::new(0xBC, "LDY", 3, 4, AddressingMode::AbsoluteX, CPU::load_index_y_with_memory),
+    Opcode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage, CPU::store_accumulator_in_memory),
+    Opcode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX, CPU::store_accumulator_in_memory),
+    Opcode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute, CPU::store_accumulator_in_memory),
+    Opcode::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX, CPU::store_accumulator_in_memory),
+    Opcode::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY, CPU::store_accumulator_in_memory),
+    Opcode::new(0x81, "STA", 2, 6, AddressingMode::IndexedIndirect, CPU::store_accumulator_in_memory),
+    Opcode::new(0x91, "STA", 2, 6, AddressingMode::IndirectIndexed, CPU::store_accumulator_in_memory),
+    Opcode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage, CPU::store_index_x_in_memory),
+    Opcode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY, CPU::store_index_x_in_memory),
+    Opcode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute, CPU::store_index_x_in_memory),
+    Opcode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage, CPU::store_index_y_in_memory),
+    Opcode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX, CPU::store_index_y_in_memory),
+    Opcode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute, CPU::store_index_y_in_memory),
+    Opcode::new(0xAA, "TAX", 1, 2, AddressingMode::Implicit, CPU::transfer_accumulator_to_index_x),
+    Opcode::new(0xA8, "TAY", 1, 2, AddressingMode::Implicit, CPU::transfer_accumulator_to_index_y),
+    Opcode::new(0xBA, "TSX", 1, 2, AddressingMode::Implicit, CPU::transfer_stack_pointer_to_index_x),
+    Opcode::new(0x8A, "TXA", 1, 2, AddressingMode::Implicit, CPU::transfer_index_x_to_accumulator),
+    Opcode::new(0x9A, "TXS", 1, 2, AddressingMode::Implicit, CPU::transfer_index_x_to_stack_register),
+    Opcode::new(0x98, "TYA", 1, 2, AddressingMode::Implicit, CPU::transfer_index_y_to_accumulator),
 
     // Stack Instructions
-    Opcode::new(0x48, "PHA", 1, 3, AddressingMode::Implicit),
-    Opcode::new(0x68, "PLA", 1, 4, AddressingMode::Implicit),
-    Opcode::new(0x08, "PHP", 1, 3, AddressingMode::Implicit),
-    Opcode::new(0x28, "PLP", 1, 4, AddressingMode::Implicit),
+    Opcode::new(0x48, "PHA", 1, 3, AddressingMode::Implicit, CPU::push_accumulator_on_stack),
+    Opcode::new(0x68, "PLA", 1, 4, AddressingMode::Implicit, CPU::pull_accumulator_from_stack),
+    Opcode::new(0x08, "PHP", 1, 3, AddressingMode::Implicit, CPU::push_processor_status_on_stack),
+    Opcode::new(0x28, "PLP", 1, 4, AddressingMode::Implicit, CPU::pull_processor_status_from_stack),
 
     // Decrements & Increments
-    Opcode::new(0xC6, "DEC", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0xD6, "DEC", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0xCE, "DEC", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0xDE, "DEC", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0xCA, "DEX", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x88, "DEY", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xE6, "INC", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0xF6, "INC", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0xEE, "INC", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0xFE, "INC", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0xE8, "INX", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xC8, "INY", 1, 2, AddressingMode::Implicit),
+    Opcode::new(0xC6, "DEC", 2, 5, AddressingMode::ZeroPage, CPU::decrement_memory_by_one),
+    Opcode::new(0xD6, "DEC", 2, 6, AddressingMode::ZeroPageX, CPU::decrement_memory_by_one),
+    Opcode::new(0xCE, "DEC", 3, 6, AddressingMode::Absolute, CPU::decrement_memory_by_one),
+    Opcode::new(0xDE, "DEC", 3, 7, AddressingMode::AbsoluteX, CPU::decrement_memory_by_one),
+    Opcode::new(0xCA, "DEX", 1, 2, AddressingMode::Implicit, CPU::decrement_index_x_by_one),
+    Opcode::new(0x88, "DEY", 1, 2, AddressingMode::Implicit, CPU::decrement_index_y_by_one),
+    Opcode::new(0xE6, "INC", 2, 5, AddressingMode::ZeroPage, CPU::increment_memory_by_one),
+    Opcode::new(0xF6, "INC", 2, 6, AddressingMode::ZeroPageX, CPU::increment_memory_by_one),
+    Opcode::new(0xEE, "INC", 3, 6, AddressingMode::Absolute, CPU::increment_memory_by_one),
+    Opcode::new(0xFE, "INC", 3, 7, AddressingMode::AbsoluteX, CPU::increment_memory_by_one),
+    Opcode::new(0xE8, "INX", 1, 2, AddressingMode::Implicit, CPU::increment_index_x_by_one),
+    Opcode::new(0xC8, "INY", 1, 2, AddressingMode::Implicit, CPU::increment_index_y_by_one),
 
     // Arithmetic Operations
-    Opcode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x7D, "ADC", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x79, "ADC", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0x61, "ADC", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0x71, "ADC", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xFD, "SBC", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0xF9, "SBC", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xE1, "SBC", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0xF1, "SBC", 2, 5, AddressingMode::IndirectIndexed),
+    Opcode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x7D, "ADC", 3, 4, AddressingMode::AbsoluteX, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x79, "ADC", 3, 4, AddressingMode::AbsoluteY, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x61, "ADC", 2, 6, AddressingMode::IndexedIndirect, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0x71, "ADC", 2, 5, AddressingMode::IndirectIndexed, CPU::add_memory_to_accumulator_with_carry),
+    Opcode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xFD, "SBC", 3, 4, AddressingMode::AbsoluteX, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xF9, "SBC", 3, 4, AddressingMode::AbsoluteY, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xE1, "SBC", 2, 6, AddressingMode::IndexedIndirect, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0xF1, "SBC", 2, 5, AddressingMode::IndirectIndexed, CPU::subtract_memory_from_accumulator_with_borrow),
 
     // Logical Operations
-    Opcode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x3D, "AND", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x39, "AND", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0x21, "AND", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0x31, "AND", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x4D, "EOR", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x5D, "EOR", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x59, "EOR", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0x41, "EOR", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0x51, "EOR", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x0D, "ORA", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x1D, "ORA", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x19, "ORA", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0x01, "ORA", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0x11, "ORA", 2, 5, AddressingMode::IndirectIndexed),
+    Opcode::new(0x29, "AND", 2, 2, AddressingMode::Immediate, CPU::and_memory_with_accumulator),
+    Opcode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage, CPU::and_memory_with_accumulator),
+    Opcode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX, CPU::and_memory_with_accumulator),
+    Opcode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute, CPU::and_memory_with_accumulator),
+    Opcode::new(0x3D, "AND", 3, 4, AddressingMode::AbsoluteX, CPU::and_memory_with_accumulator),
+    Opcode::new(0x39, "AND", 3, 4, AddressingMode::AbsoluteY, CPU::and_memory_with_accumulator),
+    Opcode::new(0x21, "AND", 2, 6, AddressingMode::IndexedIndirect, CPU::and_memory_with_accumulator),
+    Opcode::new(0x31, "AND", 2, 5, AddressingMode::IndirectIndexed, CPU::and_memory_with_accumulator),
+    Opcode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPageX, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x4D, "EOR", 3, 4, AddressingMode::Absolute, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x5D, "EOR", 3, 4, AddressingMode::AbsoluteX, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x59, "EOR", 3, 4, AddressingMode::AbsoluteY, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x41, "EOR", 2, 6, AddressingMode::IndexedIndirect, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x51, "EOR", 2, 5, AddressingMode::IndirectIndexed, CPU::exclusive_or_memory_with_accumulator),
+    Opcode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate, CPU::or_memory_with_accumulator),
+    Opcode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage, CPU::or_memory_with_accumulator),
+    Opcode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPageX, CPU::or_memory_with_accumulator),
+    Opcode::new(0x0D, "ORA", 3, 4, AddressingMode::Absolute, CPU::or_memory_with_accumulator),
+    Opcode::new(0x1D, "ORA", 3, 4, AddressingMode::AbsoluteX, CPU::or_memory_with_accumulator),
+    Opcode::new(0x19, "ORA", 3, 4, AddressingMode::AbsoluteY, CPU::or_memory_with_accumulator),
+    Opcode::new(0x01, "ORA", 2, 6, AddressingMode::IndexedIndirect, CPU::or_memory_with_accumulator),
+    Opcode::new(0x11, "ORA", 2, 5, AddressingMode::IndirectIndexed, CPU::or_memory_with_accumulator),
 
     // Shift & Rotate Instructions
-    Opcode::new(0x0A, "ASL", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x1E, "ASL", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x4A, "LSR", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x4E, "LSR", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x5E, "LSR", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x2A, "ROL", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x2E, "ROL", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x3E, "ROL", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x6A, "ROR", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x6E, "ROR", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x7E, "ROR", 3, 7, AddressingMode::AbsoluteX),
+    Opcode::new(0x0A, "ASL", 1, 2, AddressingMode::Implicit, CPU::shift_left_one_bit_accumulator),
+    Opcode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage, CPU::shift_left_one_bit_memory),
+    Opcode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX, CPU::shift_left_one_bit_memory),
+    Opcode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute, CPU::shift_left_one_bit_memory),
+    Opcode::new(0x1E, "ASL", 3, 7, AddressingMode::AbsoluteX, CPU::shift_left_one_bit_memory),
+    Opcode::new(0x4A, "LSR", 1, 2, AddressingMode::Implicit, CPU::shift_one_bit_right_accumulator),
+    Opcode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage, CPU::shift_one_bit_right_memory),
+    Opcode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPageX, CPU::shift_one_bit_right_memory),
+    Opcode::new(0x4E, "LSR", 3, 6, AddressingMode::Absolute, CPU::shift_one_bit_right_memory),
+    Opcode::new(0x5E, "LSR", 3, 7, AddressingMode::AbsoluteX, CPU::shift_one_bit_right_memory),
+    Opcode::new(0x2A, "ROL", 1, 2, AddressingMode::Implicit, CPU::rotate_one_bit_left_accumulator),
+    Opcode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage, CPU::rotate_one_bit_left_memory),
+    Opcode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPageX, CPU::rotate_one_bit_left_memory),
+    Opcode::new(0x2E, "ROL", 3, 6, AddressingMode::Absolute, CPU::rotate_one_bit_left_memory),
+    Opcode::new(0x3E, "ROL", 3, 7, AddressingMode::AbsoluteX, CPU::rotate_one_bit_left_memory),
+    Opcode::new(0x6A, "ROR", 1, 2, AddressingMode::Implicit, CPU::rotate_one_bit_right_accumulator),
+    Opcode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage, CPU::rotate_one_bit_right_memory),
+    Opcode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPageX, CPU::rotate_one_bit_right_memory),
+    Opcode::new(0x6E, "ROR", 3, 6, AddressingMode::Absolute, CPU::rotate_one_bit_right_memory),
+    Opcode::new(0x7E, "ROR", 3, 7, AddressingMode::AbsoluteX, CPU::rotate_one_bit_right_memory),
 
     // Flag Instructions
-    Opcode::new(0x18, "CLC", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xD8, "CLD", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x58, "CLI", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xB8, "CLV", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x38, "SEC", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xF8, "SED", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x78, "SEI", 1, 2, AddressingMode::Implicit),
+    Opcode::new(0x18, "CLC", 1, 2, AddressingMode::Implicit, CPU::clear_carry_flag),
+    Opcode::new(0xD8, "CLD", 1, 2, AddressingMode::Implicit, CPU::clear_decimal_mode),
+    Opcode::new(0x58, "CLI", 1, 2, AddressingMode::Implicit, CPU::clear_interrupt_disable_bit),
+    Opcode::new(0xB8, "CLV", 1, 2, AddressingMode::Implicit, CPU::clear_overflow_flag),
+    Opcode::new(0x38, "SEC", 1, 2, AddressingMode::Implicit, CPU::set_carry_flag),
+    Opcode::new(0xF8, "SED", 1, 2, AddressingMode::Implicit, CPU::set_decimal_mode),
+    Opcode::new(0x78, "SEI", 1, 2, AddressingMode::Implicit, CPU::set_interrupt_disable_bit),
 
     // Comparisons
-    Opcode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xDD, "CMP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0xD9, "CMP", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xC1, "CMP", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0xD1, "CMP", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xC0, "CPY", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xC4, "CPY", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xCC, "CPY", 3, 4, AddressingMode::Absolute),
+    Opcode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xDD, "CMP", 3, 4, AddressingMode::AbsoluteX, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xD9, "CMP", 3, 4, AddressingMode::AbsoluteY, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xC1, "CMP", 2, 6, AddressingMode::IndexedIndirect, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xD1, "CMP", 2, 5, AddressingMode::IndirectIndexed, CPU::compare_memory_with_accumulator),
+    Opcode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate, CPU::compare_memory_and_index_x),
+    Opcode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage, CPU::compare_memory_and_index_x),
+    Opcode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute, CPU::compare_memory_and_index_x),
+    Opcode::new(0xC0, "CPY", 2, 2, AddressingMode::Immediate, CPU::compare_memory_and_index_y),
+    Opcode::new(0xC4, "CPY", 2, 3, AddressingMode::ZeroPage, CPU::compare_memory_and_index_y),
+    Opcode::new(0xCC, "CPY", 3, 4, AddressingMode::Absolute, CPU::compare_memory_and_index_y),
 
     // Conditional Branch Instructions
-    Opcode::new(0x90, "BCC", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0xB0, "BCS", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0xF0, "BEQ", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0x30, "BMI", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0xD0, "BNE", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0x10, "BPL", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0x50, "BVC", 2, 2, AddressingMode::Implicit),
-    Opcode::new(0x70, "BVS", 2, 2, AddressingMode::Implicit),
+    Opcode::new(0x90, "BCC", 2, 2, AddressingMode::Implicit, CPU::branch_on_carry_clear),
+    Opcode::new(0xB0, "BCS", 2, 2, AddressingMode::Implicit, CPU::branch_on_carry_set),
+    Opcode::new(0xF0, "BEQ", 2, 2, AddressingMode::Implicit, CPU::branch_on_result_zero),
+    Opcode::new(0x30, "BMI", 2, 2, AddressingMode::Implicit, CPU::branch_on_result_minus),
+    Opcode::new(0xD0, "BNE", 2, 2, AddressingMode::Implicit, CPU::branch_on_result_not_zero),
+    Opcode::new(0x10, "BPL", 2, 2, AddressingMode::Implicit, CPU::branch_on_result_plus),
+    Opcode::new(0x50, "BVC", 2, 2, AddressingMode::Implicit, CPU::branch_on_overflow_clear),
+    Opcode::new(0x70, "BVS", 2, 2, AddressingMode::Implicit, CPU::branch_on_overflow_set),
 
     // Jumps & Subroutines
     // TODO: AddressingMode that acts as Immidiate
-    Opcode::new(0x4C, "JMP", 3, 5, AddressingMode::Absolute),
-    Opcode::new(0x6C, "JMP", 3, 5, AddressingMode::Indirect),
-    Opcode::new(0x20, "JSR", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x60, "RTS", 1, 6, AddressingMode::Implicit),
+    Opcode::new(0x4C, "JMP", 3, 3, AddressingMode::Absolute, CPU::jump_to_new_location),
+    Opcode::new(0x6C, "JMP", 3, 5, AddressingMode::Indirect, CPU::jump_to_new_location),
+    Opcode::new(0x20, "JSR", 3, 6, AddressingMode::Absolute, CPU::jump_to_new_location_saving_return_address),
+    Opcode::new(0x60, "RTS", 1, 6, AddressingMode::Implicit, CPU::return_from_subroutine),
 
     // Interrupts
-    Opcode::new(0x00, "BRK", 1, 7, AddressingMode::Implicit),
-    Opcode::new(0x40, "RTI", 1, 6, AddressingMode::Implicit),
+    Opcode::new(0x00, "BRK", 1, 7, AddressingMode::Implicit, CPU::force_break),
+    Opcode::new(0x40, "RTI", 1, 6, AddressingMode::Implicit, CPU::return_from_interrupt),
 
     // Other
-    Opcode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x2C, "BIT", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xEA, "NOP", 1, 2, AddressingMode::Implicit),
+    Opcode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage, CPU::test_bits_in_memory_with_accumulator),
+    Opcode::new(0x2C, "BIT", 3, 4, AddressingMode::Absolute, CPU::test_bits_in_memory_with_accumulator),
+    Opcode::new(0xEA, "NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
 
     // "Illegal" Opcodes and Undocumented Instructions
-    Opcode::new(0x4B, "*ALR", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x0B, "*ANC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x2B, "*ANC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x8B, "*XAA", 2, 2, AddressingMode::Immediate), // ANE
-    Opcode::new(0x6B, "*ARR", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xC7, "*DCP", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0xD7, "*DCP", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0xCF, "*DCP", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0xDF, "*DCP", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0xDB, "*DCP", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0xC3, "*DCP", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0xD3, "*DCP", 2, 8, AddressingMode::IndirectIndexed),
-    Opcode::new(0xE7, "*ISB", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0xF7, "*ISB", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0xEF, "*ISB", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0xFF, "*ISB", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0xFB, "*ISB", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0xE3, "*ISB", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0xF3, "*ISB", 2, 4, AddressingMode::IndirectIndexed),
-    Opcode::new(0xBB, "*LAS", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xA7, "*LAX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB7, "*LAX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0xAF, "*LAX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBF, "*LAX", 3, 4, AddressingMode::AbsoluteY),
-    Opcode::new(0xA3, "*LAX", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0xB3, "*LAX", 2, 5, AddressingMode::IndirectIndexed),
-    Opcode::new(0xAB, "*LXA", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x27, "*RLA", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x37, "*RLA", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x2F, "*RLA", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x3F, "*RLA", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x3B, "*RLA", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0x23, "*RLA", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0x33, "*RLA", 2, 8, AddressingMode::IndirectIndexed),
-    Opcode::new(0x67, "*RRA", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x77, "*RRA", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x6F, "*RRA", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x7F, "*RRA", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x7B, "*RRA", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0x63, "*RRA", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0x73, "*RRA", 2, 8, AddressingMode::IndirectIndexed),
-    Opcode::new(0x87, "*SAX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x97, "*SAX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0x8F, "*SAX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x83, "*SAX", 2, 6, AddressingMode::IndexedIndirect),
-    Opcode::new(0xCB, "*SBX", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x9F, "*SHA", 3, 5, AddressingMode::AbsoluteY),
-    Opcode::new(0x93, "*SHA", 2, 6, AddressingMode::IndirectIndexed),
-    Opcode::new(0x9E, "*SHX", 3, 5, AddressingMode::AbsoluteY),
-    Opcode::new(0x9C, "*SHY", 3, 5, AddressingMode::AbsoluteX),
-    Opcode::new(0x07, "*SLO", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x17, "*SLO", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x0F, "*SLO", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x1F, "*SLO", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x1B, "*SLO", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0x03, "*SLO", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0x13, "*SLO", 2, 8, AddressingMode::IndirectIndexed),
-    Opcode::new(0x47, "*SRE", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x57, "*SRE", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x4F, "*SRE", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x5F, "*SRE", 3, 7, AddressingMode::AbsoluteX),
-    Opcode::new(0x5B, "*SRE", 3, 7, AddressingMode::AbsoluteY),
-    Opcode::new(0x43, "*SRE", 2, 8, AddressingMode::IndexedIndirect),
-    Opcode::new(0x53, "*SRE", 2, 8, AddressingMode::IndirectIndexed),
-    Opcode::new(0x9B, "*TAS", 3, 5, AddressingMode::AbsoluteY),
-    Opcode::new(0xEB, "*SBC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x1A, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x3A, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x5A, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x7A, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xDA, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0xFA, "*NOP", 1, 2, AddressingMode::Implicit),
-    Opcode::new(0x80, "*NOP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x82, "*NOP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x89, "*NOP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xC2, "*NOP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xE2, "*NOP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x04, "*NOP", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x44, "*NOP", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x64, "*NOP", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x14, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x34, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x54, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x74, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xD4, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xF4, "*NOP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x0C, "*NOP", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x1C, "*NOP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x3C, "*NOP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x5C, "*NOP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0x7C, "*NOP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0xDC, "*NOP", 3, 4, AddressingMode::AbsoluteX),
-    Opcode::new(0xFC, "*NOP", 3, 4, AddressingMode::AbsoluteX),
+    Opcode::new(0x4B, "*ALR", 2, 2, AddressingMode::Immediate, CPU::alr),
+    Opcode::new(0x0B, "*ANC", 2, 2, AddressingMode::Immediate, CPU::anc),
+    Opcode::new(0x2B, "*ANC", 2, 2, AddressingMode::Immediate, CPU::anc),
+    Opcode::new(0x8B, "*XAA", 2, 2, AddressingMode::Immediate, CPU::ane_xaa), // ANE
+    Opcode::new(0x6B, "*ARR", 2, 2, AddressingMode::Immediate, CPU::arr),
+    Opcode::new(0xC7, "*DCP", 2, 5, AddressingMode::ZeroPage, CPU::dcp_dcm),
+    Opcode::new(0xD7, "*DCP", 2, 6, AddressingMode::ZeroPageX, CPU::dcp_dcm),
+    Opcode::new(0xCF, "*DCP", 3, 6, AddressingMode::Absolute, CPU::dcp_dcm),
+    Opcode::new(0xDF, "*DCP", 3, 7, AddressingMode::AbsoluteX, CPU::dcp_dcm),
+    Opcode::new(0xDB, "*DCP", 3, 7, AddressingMode::AbsoluteY, CPU::dcp_dcm),
+    Opcode::new(0xC3, "*DCP", 2, 8, AddressingMode::IndexedIndirect, CPU::dcp_dcm),
+    Opcode::new(0xD3, "*DCP", 2, 8, AddressingMode::IndirectIndexed, CPU::dcp_dcm),
+    Opcode::new(0xE7, "*ISB", 2, 5, AddressingMode::ZeroPage, CPU::isc_isb_ins),
+    Opcode::new(0xF7, "*ISB", 2, 6, AddressingMode::ZeroPageX, CPU::isc_isb_ins),
+    Opcode::new(0xEF, "*ISB", 3, 6, AddressingMode::Absolute, CPU::isc_isb_ins),
+    Opcode::new(0xFF, "*ISB", 3, 7, AddressingMode::AbsoluteX, CPU::isc_isb_ins),
+    Opcode::new(0xFB, "*ISB", 3, 7, AddressingMode::AbsoluteY, CPU::isc_isb_ins),
+    Opcode::new(0xE3, "*ISB", 2, 8, AddressingMode::IndexedIndirect, CPU::isc_isb_ins),
+    Opcode::new(0xF3, "*ISB", 2, 8, AddressingMode::IndirectIndexed, CPU::isc_isb_ins),
+    Opcode::new(0xBB, "*LAS", 3, 4, AddressingMode::AbsoluteY, CPU::las_lar),
+    Opcode::new(0xA7, "*LAX", 2, 3, AddressingMode::ZeroPage, CPU::lax),
+    Opcode::new(0xB7, "*LAX", 2, 4, AddressingMode::ZeroPageY, CPU::lax),
+    Opcode::new(0xAF, "*LAX", 3, 4, AddressingMode::Absolute, CPU::lax),
+    Opcode::new(0xBF, "*LAX", 3, 4, AddressingMode::AbsoluteY, CPU::lax),
+    Opcode::new(0xA3, "*LAX", 2, 6, AddressingMode::IndexedIndirect, CPU::lax),
+    Opcode::new(0xB3, "*LAX", 2, 5, AddressingMode::IndirectIndexed, CPU::lax),
+    Opcode::new(0xAB, "*LXA", 2, 2, AddressingMode::Immediate, CPU::lxa),
+    Opcode::new(0x27, "*RLA", 2, 5, AddressingMode::ZeroPage, CPU::rla),
+    Opcode::new(0x37, "*RLA", 2, 6, AddressingMode::ZeroPageX, CPU::rla),
+    Opcode::new(0x2F, "*RLA", 3, 6, AddressingMode::Absolute, CPU::rla),
+    Opcode::new(0x3F, "*RLA", 3, 7, AddressingMode::AbsoluteX, CPU::rla),
+    Opcode::new(0x3B, "*RLA", 3, 7, AddressingMode::AbsoluteY, CPU::rla),
+    Opcode::new(0x23, "*RLA", 2, 8, AddressingMode::IndexedIndirect, CPU::rla),
+    Opcode::new(0x33, "*RLA", 2, 8, AddressingMode::IndirectIndexed, CPU::rla),
+    Opcode::new(0x67, "*RRA", 2, 5, AddressingMode::ZeroPage, CPU::rra),
+    Opcode::new(0x77, "*RRA", 2, 6, AddressingMode::ZeroPageX, CPU::rra),
+    Opcode::new(0x6F, "*RRA", 3, 6, AddressingMode::Absolute, CPU::rra),
+    Opcode::new(0x7F, "*RRA", 3, 7, AddressingMode::AbsoluteX, CPU::rra),
+    Opcode::new(0x7B, "*RRA", 3, 7, AddressingMode::AbsoluteY, CPU::rra),
+    Opcode::new(0x63, "*RRA", 2, 8, AddressingMode::IndexedIndirect, CPU::rra),
+    Opcode::new(0x73, "*RRA", 2, 8, AddressingMode::IndirectIndexed, CPU::rra),
+    Opcode::new(0x87, "*SAX", 2, 3, AddressingMode::ZeroPage, CPU::sax_axs_aax),
+    Opcode::new(0x97, "*SAX", 2, 4, AddressingMode::ZeroPageY, CPU::sax_axs_aax),
+    Opcode::new(0x8F, "*SAX", 3, 4, AddressingMode::Absolute, CPU::sax_axs_aax),
+    Opcode::new(0x83, "*SAX", 2, 6, AddressingMode::IndexedIndirect, CPU::sax_axs_aax),
+    Opcode::new(0xCB, "*SBX", 2, 2, AddressingMode::Immediate, CPU::sbx_axs),
+    Opcode::new(0x9F, "*SHA", 3, 5, AddressingMode::AbsoluteY, CPU::sha_ahx),
+    Opcode::new(0x93, "*SHA", 2, 6, AddressingMode::IndirectIndexed, CPU::sha_ahx),
+    Opcode::new(0x9E, "*SHX", 3, 5, AddressingMode::AbsoluteY, CPU::shx_sxa),
+    Opcode::new(0x9C, "*SHY", 3, 5, AddressingMode::AbsoluteX, CPU::shy_sya),
+    Opcode::new(0x07, "*SLO", 2, 5, AddressingMode::ZeroPage, CPU::slo_aso),
+    Opcode::new(0x17, "*SLO", 2, 6, AddressingMode::ZeroPageX, CPU::slo_aso),
+    Opcode::new(0x0F, "*SLO", 3, 6, AddressingMode::Absolute, CPU::slo_aso),
+    Opcode::new(0x1F, "*SLO", 3, 7, AddressingMode::AbsoluteX, CPU::slo_aso),
+    Opcode::new(0x1B, "*SLO", 3, 7, AddressingMode::AbsoluteY, CPU::slo_aso),
+    Opcode::new(0x03, "*SLO", 2, 8, AddressingMode::IndexedIndirect, CPU::slo_aso),
+    Opcode::new(0x13, "*SLO", 2, 8, AddressingMode::IndirectIndexed, CPU::slo_aso),
+    Opcode::new(0x47, "*SRE", 2, 5, AddressingMode::ZeroPage, CPU::sre_lse),
+    Opcode::new(0x57, "*SRE", 2, 6, AddressingMode::ZeroPageX, CPU::sre_lse),
+    Opcode::new(0x4F, "*SRE", 3, 6, AddressingMode::Absolute, CPU::sre_lse),
+    Opcode::new(0x5F, "*SRE", 3, 7, AddressingMode::AbsoluteX, CPU::sre_lse),
+    Opcode::new(0x5B, "*SRE", 3, 7, AddressingMode::AbsoluteY, CPU::sre_lse),
+    Opcode::new(0x43, "*SRE", 2, 8, AddressingMode::IndexedIndirect, CPU::sre_lse),
+    Opcode::new(0x53, "*SRE", 2, 8, AddressingMode::IndirectIndexed, CPU::sre_lse),
+    Opcode::new(0x9B, "*TAS", 3, 5, AddressingMode::AbsoluteY, CPU::tas_shs),
+    Opcode::new(0x02, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x12, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x22, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x32, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x42, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x52, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x62, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x72, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0x92, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0xB2, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0xD2, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0xF2, "*JAM", 1, 2, AddressingMode::Implicit, CPU::jam),
+    Opcode::new(0xEB, "*SBC", 2, 2, AddressingMode::Immediate, CPU::subtract_memory_from_accumulator_with_borrow),
+    Opcode::new(0x1A, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0x3A, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0x5A, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0x7A, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0xDA, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0xFA, "*NOP", 1, 2, AddressingMode::Implicit, CPU::no_operation),
+    Opcode::new(0x80, "*NOP", 2, 2, AddressingMode::Immediate, CPU::no_operation),
+    Opcode::new(0x82, "*NOP", 2, 2, AddressingMode::Immediate, CPU::no_operation),
+    Opcode::new(0x89, "*NOP", 2, 2, AddressingMode::Immediate, CPU::no_operation),
+    Opcode::new(0xC2, "*NOP", 2, 2, AddressingMode::Immediate, CPU::no_operation),
+    Opcode::new(0xE2, "*NOP", 2, 2, AddressingMode::Immediate, CPU::no_operation),
+    Opcode::new(0x04, "*NOP", 2, 3, AddressingMode::ZeroPage, CPU::nop_read),
+    Opcode::new(0x44, "*NOP", 2, 3, AddressingMode::ZeroPage, CPU::nop_read),
+    Opcode::new(0x64, "*NOP", 2, 3, AddressingMode::ZeroPage, CPU::nop_read),
+    Opcode::new(0x14, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0x34, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0x54, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0x74, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0xD4, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0xF4, "*NOP", 2, 4, AddressingMode::ZeroPageX, CPU::nop_read),
+    Opcode::new(0x0C, "*NOP", 3, 4, AddressingMode::Absolute, CPU::nop_read),
+    Opcode::new(0x1C, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
+    Opcode::new(0x3C, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
+    Opcode::new(0x5C, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
+    Opcode::new(0x7C, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
+    Opcode::new(0xDC, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
+    Opcode::new(0xFC, "*NOP", 3, 4, AddressingMode::AbsoluteX, CPU::nop_read),
   ];
-
-  pub static ref OPCODES_MAP: HashMap<u8, &'static Opcode> = {
-    let mut map = HashMap::new();
-    for opcode in &*CPU_OPCODES {
-      map.insert(opcode.code, opcode);
-    }
-    return map;
-  };
 }
@@ -92,6 +92,51 @@ impl Registers {
     self.set_nz_flags(self.a);
   }
 
+  /// NMOS 6502 十进制模式下的 ADC，参见
+  /// [Decimal Mode](http://www.6502.org/tutorials/decimal_mode.html#A)。
+  ///
+  /// `Z` 按二进制加法的结果设置；`N` 和 `V` 取自高半字节调整之前的中间结果，
+  /// 所以与最终写入 A 的值并不一致，这是 NMOS 6502 的真实行为。
+  pub fn add_to_a_decimal(&mut self, data: u8) {
+    let carry = if self.status.contains(Flags::C) { 1 } else { 0 };
+    let binary = (self.a as u16 + data as u16 + carry) as u8;
+
+    let mut low = (self.a & 0x0F) as u16 + (data & 0x0F) as u16 + carry;
+    if low >= 0x0A {
+      low = ((low + 0x06) & 0x0F) + 0x10;
+    }
+    let mut sum = (self.a & 0xF0) as u16 + (data & 0xF0) as u16 + low;
+
+    // 有符号的中间结果，高半字节按符号扩展
+    let signed = (self.a & 0xF0) as i8 as i16 + (data & 0xF0) as i8 as i16 + low as i16;
+    self.status.set(Flags::N, signed & 0x80 == 0x80);
+    self.status.set(Flags::V, !(-128..=127).contains(&signed));
+    self.status.set(Flags::Z, binary == 0);
+
+    if sum >= 0xA0 {
+      sum += 0x60;
+    }
+    self.status.set(Flags::C, sum >= 0x100);
+    self.a = sum as u8;
+  }
+
+  /// NMOS 6502 十进制模式下的 SBC，标志位全部按二进制减法设置，只有 A 的结果是 BCD
+  pub fn subtract_from_a_decimal(&mut self, data: u8) {
+    let borrow = if self.status.contains(Flags::C) { 0 } else { 1 };
+    let a = self.a;
+    self.add_to_a(!data);
+
+    let mut low = (a & 0x0F) as i16 - (data & 0x0F) as i16 - borrow;
+    if low < 0 {
+      low = ((low - 0x06) & 0x0F) - 0x10;
+    }
+    let mut result = (a & 0xF0) as i16 - (data & 0xF0) as i16 + low;
+    if result < 0 {
+      result -= 0x60;
+    }
+    self.a = result as u8;
+  }
+
 }
 
 #[cfg(test)]
@@ -109,4 +154,63 @@ mod test {
     assert!(registers.status.contains(Flags::N));
   }
 
+  #[test]
+  fn test_add_to_a_decimal() {
+    let mut registers = Registers::new();
+    registers.status = Flags::empty();
+
+    registers.a = 0x58;
+    registers.add_to_a_decimal(0x46);
+    assert_eq!(registers.a, 0x04);
+    assert!(registers.status.contains(Flags::C));
+
+    registers.a = 0x12;
+    registers.add_to_a_decimal(0x34);
+    assert_eq!(registers.a, 0x47);
+    assert!(!registers.status.contains(Flags::C));
+
+    // 99 + 1 = 00，Z 按二进制结果 $9A 设置，N 和 V 取自中间结果
+    registers.status = Flags::empty();
+    registers.a = 0x99;
+    registers.add_to_a_decimal(0x01);
+    assert_eq!(registers.a, 0x00);
+    assert!(registers.status.contains(Flags::C));
+    assert!(!registers.status.contains(Flags::Z));
+    assert!(registers.status.contains(Flags::N));
+    assert!(!registers.status.contains(Flags::V));
+
+    // 79 + 00 + C = 80，中间结果发生有符号溢出
+    registers.status = Flags::C;
+    registers.a = 0x79;
+    registers.add_to_a_decimal(0x00);
+    assert_eq!(registers.a, 0x80);
+    assert!(registers.status.contains(Flags::V));
+    assert!(registers.status.contains(Flags::N));
+  }
+
+  #[test]
+  fn test_subtract_from_a_decimal() {
+    let mut registers = Registers::new();
+
+    registers.status = Flags::C;
+    registers.a = 0x46;
+    registers.subtract_from_a_decimal(0x12);
+    assert_eq!(registers.a, 0x34);
+    assert!(registers.status.contains(Flags::C));
+
+    registers.status = Flags::C;
+    registers.a = 0x12;
+    registers.subtract_from_a_decimal(0x21);
+    assert_eq!(registers.a, 0x91);
+    assert!(!registers.status.contains(Flags::C));
+    // N 取自二进制结果 $F1
+    assert!(registers.status.contains(Flags::N));
+
+    registers.status = Flags::empty();
+    registers.a = 0x40;
+    registers.subtract_from_a_decimal(0x13);
+    assert_eq!(registers.a, 0x26);
+    assert!(registers.status.contains(Flags::C));
+  }
+
 }
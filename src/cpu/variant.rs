@@ -0,0 +1,17 @@
+/// CPU 的型号，决定 `D` 标志是否生效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+  /// NES 使用的 Ricoh 2A03，去掉了十进制模式，`D` 标志可以设置但 ADC/SBC 忽略它
+  #[default]
+  Ricoh2A03,
+
+  /// 标准 NMOS 6502，`D` 置位时 ADC/SBC 按 BCD 运算
+  Nmos6502,
+}
+
+impl Variant {
+  /// 是否支持十进制模式
+  pub fn has_decimal_mode(&self) -> bool {
+    return *self == Variant::Nmos6502;
+  }
+}
@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::cpu::addressing_mode::AddressingMode;
+
+/// 卡带使用了尚未实现的 mapper
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedMapper {
+  pub mapper: u16,
+}
+
+impl fmt::Display for UnsupportedMapper {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "mapper {} is not supported", self.mapper)
+  }
+}
+
+impl Error for UnsupportedMapper {}
+
+/// 解析 iNES/NES 2.0 文件失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeError {
+  /// 文件不足 16 字节，放不下文件头
+  TooShort { length: usize },
+
+  /// 开头不是 `NES<EOF>`
+  BadMagic,
+
+  /// 文件头声明的 trainer、PRG ROM、CHR ROM 超出了文件长度
+  Truncated { expected: usize, actual: usize },
+
+  /// 文件头声明没有 PRG ROM
+  MissingPrgRom,
+}
+
+impl fmt::Display for CartridgeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CartridgeError::TooShort { length } => {
+        write!(f, "file is too short for an iNES header ({} bytes)", length)
+      }
+      CartridgeError::BadMagic => write!(f, "file is not in iNES format"),
+      CartridgeError::Truncated { expected, actual } => {
+        write!(f, "file is truncated: header declares {} bytes, found {}", expected, actual)
+      }
+      CartridgeError::MissingPrgRom => write!(f, "header declares no PRG ROM"),
+    }
+  }
+}
+
+impl Error for CartridgeError {}
+
+/// 模拟过程中出现的错误，`pc` 和 `opcode` 指向出错的那条指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+  /// 指令使用了不支持的寻址模式
+  UnsupportedAddressingMode { pc: u16, opcode: u8, mode: AddressingMode },
+}
+
+impl fmt::Display for EmuError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      EmuError::UnsupportedAddressingMode { pc, opcode, mode } => {
+        write!(f, "addressing mode {:?} is not supported (opcode ${:02X} at ${:04X})", mode, opcode, pc)
+      }
+    }
+  }
+}
+
+impl Error for EmuError {}
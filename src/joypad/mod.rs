@@ -0,0 +1,113 @@
+use bitflags::bitflags;
+
+bitflags! {
+  /// 标准手柄的 8 个按键，按读取顺序从低位排到高位
+  pub struct JoypadButton: u8 {
+    const A = 0b00000001;
+    const B = 0b00000010;
+    const SELECT = 0b00000100;
+    const START = 0b00001000;
+    const UP = 0b00010000;
+    const DOWN = 0b00100000;
+    const LEFT = 0b01000000;
+    const RIGHT = 0b10000000;
+  }
+}
+
+/// 手柄端口读取时没有驱动的高位，保留的是数据总线上残留的值，
+/// 通常是 `LDA $4016` 指令最后读到的地址高字节 `$40`
+const OPEN_BUS: u8 = 0x40;
+
+/// [标准手柄](https://www.nesdev.org/wiki/Standard_controller)
+///
+/// 向 `$4016` 写入 1 时手柄持续锁存按键状态，写入 0 后锁存结束，
+/// 之后每次读取 `$4016`（1P）或 `$4017`（2P）依次移出一个按键，8 个按键读完后一直返回 1。
+pub struct Joypad {
+  strobe: bool,
+
+  /// 下一次读取返回第几个按键
+  button_index: u8,
+
+  /// 主机设置的按键状态
+  buttons: JoypadButton,
+}
+
+impl Joypad {
+  pub fn new() -> Self {
+    return Joypad {
+      strobe: false,
+      button_index: 0,
+      buttons: JoypadButton::empty(),
+    };
+  }
+
+  /// 前端或测试设置一个按键是否按下
+  pub fn set_button(&mut self, button: JoypadButton, pressed: bool) {
+    self.buttons.set(button, pressed);
+  }
+
+  /// 一次设置全部按键的状态
+  pub fn set_buttons(&mut self, buttons: JoypadButton) {
+    self.buttons = buttons;
+  }
+
+  pub fn buttons(&self) -> JoypadButton {
+    return self.buttons;
+  }
+
+  /// CPU 写入 `$4016`，只有第 0 位有效
+  pub fn write(&mut self, data: u8) {
+    self.strobe = data & 0x01 != 0;
+    if self.strobe {
+      self.button_index = 0;
+    }
+  }
+
+  /// CPU 读取端口，移出下一个按键
+  pub fn read(&mut self) -> u8 {
+    let data = self.peek();
+    if !self.strobe && self.button_index < 8 {
+      self.button_index += 1;
+    }
+    return data;
+  }
+
+  /// 查看下一个按键，不移位
+  pub fn peek(&self) -> u8 {
+    let pressed = if self.button_index >= 8 {
+      1
+    } else {
+      (self.buttons.bits() >> self.button_index) & 0x01
+    };
+    return OPEN_BUS | pressed;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_buttons_are_read_serially_after_strobe() {
+    let mut joypad = Joypad::new();
+    joypad.set_buttons(JoypadButton::A | JoypadButton::START | JoypadButton::RIGHT);
+    joypad.write(1);
+    joypad.write(0);
+
+    let bits: Vec<u8> = (0..10).map(|_| joypad.read() & 0x01).collect();
+    assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+    assert_eq!(joypad.peek() & 0xE0, OPEN_BUS);
+  }
+
+  #[test]
+  fn test_strobe_high_keeps_returning_a() {
+    let mut joypad = Joypad::new();
+    joypad.write(1);
+    joypad.set_button(JoypadButton::A, true);
+    assert_eq!(joypad.read() & 0x01, 1);
+    assert_eq!(joypad.read() & 0x01, 1);
+
+    joypad.set_button(JoypadButton::A, false);
+    assert_eq!(joypad.read() & 0x01, 0);
+  }
+}
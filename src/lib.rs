@@ -0,0 +1,12 @@
+// 代码风格上习惯显式 `return` 以及 `a = a & b` 这类写法
+#![allow(clippy::needless_return, clippy::assign_op_pattern, clippy::new_without_default)]
+
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod error;
+pub mod joypad;
+pub mod mapper;
+pub mod memory;
+pub mod ppu;
+pub mod trace;
@@ -0,0 +1,245 @@
+use super::banks::Banks;
+use super::eeprom::{Eeprom, Model};
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [Bandai FCG](https://www.nesdev.org/wiki/Bandai_FCG_board)（mapper 16、159）
+///
+/// 寄存器按地址的低 4 位区分：
+///
+/// | 寄存器   | 功能                                                        |
+/// |---------|------------------------------------------------------------|
+/// | `$0-7`  | 1 KiB CHR bank 0-7                                         |
+/// | `$8`    | `$8000` 的 16 KiB PRG bank，`$C000` 固定为最后一个 bank        |
+/// | `$9`    | 镜像                                                        |
+/// | `$A`    | 第 0 位开启 IRQ，写入时确认 IRQ                                |
+/// | `$B-C`  | IRQ 计数器的低 8 位、高 8 位                                   |
+/// | `$D`    | EEPROM：第 5 位 SCL，第 6 位 SDA，第 7 位开启读取               |
+///
+/// FCG-1/2（子 mapper 4）的寄存器在 `$6000-$7FFF`，直接写入计数器，没有 EEPROM；
+/// LZ93D50（子 mapper 5 和 mapper 159）的寄存器在 `$8000-$FFFF`，先写入锁存值，
+/// 写 `$A` 时才装入计数器，从 `$6000-$7FFF` 的第 4 位读出 EEPROM 的 SDA。
+/// mapper 16 用 256 字节的 24C02，mapper 159 用 128 字节的 24C01。
+/// 子 mapper 为 0 时两段地址都接受寄存器写入，按 LZ93D50 处理。
+pub struct BandaiFcg {
+  prg_rom: Banks,
+  chr: Banks,
+
+  /// 寄存器是否在 `$6000-$7FFF`、`$8000-$FFFF`
+  registers_at_6000: bool,
+  registers_at_8000: bool,
+  /// LZ93D50 的计数器有锁存值
+  latched: bool,
+  eeprom: Option<Eeprom>,
+  eeprom_readable: bool,
+  battery: bool,
+
+  mirroring: Mirroring,
+  irq_enabled: bool,
+  irq_latch: u16,
+  irq_counter: u16,
+  irq_pending: bool,
+}
+
+impl BandaiFcg {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let (registers_at_6000, registers_at_8000, eeprom) = match (cartridge.mapper, cartridge.submapper) {
+      (159, _) => (false, true, Some(Model::X24C01)),
+      (_, 4) => (true, false, None),
+      (_, 5) => (false, true, Some(Model::X24C02)),
+      (_, _) => (true, true, Some(Model::X24C02)),
+    };
+    let mut mapper = BandaiFcg {
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x4000, false),
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      registers_at_6000,
+      registers_at_8000,
+      latched: registers_at_8000,
+      eeprom: eeprom.map(Eeprom::new),
+      eeprom_readable: false,
+      battery: cartridge.battery,
+      mirroring: Mirroring::Vertical,
+      irq_enabled: false,
+      irq_latch: 0,
+      irq_counter: 0,
+      irq_pending: false,
+    };
+    mapper.prg_rom.set_from_end(1, 1);
+    return mapper;
+  }
+
+  fn write_register(&mut self, register: u16, data: u8) {
+    match register {
+      0x0..=0x7 => self.chr.set(register as usize, data as usize),
+      0x8 => self.prg_rom.set(0, (data & 0x0F) as usize),
+      0x9 => {
+        self.mirroring = match data & 0x03 {
+          0 => Mirroring::Vertical,
+          1 => Mirroring::Horizontal,
+          2 => Mirroring::SingleScreenLower,
+          _ => Mirroring::SingleScreenUpper,
+        };
+      }
+      0xA => {
+        self.irq_enabled = data & 0x01 != 0;
+        self.irq_pending = false;
+        if self.latched {
+          self.irq_counter = self.irq_latch;
+        }
+      }
+      0xB | 0xC => {
+        let shift = if register == 0xB { 0 } else { 8 };
+        let value = (self.irq_latch & !(0xFF << shift)) | ((data as u16) << shift);
+        self.irq_latch = value;
+        if !self.latched {
+          self.irq_counter = value;
+        }
+      }
+      0xD => {
+        self.eeprom_readable = data & 0x80 != 0;
+        if let Some(eeprom) = self.eeprom.as_mut() {
+          eeprom.write(data & 0x20 != 0, data & 0x40 != 0);
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+impl Mapper for BandaiFcg {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF => match &self.eeprom {
+        Some(eeprom) if self.eeprom_readable => (eeprom.read() as u8) << 4,
+        _ => 0,
+      },
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    let decoded = match address {
+      0x6000..=0x7FFF => self.registers_at_6000,
+      0x8000..=0xFFFF => self.registers_at_8000,
+      _ => false,
+    };
+    if decoded {
+      self.write_register(address & 0x0F, data);
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq_pending;
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    if !self.irq_enabled {
+      return;
+    }
+    // 先检查再减一，计数器为 0 时触发
+    for _ in 0..cycles {
+      if self.irq_counter == 0 {
+        self.irq_pending = true;
+      }
+      self.irq_counter = self.irq_counter.wrapping_sub(1);
+    }
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.eeprom.as_ref().filter(|_| self.battery).map(|eeprom| eeprom.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    if let Some(eeprom) = self.eeprom.as_mut() {
+      eeprom.load(data);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_register_ranges() {
+    let mut cartridge = banked_cartridge(16, 0x4000, 8, 0x0400, 16);
+    cartridge.submapper = 4;
+    let mut mapper = BandaiFcg::new(cartridge);
+    mapper.cpu_write(0x8008, 3);
+    assert_eq!(mapper.cpu_peek(0x8000), 0);
+    mapper.cpu_write(0x6008, 3);
+    mapper.cpu_write(0x7FF3, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xC000), 7);
+    assert_eq!(mapper.ppu_read(0x0C00), 5);
+    assert_eq!(mapper.save_data(), None);
+
+    let mut cartridge = banked_cartridge(159, 0x4000, 8, 0x0400, 16);
+    cartridge.battery = true;
+    let mut mapper = BandaiFcg::new(cartridge);
+    mapper.cpu_write(0x6008, 3);
+    assert_eq!(mapper.cpu_peek(0x8000), 0);
+    mapper.cpu_write(0x8009, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    assert_eq!(mapper.save_data().map(|data| data.len()), Some(0x80));
+
+    // 没有电池的卡带 EEPROM 不需要保存
+    let mapper = BandaiFcg::new(banked_cartridge(159, 0x4000, 8, 0x0400, 16));
+    assert_eq!(mapper.save_data(), None);
+  }
+
+  #[test]
+  fn test_irq_latch() {
+    let mut mapper = BandaiFcg::new(banked_cartridge(159, 0x4000, 8, 0x0400, 16));
+    mapper.cpu_write(0x800B, 2);
+    mapper.cpu_write(0x800C, 0);
+    mapper.tick(10);
+    assert!(!mapper.irq());
+
+    // LZ93D50 写 $A 时装入计数器
+    mapper.cpu_write(0x800A, 1);
+    mapper.tick(2);
+    assert!(!mapper.irq());
+    mapper.tick(1);
+    assert!(mapper.irq());
+    mapper.cpu_write(0x800A, 0);
+    assert!(!mapper.irq());
+  }
+
+  #[test]
+  fn test_eeprom_through_registers() {
+    let mut mapper = BandaiFcg::new(banked_cartridge(16, 0x4000, 8, 0x0400, 16));
+    let mut save = vec![0; 0x100];
+    save[0] = 0x80;
+    mapper.load_save_data(&save);
+
+    // 起始条件之后发送设备地址 $A1，读出第 0 个字节的最高位
+    let mut line = |scl: u8, sda: u8| mapper.cpu_write(0x800D, 0x80 | (sda << 6) | (scl << 5));
+    line(0, 1);
+    line(1, 1);
+    line(1, 0);
+    for bit in [1, 0, 1, 0, 0, 0, 0, 1, 1] {
+      line(0, bit);
+      line(1, bit);
+      line(0, bit);
+    }
+    line(0, 1);
+    line(1, 1);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x10);
+  }
+}
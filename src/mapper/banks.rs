@@ -0,0 +1,122 @@
+/// 按 bank 切换的一块卡带存储（PRG ROM、CHR ROM/RAM、PRG RAM）。
+///
+/// CPU 或 PPU 看到的窗口被分成大小相同的若干槽，每个槽可以映射到存储中的任意一个 bank。
+/// 槽的大小取 mapper 支持的最小切换粒度，更大的切换单位由调用方设置连续的几个槽。
+pub struct Banks {
+  data: Vec<u8>,
+  writable: bool,
+  bank_size: usize,
+
+  /// 每个槽映射到的 bank 在 `data` 中的偏移
+  slots: Vec<usize>,
+}
+
+impl Banks {
+  /// 窗口大小为 `window`，初始时第 i 个槽映射到第 i 个 bank
+  pub fn new(data: Vec<u8>, window: usize, bank_size: usize, writable: bool) -> Self {
+    let mut data = data;
+    if data.is_empty() {
+      data = vec![0; window];
+    }
+    let mut banks = Banks {
+      data,
+      writable,
+      bank_size,
+      slots: vec![0; window / bank_size],
+    };
+    for slot in 0..banks.slots.len() {
+      banks.set(slot, slot);
+    }
+    return banks;
+  }
+
+  /// 卡带没有 CHR ROM 时使用大小为 `chr_ram_size` 的可写 CHR RAM，至少 8 KiB
+  pub fn chr(chr_rom: Vec<u8>, chr_ram_size: usize, bank_size: usize) -> Self {
+    if chr_rom.is_empty() {
+      return Banks::new(vec![0; chr_ram_size.max(0x2000)], 0x2000, bank_size, true);
+    }
+    return Banks::new(chr_rom, 0x2000, bank_size, false);
+  }
+
+  /// 存储中 bank 的个数
+  pub fn bank_count(&self) -> usize {
+    return (self.data.len() / self.bank_size).max(1);
+  }
+
+  /// 把第 `slot` 个槽映射到第 `bank` 个 bank，超出范围的编号按存储大小回绕
+  pub fn set(&mut self, slot: usize, bank: usize) {
+    self.slots[slot] = (bank % self.bank_count()) * self.bank_size;
+  }
+
+  /// 把第 `slot` 个槽映射到倒数第 `from_end` 个 bank（1 是最后一个）
+  pub fn set_from_end(&mut self, slot: usize, from_end: usize) {
+    let count = self.bank_count();
+    self.set(slot, count - from_end % count);
+  }
+
+  fn offset(&self, address: usize) -> usize {
+    let slot = (address / self.bank_size) % self.slots.len();
+    return (self.slots[slot] + address % self.bank_size) % self.data.len();
+  }
+
+  /// `address` 是相对窗口起点的偏移
+  pub fn read(&self, address: usize) -> u8 {
+    return self.data[self.offset(address)];
+  }
+
+  /// 只读存储忽略写入
+  pub fn write(&mut self, address: usize, data: u8) {
+    if self.writable {
+      let offset = self.offset(address);
+      self.data[offset] = data;
+    }
+  }
+
+  pub fn is_writable(&self) -> bool {
+    return self.writable;
+  }
+
+  /// 整块存储的内容，用于保存电池供电的 PRG RAM
+  pub fn data(&self) -> &[u8] {
+    return &self.data;
+  }
+
+  /// 从存档恢复存储的内容，长度不一致时只复制重叠的部分
+  pub fn load(&mut self, data: &[u8]) {
+    let length = data.len().min(self.data.len());
+    self.data[..length].copy_from_slice(&data[..length]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_banks_switch_and_wrap() {
+    let data: Vec<u8> = (0..4).flat_map(|bank| vec![bank; 0x1000]).collect();
+    let mut banks = Banks::new(data, 0x2000, 0x1000, false);
+    assert_eq!(banks.read(0x0000), 0);
+    assert_eq!(banks.read(0x1FFF), 1);
+
+    banks.set(0, 3);
+    banks.set(1, 5);
+    assert_eq!(banks.read(0x0000), 3);
+    assert_eq!(banks.read(0x1000), 1);
+
+    banks.set_from_end(1, 1);
+    assert_eq!(banks.read(0x1000), 3);
+
+    banks.write(0x0000, 0xFF);
+    assert_eq!(banks.read(0x0000), 3);
+  }
+
+  #[test]
+  fn test_empty_chr_is_ram() {
+    let mut banks = Banks::chr(vec![], 0x2000, 0x0400);
+    banks.set(0, 7);
+    banks.write(0x0010, 0x42);
+    assert_eq!(banks.read(0x0010), 0x42);
+    assert_eq!(banks.read(0x1C10), 0x42);
+  }
+}
@@ -0,0 +1,266 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// 只用分立逻辑芯片（锁存器）实现 bank 切换的卡带
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Board {
+  /// [UxROM](https://www.nesdev.org/wiki/UxROM)（mapper 2）：`$8000` 切换 16 KiB，`$C000` 固定最后一个 bank
+  UxRom,
+
+  /// [CNROM](https://www.nesdev.org/wiki/CNROM)（mapper 3）：切换 8 KiB CHR
+  CnRom,
+
+  /// [AxROM](https://www.nesdev.org/wiki/AxROM)（mapper 7）：切换 32 KiB PRG，第 4 位选择单屏命名表
+  AxRom,
+
+  /// [GxROM](https://www.nesdev.org/wiki/GxROM)（mapper 66）：第 4-5 位切换 32 KiB PRG，第 0-1 位切换 8 KiB CHR
+  GxRom,
+
+  /// [Color Dreams](https://www.nesdev.org/wiki/Color_Dreams)（mapper 11）：第 0-1 位切换 32 KiB PRG，第 4-7 位切换 8 KiB CHR
+  ColorDreams,
+
+  /// [BNROM](https://www.nesdev.org/wiki/BNROM)（mapper 34）：切换 32 KiB PRG
+  BnRom,
+
+  /// [NINA-001](https://www.nesdev.org/wiki/NINA-001)（mapper 34，有 CHR ROM）：
+  /// `$7FFD` 切换 32 KiB PRG，`$7FFE`、`$7FFF` 各切换 4 KiB CHR
+  Nina001,
+}
+
+impl Board {
+  /// 锁存器挂在 ROM 的地址上，写入时 ROM 也在输出数据，
+  /// 实际写入的是两者按位与的结果（[bus conflict](https://www.nesdev.org/wiki/Bus_conflict)）
+  fn has_bus_conflicts(&self) -> bool {
+    return !matches!(self, Board::AxRom | Board::Nina001);
+  }
+}
+
+/// 分立逻辑 mapper，寄存器就是 `$8000-$FFFF`（NINA-001 是 `$7FFD-$7FFF`）上的一个锁存器
+pub struct Discrete {
+  board: Board,
+  prg_rom: Banks,
+  chr: Banks,
+
+  /// 只有 NINA-001 有 PRG RAM
+  prg_ram: Option<Box<[u8; 0x2000]>>,
+
+  mirroring: Mirroring,
+}
+
+impl Discrete {
+  pub fn new(board: Board, cartridge: Cartridge) -> Self {
+    let mut mapper = Discrete {
+      board,
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x4000, false),
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x1000),
+      prg_ram: if board == Board::Nina001 { Some(Box::new([0; 0x2000])) } else { None },
+      mirroring: cartridge.nametable_mirroring,
+    };
+    if board == Board::UxRom {
+      mapper.prg_rom.set_from_end(1, 1);
+    } else {
+      mapper.select_prg_32k(0);
+    }
+    if board == Board::AxRom {
+      mapper.mirroring = Mirroring::SingleScreenLower;
+    }
+    return mapper;
+  }
+
+  /// mapper 34 的两种卡带由 NES 2.0 的子 mapper 区分：1 是 NINA-001，2 是 BNROM。
+  /// 子 mapper 0 或 iNES 文件按 CHR 大小猜测：BNROM 只有 8KB CHR，NINA-001 的 CHR ROM 更大
+  pub fn mapper_34(cartridge: Cartridge) -> Self {
+    let board = match cartridge.submapper {
+      1 => Board::Nina001,
+      2 => Board::BnRom,
+      _ if cartridge.chr_rom.len() > 0x2000 => Board::Nina001,
+      _ => Board::BnRom,
+    };
+    return Discrete::new(board, cartridge);
+  }
+
+  fn select_prg_32k(&mut self, bank: usize) {
+    self.prg_rom.set(0, bank * 2);
+    self.prg_rom.set(1, bank * 2 + 1);
+  }
+
+  fn select_chr_8k(&mut self, bank: usize) {
+    self.chr.set(0, bank * 2);
+    self.chr.set(1, bank * 2 + 1);
+  }
+
+  fn write_latch(&mut self, data: u8) {
+    let data = data as usize;
+    match self.board {
+      Board::UxRom => self.prg_rom.set(0, data),
+      Board::CnRom => self.select_chr_8k(data),
+      Board::AxRom => {
+        self.select_prg_32k(data & 0x07);
+        self.mirroring = if data & 0x10 == 0 { Mirroring::SingleScreenLower } else { Mirroring::SingleScreenUpper };
+      }
+      Board::GxRom => {
+        self.select_prg_32k((data >> 4) & 0x03);
+        self.select_chr_8k(data & 0x03);
+      }
+      Board::ColorDreams => {
+        self.select_prg_32k(data & 0x03);
+        self.select_chr_8k(data >> 4);
+      }
+      Board::BnRom => self.select_prg_32k(data),
+      Board::Nina001 => {}
+    }
+  }
+}
+
+impl Mapper for Discrete {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match (address, &self.prg_ram) {
+      (0x6000..=0x7FFF, Some(ram)) => ram[(address & 0x1FFF) as usize],
+      (0x8000..=0xFFFF, _) => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF => {
+        if let Some(ram) = &mut self.prg_ram {
+          ram[(address & 0x1FFF) as usize] = data;
+          match address {
+            0x7FFD => self.select_prg_32k((data & 0x01) as usize),
+            0x7FFE => self.chr.set(0, (data & 0x0F) as usize),
+            0x7FFF => self.chr.set(1, (data & 0x0F) as usize),
+            _ => {}
+          }
+        }
+      }
+      0x8000..=0xFFFF => {
+        let data = if self.board.has_bus_conflicts() { data & self.cpu_peek(address) } else { data };
+        self.write_latch(data);
+      }
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  /// 把 ROM 全部填成 $FF 的 16 KiB bank，避免写入时的总线冲突
+  fn conflict_free(mut cartridge: Cartridge, last_bank_value: u8) -> Cartridge {
+    let len = cartridge.prg_rom.len();
+    cartridge.prg_rom[len - 0x4000..].fill(last_bank_value);
+    return cartridge;
+  }
+
+  #[test]
+  fn test_uxrom() {
+    let mut mapper = Discrete::new(Board::UxRom, conflict_free(banked_cartridge(2, 0x4000, 8, 0x2000, 0), 0xFF));
+    assert_eq!(mapper.cpu_peek(0x8000), 0);
+    assert_eq!(mapper.cpu_peek(0xC000), 0xFF);
+
+    mapper.cpu_write(0xC000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), 5);
+
+    // 总线冲突：ROM 在 $8000 输出 5，写入 6 的结果是 4
+    mapper.cpu_write(0x8000, 6);
+    assert_eq!(mapper.cpu_peek(0x8000), 4);
+
+    mapper.ppu_write(0x0100, 0x42);
+    assert_eq!(mapper.ppu_read(0x0100), 0x42);
+  }
+
+  #[test]
+  fn test_cnrom() {
+    let mut mapper = Discrete::new(Board::CnRom, conflict_free(banked_cartridge(3, 0x4000, 2, 0x2000, 4), 0xFF));
+    mapper.cpu_write(0xC000, 3);
+    assert_eq!(mapper.ppu_read(0x0000), 3);
+    assert_eq!(mapper.ppu_read(0x1FFF), 3);
+
+    // CHR ROM 不可写
+    mapper.ppu_write(0x0000, 0x42);
+    assert_eq!(mapper.ppu_read(0x0000), 3);
+  }
+
+  #[test]
+  fn test_axrom_switches_32k_and_single_screen() {
+    let mut mapper = Discrete::new(Board::AxRom, banked_cartridge(7, 0x8000, 8, 0x2000, 0));
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+    // 没有总线冲突
+    mapper.cpu_write(0x8000, 0x13);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xFFFF), 3);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+  }
+
+  #[test]
+  fn test_gxrom_and_color_dreams() {
+    let mut gxrom = Discrete::new(Board::GxRom, banked_cartridge(66, 0x8000, 4, 0x2000, 4));
+    gxrom.cpu_write(0x8000, 0x21);
+    // ROM 在 $8000 输出 0，总线冲突把写入的值变成 0
+    assert_eq!(gxrom.cpu_peek(0x8000), 0);
+
+    let mut cartridge = banked_cartridge(66, 0x8000, 4, 0x2000, 4);
+    cartridge.prg_rom.iter_mut().step_by(0x8000).for_each(|byte| *byte = 0xFF);
+    let mut gxrom = Discrete::new(Board::GxRom, cartridge);
+    gxrom.cpu_write(0x8000, 0x21);
+    assert_eq!(gxrom.cpu_peek(0x8001), 2);
+    assert_eq!(gxrom.ppu_read(0x0000), 1);
+
+    let mut cartridge = banked_cartridge(11, 0x8000, 4, 0x2000, 16);
+    cartridge.prg_rom.iter_mut().skip(0x7FFF).step_by(0x8000).for_each(|byte| *byte = 0xFF);
+    let mut color_dreams = Discrete::new(Board::ColorDreams, cartridge);
+    color_dreams.cpu_write(0xFFFF, 0x52);
+    assert_eq!(color_dreams.cpu_peek(0x8000), 2);
+    assert_eq!(color_dreams.ppu_read(0x0000), 5);
+  }
+
+  #[test]
+  fn test_mapper_34_boards() {
+    let mut cartridge = banked_cartridge(34, 0x8000, 4, 0x2000, 0);
+    cartridge.prg_rom.fill(0xFF);
+    cartridge.prg_rom[0x10000] = 2;
+    let mut bnrom = Discrete::mapper_34(cartridge);
+    assert_eq!(bnrom.board, Board::BnRom);
+    bnrom.cpu_write(0x8000, 2);
+    assert_eq!(bnrom.cpu_peek(0x8000), 2);
+
+    let mut nina = Discrete::mapper_34(banked_cartridge(34, 0x8000, 2, 0x1000, 16));
+    assert_eq!(nina.board, Board::Nina001);
+    nina.cpu_write(0x7FFD, 1);
+    nina.cpu_write(0x7FFE, 9);
+    nina.cpu_write(0x7FFF, 12);
+    assert_eq!(nina.cpu_peek(0x8000), 1);
+    assert_eq!(nina.ppu_read(0x0000), 9);
+    assert_eq!(nina.ppu_read(0x1000), 12);
+    assert_eq!(nina.cpu_peek(0x7FFF), 12);
+  }
+
+  #[test]
+  fn test_mapper_34_board_from_submapper() {
+    let mut cartridge = banked_cartridge(34, 0x8000, 2, 0x2000, 1);
+    cartridge.submapper = 1;
+    assert_eq!(Discrete::mapper_34(cartridge).board, Board::Nina001);
+
+    let mut cartridge = banked_cartridge(34, 0x8000, 2, 0x1000, 16);
+    cartridge.submapper = 2;
+    assert_eq!(Discrete::mapper_34(cartridge).board, Board::BnRom);
+  }
+}
@@ -0,0 +1,283 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+  /// 128 字节，起始条件之后直接发送 7 位字地址和读写位，地址和数据都是低位在前
+  X24C01,
+  /// 256 字节，标准 I²C：设备地址、字地址、数据，高位在前
+  X24C02,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+  /// 等待起始条件
+  Idle,
+  /// 24C02 的设备地址和读写位
+  Device,
+  /// 字地址，24C01 还包括读写位
+  Address,
+  Write,
+  Read,
+}
+
+/// Bandai 卡带上保存进度的 [24C01/24C02 串行 EEPROM](https://www.nesdev.org/wiki/Bandai_FCG_board#Serial_EEPROM)。
+///
+/// CPU 通过 mapper 的寄存器直接控制时钟线 SCL 和数据线 SDA：SCL 为高时 SDA 下降是起始条件，
+/// SDA 上升是停止条件；其余时间 SCL 的上升沿读入一位，每个字节之后的第 9 个时钟是应答位。
+pub struct Eeprom {
+  model: Model,
+  data: Vec<u8>,
+  state: State,
+  /// 当前字节完成之后进入的状态
+  next: State,
+
+  scl: bool,
+  sda: bool,
+  /// EEPROM 输出到 SDA 的电平，高电平（释放）时读出主机自己写入的值
+  output: bool,
+
+  address: u8,
+  shift: u8,
+  /// 当前字节中已经传送的位数，第 9 位是应答位
+  bit: u8,
+}
+
+impl Eeprom {
+  pub fn new(model: Model) -> Self {
+    let size = match model {
+      Model::X24C01 => 0x80,
+      Model::X24C02 => 0x100,
+    };
+    return Eeprom {
+      model,
+      data: vec![0; size],
+      state: State::Idle,
+      next: State::Idle,
+      scl: false,
+      sda: false,
+      output: true,
+      address: 0,
+      shift: 0,
+      bit: 0,
+    };
+  }
+
+  pub fn data(&self) -> &[u8] {
+    return &self.data;
+  }
+
+  /// 从存档恢复 EEPROM 的内容，长度不一致时只复制重叠的部分
+  pub fn load(&mut self, data: &[u8]) {
+    let length = data.len().min(self.data.len());
+    self.data[..length].copy_from_slice(&data[..length]);
+  }
+
+  /// SDA 线上的电平，EEPROM 和主机都可以拉低
+  pub fn read(&self) -> bool {
+    return self.output && self.sda;
+  }
+
+  /// 主机设置 SCL 和 SDA 的电平
+  pub fn write(&mut self, scl: bool, sda: bool) {
+    if self.scl && scl && sda != self.sda {
+      if sda {
+        self.stop();
+      } else {
+        self.start();
+      }
+    } else if !self.scl && scl {
+      self.rise(sda);
+    } else if self.scl && !scl {
+      self.fall();
+    }
+    self.scl = scl;
+    self.sda = sda;
+  }
+
+  fn start(&mut self) {
+    self.state = match self.model {
+      Model::X24C01 => State::Address,
+      Model::X24C02 => State::Device,
+    };
+    self.shift = 0;
+    self.bit = 0;
+    self.output = true;
+  }
+
+  fn stop(&mut self) {
+    self.state = State::Idle;
+    self.output = true;
+  }
+
+  fn lsb_first(&self) -> bool {
+    return self.model == Model::X24C01;
+  }
+
+  fn size_mask(&self) -> u8 {
+    return (self.data.len() - 1) as u8;
+  }
+
+  /// 时钟上升沿：读入主机发送的一位，或者主机读走了 EEPROM 输出的一位
+  fn rise(&mut self, sda: bool) {
+    if self.state == State::Idle {
+      return;
+    }
+    if self.bit < 8 {
+      if self.state != State::Read {
+        if self.lsb_first() {
+          self.shift |= (sda as u8) << self.bit;
+        } else {
+          self.shift = (self.shift << 1) | sda as u8;
+        }
+      }
+    } else if self.bit == 8 && self.state == State::Read {
+      // 主机在第 9 个时钟应答，不应答则结束读取
+      self.next = if sda { State::Idle } else { State::Read };
+    }
+    self.bit += 1;
+  }
+
+  /// 时钟下降沿：EEPROM 改变自己的输出
+  fn fall(&mut self) {
+    match (self.state, self.bit) {
+      (State::Idle, _) | (_, 0) => {}
+      (State::Read, 1..=7) => self.output = self.read_bit(),
+      (_, 1..=7) => {}
+      // 释放 SDA，等待主机应答
+      (State::Read, 8) => self.output = true,
+      (_, 8) => self.receive(),
+      (state, _) => {
+        if state == State::Read && self.next == State::Read {
+          self.address = self.address.wrapping_add(1) & self.size_mask();
+        }
+        self.state = self.next;
+        self.bit = 0;
+        self.shift = 0;
+        self.output = if self.state == State::Read { self.read_bit() } else { true };
+      }
+    }
+  }
+
+  /// 收到一个完整的字节，拉低 SDA 应答
+  fn receive(&mut self) {
+    let byte = self.shift;
+    self.next = match (self.state, self.model) {
+      (State::Device, _) if byte & 0xF0 != 0xA0 => State::Idle,
+      (State::Device, _) => if byte & 0x01 != 0 { State::Read } else { State::Address },
+      (State::Address, Model::X24C01) => {
+        self.address = byte & 0x7F;
+        if byte & 0x80 != 0 { State::Read } else { State::Write }
+      }
+      (State::Address, _) => {
+        self.address = byte;
+        State::Write
+      }
+      (_, model) => {
+        self.data[self.address as usize] = byte;
+        // 连续写入时地址只在一页内回绕，24C01 一页 4 字节，24C02 一页 8 字节
+        let page = if model == Model::X24C01 { 0x03 } else { 0x07 };
+        self.address = (self.address & !page) | (self.address.wrapping_add(1) & page);
+        State::Write
+      }
+    };
+    self.output = self.next == State::Idle;
+  }
+
+  fn read_bit(&self) -> bool {
+    let byte = self.data[self.address as usize];
+    let bit = if self.lsb_first() { self.bit } else { 7 - self.bit };
+    return (byte >> bit) & 0x01 != 0;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// 按 I²C 时序操作 EEPROM 的主机
+  struct Host<'a>(&'a mut Eeprom);
+
+  impl Host<'_> {
+    fn start(&mut self) {
+      self.0.write(false, true);
+      self.0.write(true, true);
+      self.0.write(true, false);
+      self.0.write(false, false);
+    }
+
+    fn stop(&mut self) {
+      self.0.write(false, false);
+      self.0.write(true, false);
+      self.0.write(true, true);
+    }
+
+    /// 发送一位，返回时钟为高时 SDA 上的电平
+    fn clock(&mut self, sda: bool) -> bool {
+      self.0.write(false, sda);
+      self.0.write(true, sda);
+      let line = self.0.read();
+      self.0.write(false, sda);
+      return line;
+    }
+
+    /// 发送一个字节，返回 EEPROM 是否应答
+    fn send(&mut self, byte: u8, lsb_first: bool) -> bool {
+      for i in 0..8 {
+        let bit = if lsb_first { i } else { 7 - i };
+        self.clock((byte >> bit) & 0x01 != 0);
+      }
+      return !self.clock(true);
+    }
+
+    fn receive(&mut self, lsb_first: bool, ack: bool) -> u8 {
+      let mut byte = 0;
+      for i in 0..8 {
+        let bit = if lsb_first { i } else { 7 - i };
+        byte |= (self.clock(true) as u8) << bit;
+      }
+      self.clock(!ack);
+      return byte;
+    }
+  }
+
+  #[test]
+  fn test_24c02_write_and_random_read() {
+    let mut eeprom = Eeprom::new(Model::X24C02);
+    let mut host = Host(&mut eeprom);
+    host.start();
+    assert!(host.send(0xA0, false));
+    assert!(host.send(0x10, false));
+    assert!(host.send(0x12, false));
+    assert!(host.send(0x34, false));
+    host.stop();
+
+    host.start();
+    assert!(host.send(0xA0, false));
+    assert!(host.send(0x10, false));
+    host.start();
+    assert!(host.send(0xA1, false));
+    assert_eq!(host.receive(false, true), 0x12);
+    assert_eq!(host.receive(false, false), 0x34);
+    host.stop();
+
+    // 设备地址不对时不应答
+    host.start();
+    assert!(!host.send(0x50, false));
+    host.stop();
+    assert_eq!(&eeprom.data()[0x10..0x12], &[0x12, 0x34]);
+  }
+
+  #[test]
+  fn test_24c01_lsb_first() {
+    let mut eeprom = Eeprom::new(Model::X24C01);
+    let mut host = Host(&mut eeprom);
+    host.start();
+    assert!(host.send(0x05, true));
+    assert!(host.send(0x81, true));
+    host.stop();
+
+    host.start();
+    assert!(host.send(0x80 | 0x05, true));
+    assert_eq!(host.receive(true, false), 0x81);
+    host.stop();
+    assert_eq!(eeprom.data()[0x05], 0x81);
+  }
+}
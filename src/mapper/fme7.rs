@@ -0,0 +1,202 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [Sunsoft FME-7](https://www.nesdev.org/wiki/Sunsoft_FME-7)（mapper 69，包括 5A、5B）
+///
+/// | 地址            | 寄存器                                 |
+/// |-----------------|---------------------------------------|
+/// | `$8000-$9FFF`   | 命令：选择下面的哪一个内部寄存器         |
+/// | `$A000-$BFFF`   | 参数：写入选中的内部寄存器               |
+/// | `$C000-$FFFF`   | 5B 的扩展音频                          |
+///
+/// | 命令    | 内部寄存器                                                     |
+/// |--------|---------------------------------------------------------------|
+/// | `$0-7` | 1 KiB CHR bank 0-7                                            |
+/// | `$8`   | `$6000` 的 8 KiB bank，第 6 位选择 RAM，第 7 位开启 RAM           |
+/// | `$9-B` | `$8000`、`$A000`、`$C000` 的 8 KiB PRG bank，`$E000` 固定          |
+/// | `$C`   | 镜像                                                          |
+/// | `$D`   | IRQ 控制：第 0 位开启 IRQ，第 7 位开启计数器，写入时确认 IRQ        |
+/// | `$E-F` | IRQ 计数器的低 8 位、高 8 位                                    |
+///
+/// IRQ 计数器每个 CPU 周期减一，从 $0000 回绕到 $FFFF 时触发 IRQ。
+pub struct Fme7 {
+  /// `$6000-$FFFF` 的 5 个 8 KiB 槽，第 0 个槽是 `$6000`
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  command: u8,
+  /// 命令 `$8` 的参数
+  prg_6000: u8,
+  mirroring: Mirroring,
+
+  irq_control: u8,
+  irq_counter: u16,
+  irq_pending: bool,
+}
+
+impl Fme7 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let mut mapper = Fme7 {
+      prg_rom: Banks::new(cartridge.prg_rom, 0xA000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      command: 0,
+      prg_6000: 0,
+      mirroring: Mirroring::Vertical,
+      irq_control: 0,
+      irq_counter: 0,
+      irq_pending: false,
+    };
+    mapper.prg_rom.set_from_end(4, 1);
+    return mapper;
+  }
+
+  fn write_parameter(&mut self, data: u8) {
+    match self.command {
+      command @ 0x0..=0x7 => self.chr.set(command as usize, data as usize),
+      0x8 => {
+        self.prg_6000 = data;
+        if data & 0x40 == 0 {
+          self.prg_rom.set(0, (data & 0x3F) as usize);
+        } else {
+          self.prg_ram.set(0, (data & 0x3F) as usize);
+        }
+      }
+      command @ 0x9..=0xB => self.prg_rom.set((command - 0x8) as usize, (data & 0x3F) as usize),
+      0xC => {
+        self.mirroring = match data & 0x03 {
+          0 => Mirroring::Vertical,
+          1 => Mirroring::Horizontal,
+          2 => Mirroring::SingleScreenLower,
+          _ => Mirroring::SingleScreenUpper,
+        };
+      }
+      0xD => {
+        self.irq_control = data;
+        self.irq_pending = false;
+      }
+      0xE => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+      _ => self.irq_counter = (self.irq_counter & 0x00FF) | ((data as u16) << 8),
+    }
+  }
+
+  fn prg_ram_selected(&self) -> bool {
+    return self.prg_6000 & 0x40 != 0;
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    return self.prg_6000 & 0xC0 == 0xC0;
+  }
+}
+
+impl Mapper for Fme7 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read((address & 0x1FFF) as usize),
+      // 选择了 RAM 但是没有开启时是开路总线
+      0x6000..=0x7FFF if self.prg_ram_selected() => 0,
+      0x6000..=0xFFFF => self.prg_rom.read((address - 0x6000) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0x8000..=0x9FFF => self.command = data & 0x0F,
+      0xA000..=0xBFFF => self.write_parameter(data),
+      // 5B 的扩展音频，模拟器还没有 APU
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq_pending;
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    if self.irq_control & 0x80 == 0 {
+      return;
+    }
+    for _ in 0..cycles {
+      self.irq_counter = self.irq_counter.wrapping_sub(1);
+      if self.irq_counter == 0xFFFF && self.irq_control & 0x01 != 0 {
+        self.irq_pending = true;
+      }
+    }
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  fn write(mapper: &mut Fme7, command: u8, parameter: u8) {
+    mapper.cpu_write(0x8000, command);
+    mapper.cpu_write(0xA000, parameter);
+  }
+
+  #[test]
+  fn test_banks() {
+    let mut mapper = Fme7::new(banked_cartridge(69, 0x2000, 16, 0x0400, 16));
+    write(&mut mapper, 0x9, 3);
+    write(&mut mapper, 0xB, 5);
+    write(&mut mapper, 0x2, 7);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xC000), 5);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+    assert_eq!(mapper.ppu_read(0x0800), 7);
+
+    // $6000 可以映射 ROM 或者 RAM
+    write(&mut mapper, 0x8, 9);
+    assert_eq!(mapper.cpu_peek(0x6000), 9);
+    write(&mut mapper, 0x8, 0xC0);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+    write(&mut mapper, 0x8, 0x40);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+  }
+
+  #[test]
+  fn test_irq() {
+    let mut mapper = Fme7::new(banked_cartridge(69, 0x2000, 16, 0x0400, 16));
+    write(&mut mapper, 0xE, 2);
+    write(&mut mapper, 0xF, 0);
+    write(&mut mapper, 0xD, 0x81);
+    mapper.tick(2);
+    assert!(!mapper.irq());
+    mapper.tick(1);
+    assert!(mapper.irq());
+
+    write(&mut mapper, 0xD, 0x80);
+    assert!(!mapper.irq());
+    mapper.tick(0x10000);
+    assert!(!mapper.irq());
+  }
+}
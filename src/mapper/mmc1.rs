@@ -0,0 +1,254 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [MMC1](https://www.nesdev.org/wiki/MMC1)（mapper 1，SxROM）
+///
+/// 寄存器通过 5 位串行移位寄存器写入：向 `$8000-$FFFF` 写 5 次，每次移入第 0 位，
+/// 第 5 次写入时由地址的第 13、14 位选择目标寄存器；写入的值第 7 位为 1 时复位移位寄存器。
+///
+/// | 地址            | 寄存器                         |
+/// |-----------------|--------------------------------|
+/// | `$8000-$9FFF`   | 控制：镜像、PRG 模式、CHR 模式   |
+/// | `$A000-$BFFF`   | CHR bank 0                     |
+/// | `$C000-$DFFF`   | CHR bank 1                     |
+/// | `$E000-$FFFF`   | PRG bank，第 4 位关闭 PRG RAM    |
+///
+/// 512 KiB PRG ROM 的 SUROM/SXROM 用 CHR bank 0 的第 4 位选择 256 KiB 的 PRG ROM，
+/// 16/32 KiB PRG RAM 的 SOROM/SXROM 用第 2-3 位选择 8 KiB 的 PRG RAM bank。
+pub struct Mmc1 {
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  shift_register: u8,
+  shift_count: u8,
+
+  control: u8,
+  chr_bank: [u8; 2],
+  prg_bank: u8,
+}
+
+impl Mmc1 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let mut mapper = Mmc1 {
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x4000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x1000),
+      shift_register: 0,
+      shift_count: 0,
+      // 上电时固定最后一个 PRG bank，复位向量才能找到
+      control: 0x0C,
+      chr_bank: [0; 2],
+      prg_bank: 0,
+    };
+    mapper.update_banks();
+    return mapper;
+  }
+
+  fn write_register(&mut self, address: u16, data: u8) {
+    match address {
+      0x8000..=0x9FFF => self.control = data,
+      0xA000..=0xBFFF => self.chr_bank[0] = data,
+      0xC000..=0xDFFF => self.chr_bank[1] = data,
+      _ => self.prg_bank = data,
+    }
+    self.update_banks();
+  }
+
+  fn update_banks(&mut self) {
+    // SUROM：CHR bank 0 的第 4 位是 PRG ROM 的 A18
+    let outer = if self.prg_rom.bank_count() > 16 { (self.chr_bank[0] & 0x10) as usize } else { 0 };
+    let bank = outer | (self.prg_bank & 0x0F) as usize;
+    match (self.control >> 2) & 0x03 {
+      // 32 KiB 模式忽略最低位
+      0 | 1 => {
+        self.prg_rom.set(0, bank & !1);
+        self.prg_rom.set(1, bank | 1);
+      }
+      // 固定 $8000 为第一个 bank
+      2 => {
+        self.prg_rom.set(0, outer);
+        self.prg_rom.set(1, bank);
+      }
+      // 固定 $C000 为最后一个 bank
+      _ => {
+        self.prg_rom.set(0, bank);
+        self.prg_rom.set(1, outer | 0x0F);
+      }
+    }
+
+    if self.control & 0x10 == 0 {
+      let bank = (self.chr_bank[0] & 0x1E) as usize;
+      self.chr.set(0, bank);
+      self.chr.set(1, bank | 1);
+    } else {
+      self.chr.set(0, self.chr_bank[0] as usize);
+      self.chr.set(1, self.chr_bank[1] as usize);
+    }
+
+    // SOROM 用第 3 位，SXROM 用第 2-3 位
+    let ram_bank = match self.prg_ram.bank_count() {
+      2 => (self.chr_bank[0] >> 3) & 0x01,
+      _ => (self.chr_bank[0] >> 2) & 0x03,
+    };
+    self.prg_ram.set(0, ram_bank as usize);
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    return self.prg_bank & 0x10 == 0;
+  }
+}
+
+impl Mapper for Mmc1 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0x8000..=0xFFFF => {
+        if data & 0x80 != 0 {
+          self.shift_register = 0;
+          self.shift_count = 0;
+          self.control |= 0x0C;
+          self.update_banks();
+          return;
+        }
+        self.shift_register |= (data & 0x01) << self.shift_count;
+        self.shift_count += 1;
+        if self.shift_count == 5 {
+          let value = self.shift_register;
+          self.shift_register = 0;
+          self.shift_count = 0;
+          self.write_register(address, value);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return match self.control & 0x03 {
+      0 => Mirroring::SingleScreenLower,
+      1 => Mirroring::SingleScreenUpper,
+      2 => Mirroring::Vertical,
+      _ => Mirroring::Horizontal,
+    };
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  fn write_serial(mapper: &mut Mmc1, address: u16, value: u8) {
+    for bit in 0..5 {
+      mapper.cpu_write(address, (value >> bit) & 0x01);
+    }
+  }
+
+  #[test]
+  fn test_prg_modes() {
+    let mut mapper = Mmc1::new(banked_cartridge(1, 0x4000, 8, 0x1000, 4));
+    assert_eq!(mapper.cpu_peek(0x8000), 0);
+    assert_eq!(mapper.cpu_peek(0xC000), 7);
+
+    write_serial(&mut mapper, 0xE000, 3);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xC000), 7);
+
+    // 固定 $8000
+    write_serial(&mut mapper, 0x8000, 0b01000);
+    assert_eq!(mapper.cpu_peek(0x8000), 0);
+    assert_eq!(mapper.cpu_peek(0xC000), 3);
+
+    // 32 KiB
+    write_serial(&mut mapper, 0x8000, 0b00000);
+    assert_eq!(mapper.cpu_peek(0x8000), 2);
+    assert_eq!(mapper.cpu_peek(0xC000), 3);
+
+    // 复位把 PRG 模式恢复为固定最后一个 bank
+    mapper.cpu_write(0x8000, 0x80);
+    assert_eq!(mapper.cpu_peek(0xC000), 7);
+  }
+
+  #[test]
+  fn test_chr_modes_and_mirroring() {
+    let mut mapper = Mmc1::new(banked_cartridge(1, 0x4000, 2, 0x1000, 8));
+    write_serial(&mut mapper, 0xA000, 5);
+    write_serial(&mut mapper, 0xC000, 2);
+    // 8 KiB 模式忽略最低位和 CHR bank 1
+    assert_eq!(mapper.ppu_read(0x0000), 4);
+    assert_eq!(mapper.ppu_read(0x1000), 5);
+
+    write_serial(&mut mapper, 0x8000, 0b11110);
+    assert_eq!(mapper.ppu_read(0x0000), 5);
+    assert_eq!(mapper.ppu_read(0x1000), 2);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+    write_serial(&mut mapper, 0x8000, 0b11101);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+  }
+
+  #[test]
+  fn test_prg_ram_enable() {
+    let mut mapper = Mmc1::new(banked_cartridge(1, 0x4000, 2, 0x1000, 2));
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+
+    write_serial(&mut mapper, 0xE000, 0x10);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+    mapper.cpu_write(0x6000, 0x11);
+
+    write_serial(&mut mapper, 0xE000, 0x00);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+  }
+
+  #[test]
+  fn test_surom_and_sxrom_use_chr_bank_as_outer_bank() {
+    let mut cartridge = banked_cartridge(1, 0x4000, 32, 0x1000, 0);
+    cartridge.prg_ram_size = 0x8000;
+    let mut mapper = Mmc1::new(cartridge);
+    assert_eq!(mapper.cpu_peek(0xC000), 15);
+
+    write_serial(&mut mapper, 0xA000, 0x10);
+    write_serial(&mut mapper, 0xE000, 2);
+    assert_eq!(mapper.cpu_peek(0x8000), 18);
+    assert_eq!(mapper.cpu_peek(0xC000), 31);
+
+    mapper.cpu_write(0x6000, 0x11);
+    write_serial(&mut mapper, 0xA000, 0x0C);
+    assert_eq!(mapper.cpu_peek(0x8000), 2);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+    mapper.cpu_write(0x6000, 0x33);
+
+    write_serial(&mut mapper, 0xA000, 0x00);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x11);
+  }
+}
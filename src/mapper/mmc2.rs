@@ -0,0 +1,176 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [MMC2](https://www.nesdev.org/wiki/MMC2)（mapper 9）和 [MMC4](https://www.nesdev.org/wiki/MMC4)（mapper 10）
+///
+/// | 地址            | 寄存器                                                     |
+/// |-----------------|-----------------------------------------------------------|
+/// | `$A000-$AFFF`   | MMC2：`$8000` 的 8 KiB PRG bank；MMC4：16 KiB PRG bank        |
+/// | `$B000-$BFFF`   | 锁存器 0 为 $FD 时 `$0000` 的 4 KiB CHR bank                  |
+/// | `$C000-$CFFF`   | 锁存器 0 为 $FE 时 `$0000` 的 4 KiB CHR bank                  |
+/// | `$D000-$DFFF`   | 锁存器 1 为 $FD 时 `$1000` 的 4 KiB CHR bank                  |
+/// | `$E000-$EFFF`   | 锁存器 1 为 $FE 时 `$1000` 的 4 KiB CHR bank                  |
+/// | `$F000-$FFFF`   | 镜像                                                       |
+///
+/// PPU 取图块 $FD 或 $FE 的最后一行时切换锁存器，新的 bank 从下一次读取开始生效。
+/// MMC2 的锁存器 0 只认 `$0FD8`、`$0FE8` 这两个地址，其余情形认 `$xFD8-$xFDF`、`$xFE8-$xFEF`。
+/// 只有 MMC4 的卡带有 PRG RAM。
+pub struct Mmc2 {
+  mmc4: bool,
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  /// `$B000-$E000` 的 4 个 CHR bank
+  chr_banks: [u8; 4],
+  /// 两个锁存器是否为 $FE
+  latches: [bool; 2],
+  mirroring: Mirroring,
+}
+
+impl Mmc2 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let mut mapper = Mmc2 {
+      mmc4: cartridge.mapper == 10,
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x1000),
+      chr_banks: [0; 4],
+      latches: [true; 2],
+      mirroring: Mirroring::Vertical,
+    };
+    mapper.select_prg(0);
+    mapper.prg_rom.set_from_end(2, 2);
+    mapper.prg_rom.set_from_end(3, 1);
+    mapper.update_chr();
+    return mapper;
+  }
+
+  fn select_prg(&mut self, bank: u8) {
+    if self.mmc4 {
+      let bank = (bank & 0x0F) as usize * 2;
+      self.prg_rom.set(0, bank);
+      self.prg_rom.set(1, bank + 1);
+    } else {
+      self.prg_rom.set(0, (bank & 0x0F) as usize);
+      self.prg_rom.set_from_end(1, 3);
+    }
+  }
+
+  fn update_chr(&mut self) {
+    for slot in 0..2 {
+      let bank = self.chr_banks[slot * 2 + self.latches[slot] as usize];
+      self.chr.set(slot, bank as usize);
+    }
+  }
+
+  fn update_latches(&mut self, address: u16) {
+    let slot = (address >> 12) as usize;
+    let row = if slot == 0 && !self.mmc4 { address & 0x0FFF } else { address & 0x0FF8 };
+    let latch = match row {
+      0x0FD8 => false,
+      0x0FE8 => true,
+      _ => return,
+    };
+    if self.latches[slot] != latch {
+      self.latches[slot] = latch;
+      self.update_chr();
+    }
+  }
+}
+
+impl Mapper for Mmc2 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.mmc4 => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF if self.mmc4 => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0xA000..=0xAFFF => self.select_prg(data),
+      0xB000..=0xEFFF => {
+        self.chr_banks[((address - 0xB000) >> 12) as usize] = data & 0x1F;
+        self.update_chr();
+      }
+      0xF000..=0xFFFF => {
+        self.mirroring = if data & 0x01 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+      }
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    let data = self.chr.read(address as usize);
+    self.update_latches(address);
+    return data;
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return (self.mmc4 && self.battery).then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_prg_banks() {
+    let mut mapper = Mmc2::new(banked_cartridge(9, 0x2000, 16, 0x1000, 32));
+    mapper.cpu_write(0xA000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), 5);
+    assert_eq!(mapper.cpu_peek(0xA000), 13);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+
+    let mut mapper = Mmc2::new(banked_cartridge(10, 0x2000, 16, 0x1000, 32));
+    mapper.cpu_write(0xA000, 2);
+    assert_eq!(mapper.cpu_peek(0x8000), 4);
+    assert_eq!(mapper.cpu_peek(0xA000), 5);
+    assert_eq!(mapper.cpu_peek(0xC000), 14);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+  }
+
+  #[test]
+  fn test_chr_latches() {
+    let mut mapper = Mmc2::new(banked_cartridge(9, 0x2000, 16, 0x1000, 32));
+    for (register, bank) in [(0xB000, 1), (0xC000, 2), (0xD000, 3), (0xE000, 4)] {
+      mapper.cpu_write(register, bank);
+    }
+    assert_eq!(mapper.ppu_read(0x0000), 2);
+    assert_eq!(mapper.ppu_read(0x1000), 4);
+
+    // 读取图块 $FD 的最后一行之后才切换
+    assert_eq!(mapper.ppu_read(0x0FD8), 2);
+    assert_eq!(mapper.ppu_read(0x0000), 1);
+    mapper.ppu_read(0x1FDF);
+    assert_eq!(mapper.ppu_read(0x1000), 3);
+
+    // MMC2 的锁存器 0 只认 $0FE8
+    mapper.ppu_read(0x0FE9);
+    assert_eq!(mapper.ppu_read(0x0000), 1);
+    mapper.ppu_read(0x0FE8);
+    assert_eq!(mapper.ppu_read(0x0000), 2);
+  }
+}
@@ -0,0 +1,343 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// A12 至少要保持低电平这么多个 PPU 时钟（约 3 个 CPU 周期），上升沿才会计数。
+/// 背景和精灵用同一张图案表时，取命名表造成的短暂低电平会被过滤掉
+const A12_FILTER_DOTS: u64 = 10;
+
+/// MMC3 芯片的版本，两者的 IRQ 计数器在重新装载为 0 时表现不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revision {
+  /// MMC3B/MMC3C（Sharp）：计数器在时钟之后为 0 就触发 IRQ，锁存值为 0 时每条扫描线都触发
+  Sharp,
+
+  /// MMC3A（NEC）：只有从 1 减到 0 才触发 IRQ，重新装载为 0 不触发
+  Nec,
+}
+
+/// [MMC3](https://www.nesdev.org/wiki/MMC3)（mapper 4，TxROM）
+///
+/// | 地址（偶数）     | 寄存器                          | 地址（奇数）     | 寄存器                      |
+/// |-----------------|--------------------------------|-----------------|----------------------------|
+/// | `$8000-$9FFE`   | bank 选择、PRG 模式、CHR 反转    | `$8001-$9FFF`   | bank 数据                   |
+/// | `$A000-$BFFE`   | 镜像                            | `$A001-$BFFF`   | PRG RAM 保护                |
+/// | `$C000-$DFFE`   | IRQ 锁存值                      | `$C001-$DFFF`   | IRQ 重新装载                 |
+/// | `$E000-$FFFE`   | 关闭并确认 IRQ                   | `$E001-$FFFF`   | 开启 IRQ                    |
+///
+/// IRQ 计数器由 PPU A12 的上升沿驱动，背景用 `$0000`、精灵用 `$1000` 时每条扫描线计数一次。
+pub struct Mmc3 {
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+  four_screen: bool,
+
+  bank_select: u8,
+  registers: [u8; 8],
+  mirroring: Mirroring,
+  prg_ram_protect: u8,
+
+  revision: Revision,
+  irq_latch: u8,
+  irq_counter: u8,
+  irq_reload: bool,
+  irq_enabled: bool,
+  irq_pending: bool,
+
+  /// 上一次看到的 A12 电平，以及它最近一次变为低电平的时间
+  a12: bool,
+  a12_low_since: u64,
+}
+
+impl Mmc3 {
+  /// NES 2.0 文件头的子 mapper 4 是 MMC3A，其余按 MMC3B/MMC3C 处理
+  pub fn new(cartridge: Cartridge) -> Self {
+    let revision = match cartridge.submapper {
+      4 => Revision::Nec,
+      _ => Revision::Sharp,
+    };
+    return Mmc3::with_revision(cartridge, revision);
+  }
+
+  pub fn with_revision(cartridge: Cartridge, revision: Revision) -> Self {
+    let four_screen = cartridge.nametable_mirroring == Mirroring::FourScreen;
+    let mut mapper = Mmc3 {
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      four_screen,
+      bank_select: 0,
+      registers: [0, 2, 4, 5, 6, 7, 0, 1],
+      mirroring: cartridge.nametable_mirroring,
+      prg_ram_protect: 0x80,
+      revision,
+      irq_latch: 0,
+      irq_counter: 0,
+      irq_reload: false,
+      irq_enabled: false,
+      irq_pending: false,
+      a12: false,
+      a12_low_since: 0,
+    };
+    mapper.update_banks();
+    return mapper;
+  }
+
+  fn update_banks(&mut self) {
+    // R6、R7 只有 6 位
+    let r6 = (self.registers[6] & 0x3F) as usize;
+    let r7 = (self.registers[7] & 0x3F) as usize;
+    if self.bank_select & 0x40 == 0 {
+      self.prg_rom.set(0, r6);
+      self.prg_rom.set_from_end(2, 2);
+    } else {
+      self.prg_rom.set_from_end(0, 2);
+      self.prg_rom.set(2, r6);
+    }
+    self.prg_rom.set(1, r7);
+    self.prg_rom.set_from_end(3, 1);
+
+    // CHR 反转时交换 $0000 和 $1000 两半，R0、R1 是 2 KiB bank，忽略最低位
+    let base = if self.bank_select & 0x80 == 0 { 0 } else { 4 };
+    for i in 0..2 {
+      let bank = (self.registers[i] & 0xFE) as usize;
+      self.chr.set(base + i * 2, bank);
+      self.chr.set(base + i * 2 + 1, bank | 1);
+    }
+    for i in 0..4 {
+      self.chr.set((base + 4 + i) % 8, self.registers[2 + i] as usize);
+    }
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    return self.prg_ram_protect & 0x80 != 0;
+  }
+
+  fn prg_ram_writable(&self) -> bool {
+    return self.prg_ram_enabled() && self.prg_ram_protect & 0x40 == 0;
+  }
+
+  fn write_register(&mut self, address: u16, data: u8) {
+    match (address & 0xE000, address & 0x01) {
+      (0x8000, 0) => {
+        self.bank_select = data;
+        self.update_banks();
+      }
+      (0x8000, _) => {
+        self.registers[(self.bank_select & 0x07) as usize] = data;
+        self.update_banks();
+      }
+      (0xA000, 0) => {
+        if !self.four_screen {
+          self.mirroring = if data & 0x01 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+        }
+      }
+      (0xA000, _) => self.prg_ram_protect = data,
+      (0xC000, 0) => self.irq_latch = data,
+      (0xC000, _) => {
+        self.irq_counter = 0;
+        self.irq_reload = true;
+      }
+      (0xE000, 0) => {
+        self.irq_enabled = false;
+        self.irq_pending = false;
+      }
+      _ => self.irq_enabled = true,
+    }
+  }
+
+  /// A12 的一个上升沿：计数器为 0 或者要求重新装载时装入锁存值，否则减一
+  fn clock_irq_counter(&mut self) {
+    let previous = self.irq_counter;
+    let reload = std::mem::take(&mut self.irq_reload);
+    if self.irq_counter == 0 || reload {
+      self.irq_counter = self.irq_latch;
+    } else {
+      self.irq_counter -= 1;
+    }
+
+    let trigger = match self.revision {
+      Revision::Sharp => self.irq_counter == 0,
+      Revision::Nec => self.irq_counter == 0 && (previous != 0 || reload),
+    };
+    if trigger && self.irq_enabled {
+      self.irq_pending = true;
+    }
+  }
+}
+
+impl Mapper for Mmc3 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF if self.prg_ram_writable() => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0x8000..=0xFFFF => self.write_register(address, data),
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq_pending;
+  }
+
+  fn ppu_address(&mut self, address: u16, cycle: u64) {
+    let a12 = address & 0x1000 != 0;
+    if a12 && !self.a12 && cycle - self.a12_low_since >= A12_FILTER_DOTS {
+      self.clock_irq_counter();
+    }
+    if !a12 && self.a12 {
+      self.a12_low_since = cycle;
+    }
+    self.a12 = a12;
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  /// 模拟一条扫描线：背景取 `$0000`，然后精灵取 `$1000`
+  fn scanline(mapper: &mut Mmc3, cycle: &mut u64) {
+    mapper.ppu_address(0x0000, *cycle);
+    mapper.ppu_address(0x1000, *cycle + 260);
+    *cycle += 341;
+  }
+
+  #[test]
+  fn test_prg_and_chr_banks() {
+    let mut mapper = Mmc3::new(banked_cartridge(4, 0x2000, 16, 0x0400, 16));
+    mapper.cpu_write(0x8000, 6);
+    mapper.cpu_write(0x8001, 3);
+    mapper.cpu_write(0x8000, 7);
+    mapper.cpu_write(0x8001, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xA000), 5);
+    assert_eq!(mapper.cpu_peek(0xC000), 14);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+
+    // PRG 模式 1 交换 $8000 和 $C000
+    mapper.cpu_write(0x8000, 0x40);
+    assert_eq!(mapper.cpu_peek(0x8000), 14);
+    assert_eq!(mapper.cpu_peek(0xC000), 3);
+
+    mapper.cpu_write(0x8000, 0);
+    mapper.cpu_write(0x8001, 9);
+    mapper.cpu_write(0x8000, 2);
+    mapper.cpu_write(0x8001, 12);
+    assert_eq!(mapper.ppu_read(0x0000), 8);
+    assert_eq!(mapper.ppu_read(0x0400), 9);
+    assert_eq!(mapper.ppu_read(0x1000), 12);
+
+    // CHR 反转
+    mapper.cpu_write(0x8000, 0x80);
+    assert_eq!(mapper.ppu_read(0x1000), 8);
+    assert_eq!(mapper.ppu_read(0x1400), 9);
+    assert_eq!(mapper.ppu_read(0x0000), 12);
+  }
+
+  #[test]
+  fn test_mirroring_and_prg_ram_protect() {
+    let mut mapper = Mmc3::new(banked_cartridge(4, 0x2000, 4, 0x0400, 8));
+    mapper.cpu_write(0xA000, 0);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    mapper.cpu_write(0xA000, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+    mapper.cpu_write(0x6000, 0x42);
+    mapper.cpu_write(0xA001, 0xC0);
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+
+    mapper.cpu_write(0xA001, 0x00);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+  }
+
+  #[test]
+  fn test_scanline_irq() {
+    let mut mapper = Mmc3::new(banked_cartridge(4, 0x2000, 4, 0x0400, 8));
+    let mut cycle = 100;
+    mapper.cpu_write(0xC000, 2);
+    mapper.cpu_write(0xC001, 0);
+    mapper.cpu_write(0xE001, 0);
+
+    // 装载 2，减到 1，再减到 0 时触发
+    scanline(&mut mapper, &mut cycle);
+    scanline(&mut mapper, &mut cycle);
+    assert!(!mapper.irq());
+    scanline(&mut mapper, &mut cycle);
+    assert!(mapper.irq());
+
+    mapper.cpu_write(0xE000, 0);
+    assert!(!mapper.irq());
+  }
+
+  #[test]
+  fn test_a12_filter_ignores_short_low_periods() {
+    let mut mapper = Mmc3::new(banked_cartridge(4, 0x2000, 4, 0x0400, 8));
+    mapper.cpu_write(0xC000, 0);
+    mapper.cpu_write(0xE001, 0);
+
+    // 背景用 $1000 时取命名表只让 A12 低几个时钟
+    mapper.ppu_address(0x1000, 0);
+    mapper.ppu_address(0x2000, 1);
+    mapper.ppu_address(0x1000, 5);
+    assert!(!mapper.irq());
+    mapper.ppu_address(0x0000, 100);
+    mapper.ppu_address(0x1000, 120);
+    assert!(mapper.irq());
+  }
+
+  #[test]
+  fn test_revisions_differ_when_latch_is_zero() {
+    for (revision, expected) in [(Revision::Sharp, true), (Revision::Nec, false)] {
+      let mut mapper = Mmc3::with_revision(banked_cartridge(4, 0x2000, 4, 0x0400, 8), revision);
+      let mut cycle = 100;
+      mapper.cpu_write(0xC000, 0);
+      mapper.cpu_write(0xE001, 0);
+      scanline(&mut mapper, &mut cycle);
+      scanline(&mut mapper, &mut cycle);
+      assert_eq!(mapper.irq(), expected, "{:?}", revision);
+    }
+  }
+
+  #[test]
+  fn test_revision_from_nes2_submapper() {
+    for (submapper, expected) in [(0, Revision::Sharp), (4, Revision::Nec)] {
+      let mut raw = vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x40, 0x08, submapper << 4, 0, 0, 0, 0, 0, 0, 0];
+      raw.resize(16 + 0x8000 + 0x2000, 0);
+      let mapper = Mmc3::new(Cartridge::new(&raw).unwrap());
+      assert_eq!(mapper.revision, expected);
+    }
+  }
+}
@@ -0,0 +1,617 @@
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// 超过这么多个 CPU 周期没有 PPU 读取，MMC5 认为 PPU 停止了渲染
+const IDLE_CYCLES: u64 = 3;
+
+/// 8 KiB 的 PRG 槽映射到 ROM 还是 RAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrgBank {
+  Rom(usize),
+  Ram(usize),
+}
+
+/// 当前背景图块的来源，在取命名表时决定，随后的属性表和图案表读取跟着它走
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+  Normal,
+
+  /// 扩展属性模式下，图块在 ExRAM 中对应的字节：第 0-5 位选择 4 KiB CHR bank，第 6-7 位是调色板
+  Extended(u8),
+
+  /// 垂直分屏区域里的图块，`row` 是分屏内的像素行
+  Split { x: usize, row: usize },
+}
+
+/// [MMC5](https://www.nesdev.org/wiki/MMC5)（mapper 5，ExROM）
+///
+/// | 地址            | 寄存器                                                   |
+/// |-----------------|---------------------------------------------------------|
+/// | `$5100`         | PRG 模式：32K / 16K+16K / 16K+8K+8K / 8K×4               |
+/// | `$5101`         | CHR 模式：8K / 4K / 2K / 1K                              |
+/// | `$5102-$5103`   | PRG RAM 写保护，分别写入 2 和 1 才能写                     |
+/// | `$5104`         | ExRAM 模式：命名表 / 扩展属性 / 普通 RAM / 只读 RAM         |
+/// | `$5105`         | 4 个命名表各自的来源：CIRAM A、CIRAM B、ExRAM、填充         |
+/// | `$5106-$5107`   | 填充模式的图块和属性                                       |
+/// | `$5113-$5117`   | PRG bank，`$5114-$5116` 第 7 位为 0 时映射 PRG RAM         |
+/// | `$5120-$512B`   | CHR bank，`$5120-$5127` 是 A 组，`$5128-$512B` 是 B 组     |
+/// | `$5130`         | CHR bank 的高 2 位                                        |
+/// | `$5200-$5202`   | 垂直分屏：控制、滚动、CHR bank                              |
+/// | `$5203-$5204`   | 扫描线 IRQ：比较值、开启（写）/ 状态（读）                   |
+/// | `$5205-$5206`   | 8 位乘法器，读出 16 位乘积                                  |
+/// | `$5C00-$5FFF`   | 1 KiB ExRAM                                              |
+///
+/// MMC5 看不到 PPU 的扫描线位置，只能监听 PPU 的读取：每条扫描线末尾和开头连续 3 次读取同一个
+/// 命名表地址时认为开始了新的扫描线，再数命名表读取的次数区分背景和精灵的取数阶段。
+/// 8x16 精灵时背景用 B 组 CHR bank，精灵用 A 组。
+pub struct Mmc5 {
+  prg_rom: Vec<u8>,
+  prg_ram: Vec<u8>,
+  battery: bool,
+  chr: Vec<u8>,
+  chr_writable: bool,
+  exram: [u8; 0x400],
+
+  prg_mode: u8,
+  /// `$5113-$5117`
+  prg_registers: [u8; 5],
+  prg_slots: [PrgBank; 4],
+  prg_ram_protect: [u8; 2],
+
+  chr_mode: u8,
+  chr_a: [usize; 8],
+  chr_b: [usize; 4],
+  chr_upper: u8,
+  /// 最后写入的是否是 B 组，使用 8x16 精灵且不在渲染时 PPUDATA 访问用最后写入的一组
+  last_chr_b: bool,
+
+  exram_mode: u8,
+  nametables: u8,
+  fill_tile: u8,
+  fill_attribute: u8,
+
+  split_control: u8,
+  split_scroll: u8,
+  split_bank: u8,
+
+  multiplicand: u8,
+  multiplier: u8,
+
+  irq_compare: u8,
+  irq_enabled: bool,
+  irq_pending: bool,
+
+  /// 从 CPU 写入的 PPUCTRL 得知
+  sprite_8x16: bool,
+
+  in_frame: bool,
+  scanline: u16,
+  last_address: u16,
+  repeat: u8,
+  /// 本条扫描线开始以来读取命名表（不含属性表）的次数
+  fetches: u8,
+  tile: Tile,
+  ppu_active: bool,
+  idle_cycles: u64,
+}
+
+impl Mmc5 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let chr_writable = cartridge.chr_rom.is_empty();
+    let chr = if chr_writable { vec![0; cartridge.chr_ram_size.max(0x2000)] } else { cartridge.chr_rom };
+    let mut mapper = Mmc5 {
+      prg_rom: cartridge.prg_rom,
+      prg_ram: vec![0; (cartridge.prg_ram_size + cartridge.prg_nvram_size).max(0x2000)],
+      battery: cartridge.battery,
+      chr,
+      chr_writable,
+      exram: [0; 0x400],
+      // 上电时是 8K×4 模式，`$5117` 为 $FF，复位向量在最后一个 bank
+      prg_mode: 3,
+      prg_registers: [0xFF; 5],
+      prg_slots: [PrgBank::Rom(0); 4],
+      prg_ram_protect: [0; 2],
+      chr_mode: 0,
+      chr_a: [0; 8],
+      chr_b: [0; 4],
+      chr_upper: 0,
+      last_chr_b: false,
+      exram_mode: 0,
+      nametables: 0,
+      fill_tile: 0,
+      fill_attribute: 0,
+      split_control: 0,
+      split_scroll: 0,
+      split_bank: 0,
+      multiplicand: 0xFF,
+      multiplier: 0xFF,
+      irq_compare: 0,
+      irq_enabled: false,
+      irq_pending: false,
+      sprite_8x16: false,
+      in_frame: false,
+      scanline: 0,
+      last_address: 0xFFFF,
+      repeat: 0,
+      fetches: 0,
+      tile: Tile::Normal,
+      ppu_active: false,
+      idle_cycles: 0,
+    };
+    mapper.update_prg();
+    return mapper;
+  }
+
+  fn update_prg(&mut self) {
+    let [_, r0, r1, r2, r3] = self.prg_registers;
+    let select = |value: u8| {
+      return if value & 0x80 != 0 { PrgBank::Rom((value & 0x7F) as usize) } else { PrgBank::Ram((value & 0x0F) as usize) };
+    };
+    let rom = |value: u8| PrgBank::Rom((value & 0x7F) as usize);
+    self.prg_slots = match self.prg_mode {
+      0 => {
+        let bank = r3 & 0x7C;
+        [rom(bank), rom(bank | 1), rom(bank | 2), rom(bank | 3)]
+      }
+      1 => {
+        let (low, high) = (r1 & 0xFE, r3 & 0x7E);
+        [select(low), select(low | 1), rom(high), rom(high | 1)]
+      }
+      2 => {
+        let low = r1 & 0xFE;
+        [select(low), select(low | 1), select(r2), rom(r3)]
+      }
+      _ => [select(r0), select(r1), select(r2), rom(r3)],
+    };
+  }
+
+  fn prg_ram_writable(&self) -> bool {
+    return self.prg_ram_protect == [0x02, 0x01];
+  }
+
+  fn prg_ram_offset(&self, bank: usize, address: u16) -> usize {
+    return (bank * 0x2000 + (address & 0x1FFF) as usize) % self.prg_ram.len();
+  }
+
+  fn irq_status(&self) -> u8 {
+    return ((self.irq_pending as u8) << 7) | ((self.in_frame as u8) << 6);
+  }
+
+  /// 第 33-48 次命名表读取是取精灵阶段的无用读取，期间的图案表读取属于精灵
+  fn sprite_phase(&self) -> bool {
+    return (33..=48).contains(&self.fetches);
+  }
+
+  /// `$2000-$2FFF` 中 `address` 所在命名表的来源，0-3 依次是 CIRAM A、CIRAM B、ExRAM、填充
+  fn nametable_source(&self, address: u16) -> u8 {
+    let table = (address >> 10) & 0x03;
+    return (self.nametables >> (table * 2)) & 0x03;
+  }
+
+  /// 按本条扫描线上命名表读取的次数得到图块列，决定它是否落在分屏区域或使用扩展属性。
+  /// 第 1-32 次读取是本条扫描线的第 2-33 列，第 49、50 次是下一条扫描线的前两列
+  fn background_tile(&self, offset: usize) -> Tile {
+    let (x, line) = match self.fetches {
+      1..=32 => (self.fetches as usize + 1, self.scanline as usize),
+      49 | 50 => (self.fetches as usize - 49, self.scanline as usize + 1),
+      _ => return Tile::Normal,
+    };
+    if self.split_control & 0x80 != 0 && self.exram_mode <= 1 {
+      let count = (self.split_control & 0x1F) as usize;
+      let right = self.split_control & 0x40 != 0;
+      if (x >= count) == right {
+        let row = (self.split_scroll as usize + line) % 240;
+        return Tile::Split { x: x % 32, row };
+      }
+    }
+    if self.exram_mode == 1 {
+      return Tile::Extended(self.exram[offset]);
+    }
+    return Tile::Normal;
+  }
+
+  fn chr_offset(&self, address: u16) -> usize {
+    let address = (address & 0x1FFF) as usize;
+    if self.in_frame && !self.sprite_phase() {
+      match self.tile {
+        Tile::Split { row, .. } => {
+          let offset = self.split_bank as usize * 0x1000 + ((address & 0x0FF8) | (row % 8));
+          return offset % self.chr.len();
+        }
+        Tile::Extended(ext) => {
+          let bank = ((self.chr_upper as usize) << 6) | (ext & 0x3F) as usize;
+          return (bank * 0x1000 + (address & 0x0FFF)) % self.chr.len();
+        }
+        Tile::Normal => {}
+      }
+    }
+
+    // 8x8 精灵只用 A 组
+    let use_b = match (self.sprite_8x16, self.in_frame) {
+      (false, _) => false,
+      (true, true) => !self.sprite_phase(),
+      (true, false) => self.last_chr_b,
+    };
+    // B 组只有 4 个寄存器，同时映射到 $0000 和 $1000 两半
+    let (bank, size) = match self.chr_mode {
+      0 => (if use_b { self.chr_b[3] } else { self.chr_a[7] }, 0x2000),
+      1 => (if use_b { self.chr_b[3] } else { self.chr_a[(address / 0x1000) * 4 + 3] }, 0x1000),
+      2 => {
+        let slot = address / 0x0800;
+        (if use_b { self.chr_b[(slot & 0x01) * 2 + 1] } else { self.chr_a[slot * 2 + 1] }, 0x0800)
+      }
+      _ => {
+        let slot = address / 0x0400;
+        (if use_b { self.chr_b[slot & 0x03] } else { self.chr_a[slot] }, 0x0400)
+      }
+    };
+    return (bank * size + address % size) % self.chr.len();
+  }
+
+  fn detect_scanline(&mut self) {
+    if self.in_frame {
+      self.scanline += 1;
+      if self.scanline == self.irq_compare as u16 {
+        self.irq_pending = true;
+      }
+    } else {
+      self.in_frame = true;
+      self.scanline = 0;
+      self.irq_pending = false;
+    }
+    self.fetches = 0;
+  }
+
+  fn leave_frame(&mut self) {
+    self.in_frame = false;
+    self.last_address = 0xFFFF;
+    self.repeat = 0;
+  }
+}
+
+impl Mapper for Mmc5 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x5204 => self.irq_status(),
+      0x5205 => (self.multiplicand as u16 * self.multiplier as u16) as u8,
+      0x5206 => ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8,
+      0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[(address & 0x03FF) as usize],
+      0x6000..=0x7FFF => self.prg_ram[self.prg_ram_offset(self.prg_registers[0] as usize, address)],
+      0x8000..=0xFFFF => match self.prg_slots[((address - 0x8000) / 0x2000) as usize] {
+        PrgBank::Rom(bank) => self.prg_rom[(bank * 0x2000 + (address & 0x1FFF) as usize) % self.prg_rom.len()],
+        PrgBank::Ram(bank) => self.prg_ram[self.prg_ram_offset(bank, address)],
+      },
+      _ => 0,
+    };
+  }
+
+  fn cpu_read(&mut self, address: u16) -> u8 {
+    let data = self.cpu_peek(address);
+    // 读取状态确认 IRQ
+    if address == 0x5204 {
+      self.irq_pending = false;
+    }
+    return data;
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x5100 => {
+        self.prg_mode = data & 0x03;
+        self.update_prg();
+      }
+      0x5101 => self.chr_mode = data & 0x03,
+      0x5102 | 0x5103 => self.prg_ram_protect[(address - 0x5102) as usize] = data & 0x03,
+      0x5104 => self.exram_mode = data & 0x03,
+      0x5105 => self.nametables = data,
+      0x5106 => self.fill_tile = data,
+      0x5107 => self.fill_attribute = data & 0x03,
+      0x5113..=0x5117 => {
+        self.prg_registers[(address - 0x5113) as usize] = data;
+        self.update_prg();
+      }
+      0x5120..=0x5127 => {
+        self.chr_a[(address - 0x5120) as usize] = ((self.chr_upper as usize) << 8) | data as usize;
+        self.last_chr_b = false;
+      }
+      0x5128..=0x512B => {
+        self.chr_b[(address - 0x5128) as usize] = ((self.chr_upper as usize) << 8) | data as usize;
+        self.last_chr_b = true;
+      }
+      0x5130 => self.chr_upper = data & 0x03,
+      0x5200 => self.split_control = data,
+      0x5201 => self.split_scroll = data,
+      0x5202 => self.split_bank = data,
+      0x5203 => self.irq_compare = data,
+      0x5204 => self.irq_enabled = data & 0x80 != 0,
+      0x5205 => self.multiplicand = data,
+      0x5206 => self.multiplier = data,
+      0x5C00..=0x5FFF => {
+        let index = (address & 0x03FF) as usize;
+        match self.exram_mode {
+          // 作为命名表使用时只能在渲染期间写入，否则写入的是 0
+          0 | 1 => self.exram[index] = if self.in_frame { data } else { 0 },
+          2 => self.exram[index] = data,
+          _ => {}
+        }
+      }
+      0x6000..=0x7FFF if self.prg_ram_writable() => {
+        let offset = self.prg_ram_offset(self.prg_registers[0] as usize, address);
+        self.prg_ram[offset] = data;
+      }
+      0x8000..=0xDFFF if self.prg_ram_writable() => {
+        if let PrgBank::Ram(bank) = self.prg_slots[((address - 0x8000) / 0x2000) as usize] {
+          let offset = self.prg_ram_offset(bank, address);
+          self.prg_ram[offset] = data;
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr[self.chr_offset(address)];
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    if self.chr_writable {
+      let offset = self.chr_offset(address);
+      self.chr[offset] = data;
+    }
+  }
+
+  /// 只是常见组合的近似，PPU 实际按 `nametable_bank` 和 `nametable_read` 访问命名表
+  fn mirroring(&self) -> Mirroring {
+    return match self.nametables {
+      0x00 => Mirroring::SingleScreenLower,
+      0x55 => Mirroring::SingleScreenUpper,
+      0x44 => Mirroring::Vertical,
+      0x50 => Mirroring::Horizontal,
+      _ => Mirroring::FourScreen,
+    };
+  }
+
+  fn nametable_bank(&self, table: u16) -> usize {
+    return ((self.nametables >> (table * 2)) & 0x01) as usize;
+  }
+
+  fn nametable_read(&mut self, address: u16) -> Option<u8> {
+    let offset = (address & 0x03FF) as usize;
+    let attribute = offset >= 0x3C0;
+    if self.in_frame && !self.sprite_phase() {
+      if !attribute {
+        self.tile = self.background_tile(offset);
+      }
+      match self.tile {
+        Tile::Split { x, row } => {
+          if attribute {
+            let byte = self.exram[0x3C0 + (row / 32) * 8 + x / 4];
+            let shift = ((row / 16) & 0x01) * 4 + ((x / 2) & 0x01) * 2;
+            return Some(((byte >> shift) & 0x03) * 0x55);
+          }
+          return Some(self.exram[(row / 8) * 32 + x]);
+        }
+        // 属性表读取换成扩展属性的调色板，4 个象限都一样
+        Tile::Extended(ext) if attribute => return Some((ext >> 6) * 0x55),
+        _ => {}
+      }
+    }
+
+    return match self.nametable_source(address) {
+      2 => Some(if self.exram_mode <= 1 { self.exram[offset] } else { 0 }),
+      3 => Some(if attribute { self.fill_attribute * 0x55 } else { self.fill_tile }),
+      _ => None,
+    };
+  }
+
+  fn nametable_write(&mut self, address: u16, data: u8) -> bool {
+    return match self.nametable_source(address) {
+      2 => {
+        if self.exram_mode <= 1 {
+          self.exram[(address & 0x03FF) as usize] = data;
+        }
+        true
+      }
+      3 => true,
+      _ => false,
+    };
+  }
+
+  fn ppu_register_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x2000 => self.sprite_8x16 = data & 0x20 != 0,
+      0x2001 if data & 0x18 == 0 => self.leave_frame(),
+      _ => {}
+    }
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq_pending && self.irq_enabled;
+  }
+
+  fn ppu_address(&mut self, address: u16, _cycle: u64) {
+    self.ppu_active = true;
+    if address == self.last_address {
+      self.repeat += 1;
+      if self.repeat == 2 {
+        self.detect_scanline();
+      }
+    } else {
+      self.last_address = address;
+      self.repeat = 0;
+    }
+    if (0x2000..=0x2FFF).contains(&address) && address & 0x03FF < 0x3C0 {
+      self.fetches = self.fetches.saturating_add(1);
+    }
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    if std::mem::take(&mut self.ppu_active) {
+      self.idle_cycles = 0;
+      return;
+    }
+    self.idle_cycles += cycles;
+    if self.idle_cycles >= IDLE_CYCLES {
+      self.leave_frame();
+    }
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then_some(self.prg_ram.as_slice());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    let length = data.len().min(self.prg_ram.len());
+    self.prg_ram[..length].copy_from_slice(&data[..length]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  fn test_mapper() -> Mmc5 {
+    return Mmc5::new(banked_cartridge(5, 0x2000, 16, 0x0400, 64));
+  }
+
+  /// 像 PPU 一样先把地址放到总线上，再读取数据
+  fn fetch(mapper: &mut Mmc5, address: u16) -> Option<u8> {
+    mapper.ppu_address(address, 0);
+    if address < 0x2000 {
+      return Some(mapper.ppu_read(address));
+    }
+    return mapper.nametable_read(address);
+  }
+
+  /// 连续 3 次读取同一个命名表地址，开始一条新的扫描线
+  fn start_scanline(mapper: &mut Mmc5) {
+    mapper.ppu_address(0x0000, 0);
+    for _ in 0..3 {
+      fetch(mapper, 0x2000);
+    }
+  }
+
+  #[test]
+  fn test_prg_modes_and_ram() {
+    let mut mapper = test_mapper();
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+    mapper.cpu_write(0x5114, 0x82);
+    assert_eq!(mapper.cpu_peek(0x8000), 2);
+
+    mapper.cpu_write(0x5100, 0);
+    mapper.cpu_write(0x5117, 0x87);
+    assert_eq!(mapper.cpu_peek(0x8000), 4);
+    assert_eq!(mapper.cpu_peek(0xE000), 7);
+
+    // 16K+8K+8K 模式下把 PRG RAM 映射到 $C000，解除写保护之后才能写
+    mapper.cpu_write(0x5100, 2);
+    mapper.cpu_write(0x5116, 0x00);
+    mapper.cpu_write(0xC000, 0x42);
+    assert_eq!(mapper.cpu_peek(0xC000), 0);
+    mapper.cpu_write(0x5102, 2);
+    mapper.cpu_write(0x5103, 1);
+    mapper.cpu_write(0xC000, 0x42);
+    assert_eq!(mapper.cpu_peek(0xC000), 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+  }
+
+  #[test]
+  fn test_chr_sets_for_8x16_sprites() {
+    let mut mapper = test_mapper();
+    mapper.cpu_write(0x5101, 3);
+    mapper.cpu_write(0x5120, 1);
+    mapper.cpu_write(0x5128, 9);
+    mapper.ppu_register_write(0x2000, 0x20);
+
+    // 不在渲染时用最后写入的一组
+    assert_eq!(mapper.ppu_read(0x0000), 9);
+
+    // 背景用 B 组，取精灵阶段用 A 组
+    start_scanline(&mut mapper);
+    assert_eq!(fetch(&mut mapper, 0x0000), Some(9));
+    for i in 0..32 {
+      fetch(&mut mapper, 0x2001 + i);
+    }
+    assert_eq!(fetch(&mut mapper, 0x0000), Some(1));
+  }
+
+  #[test]
+  fn test_chr_set_a_for_8x8_sprites() {
+    let mut mapper = test_mapper();
+    mapper.cpu_write(0x5101, 3);
+    mapper.cpu_write(0x5120, 1);
+    mapper.cpu_write(0x5128, 9);
+    mapper.ppu_register_write(0x2000, 0x00);
+
+    // 初始化时按顺序写完两组寄存器之后，8x8 精灵的游戏仍然用 A 组
+    assert_eq!(mapper.ppu_read(0x0000), 1);
+    start_scanline(&mut mapper);
+    assert_eq!(fetch(&mut mapper, 0x0000), Some(1));
+  }
+
+  #[test]
+  fn test_scanline_irq() {
+    let mut mapper = test_mapper();
+    mapper.cpu_write(0x5203, 2);
+    mapper.cpu_write(0x5204, 0x80);
+
+    start_scanline(&mut mapper);
+    start_scanline(&mut mapper);
+    assert!(!mapper.irq());
+    start_scanline(&mut mapper);
+    assert!(mapper.irq());
+    assert_eq!(mapper.cpu_read(0x5204), 0xC0);
+    assert!(!mapper.irq());
+
+    // 几个周期没有 PPU 读取就离开了画面
+    mapper.tick(1);
+    mapper.tick(3);
+    assert_eq!(mapper.cpu_peek(0x5204), 0x00);
+  }
+
+  #[test]
+  fn test_nametable_sources_and_extended_attributes() {
+    let mut mapper = test_mapper();
+    // 命名表 0-3 依次是 CIRAM A、CIRAM B、ExRAM、填充
+    mapper.cpu_write(0x5105, 0b11_10_01_00);
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5C00, 0b10_000011);
+    mapper.cpu_write(0x5C05, 0x33);
+    mapper.cpu_write(0x5106, 0x42);
+    mapper.cpu_write(0x5107, 2);
+
+    mapper.cpu_write(0x5104, 1);
+    assert_eq!(mapper.nametable_bank(1), 1);
+    assert_eq!(mapper.nametable_read(0x2405), None);
+    assert_eq!(mapper.nametable_read(0x2805), Some(0x33));
+    assert_eq!(mapper.nametable_read(0x2C00), Some(0x42));
+    assert_eq!(mapper.nametable_read(0x2FC0), Some(0xAA));
+
+    // 扩展属性：调色板 2，4 KiB CHR bank 3（第 12 个 1 KiB bank）
+    start_scanline(&mut mapper);
+    assert_eq!(fetch(&mut mapper, 0x23C0), Some(0xAA));
+    assert_eq!(fetch(&mut mapper, 0x0010), Some(12));
+  }
+
+  #[test]
+  fn test_vertical_split_and_multiplier() {
+    let mut mapper = test_mapper();
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5C02, 0x07);
+    mapper.cpu_write(0x5104, 0);
+    mapper.cpu_write(0x5200, 0x84);
+    mapper.cpu_write(0x5202, 1);
+
+    // 第 0 条扫描线的第 2 列在左边 4 列的分屏里，图块来自 ExRAM，图案来自 4 KiB bank 1
+    start_scanline(&mut mapper);
+    assert_eq!(mapper.nametable_read(0x2000), Some(0x07));
+    assert_eq!(fetch(&mut mapper, 0x0073), Some(4));
+
+    mapper.cpu_write(0x5205, 200);
+    mapper.cpu_write(0x5206, 100);
+    assert_eq!(mapper.cpu_peek(0x5205), (20000 & 0xFF) as u8);
+    assert_eq!(mapper.cpu_peek(0x5206), (20000 >> 8) as u8);
+  }
+}
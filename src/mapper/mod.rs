@@ -0,0 +1,313 @@
+pub mod bandai;
+pub mod banks;
+pub mod discrete;
+pub mod eeprom;
+pub mod fme7;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc3;
+pub mod mmc5;
+pub mod namco163;
+pub mod nrom;
+pub mod vrc;
+pub mod vrc6;
+pub mod vrc7;
+pub mod vrc_irq;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use self::bandai::BandaiFcg;
+use self::discrete::{Board, Discrete};
+use self::fme7::Fme7;
+use self::mmc1::Mmc1;
+use self::mmc2::Mmc2;
+use self::mmc3::Mmc3;
+use self::mmc5::Mmc5;
+use self::namco163::Namco163;
+use self::nrom::Nrom;
+use self::vrc::Vrc;
+use self::vrc6::Vrc6;
+use self::vrc7::Vrc7;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+use crate::error::UnsupportedMapper;
+
+/// [Mapper](https://www.nesdev.org/wiki/Mapper)：卡带上的 bank 切换和其他硬件。
+///
+/// CPU 通过 `$4020-$FFFF` 访问卡带，PPU 通过 `$0000-$1FFF` 访问图案表，
+/// 命名表的镜像方式和 IRQ 也由卡带决定。
+pub trait Mapper {
+  /// 查看 CPU 地址 `$4020-$FFFF` 上的值，不改变任何状态
+  fn cpu_peek(&self, address: u16) -> u8;
+
+  /// CPU 读取 `$4020-$FFFF`，少数 mapper 的寄存器读取有副作用
+  fn cpu_read(&mut self, address: u16) -> u8 {
+    return self.cpu_peek(address);
+  }
+
+  /// CPU 写入 `$4020-$FFFF`，包括 PRG RAM 和 mapper 寄存器
+  fn cpu_write(&mut self, address: u16, data: u8);
+
+  /// PPU 读取图案表 `$0000-$1FFF`
+  fn ppu_read(&mut self, address: u16) -> u8;
+
+  /// PPU 写入图案表 `$0000-$1FFF`，只有 CHR RAM 可写
+  fn ppu_write(&mut self, address: u16, data: u8);
+
+  /// 当前的命名表镜像方式
+  fn mirroring(&self) -> Mirroring;
+
+  /// `$2000-$2FFF` 中第 `table`（0-3）个命名表使用的 1 KiB 命名表 RAM，默认按 `mirroring()` 映射
+  fn nametable_bank(&self, table: u16) -> usize {
+    return self.mirroring().nametable_bank(table);
+  }
+
+  /// PPU 读取命名表 `$2000-$3EFF`。返回 `None` 时读取主机内的命名表 RAM，
+  /// MMC5 这类 mapper 可以换成卡带上的 RAM 或者填充数据
+  fn nametable_read(&mut self, _address: u16) -> Option<u8> {
+    return None;
+  }
+
+  /// PPU 写入命名表 `$2000-$3EFF`，返回 `true` 表示已经由 mapper 处理
+  fn nametable_write(&mut self, _address: u16, _data: u8) -> bool {
+    return false;
+  }
+
+  /// CPU 写入了 PPU 寄存器 `$2000-$2007`。卡带也接在 CPU 总线上，MMC5 由此得知精灵大小
+  fn ppu_register_write(&mut self, _address: u16, _data: u8) {}
+
+  /// 是否正在拉低 IRQ 线
+  fn irq(&self) -> bool {
+    return false;
+  }
+
+  /// PPU 地址总线上出现了 `address`，`cycle` 是上电以来的 PPU 时钟数。
+  /// 渲染取数、PPUDATA 访问和 `$2006` 写入都会调用，MMC3 用它检测 A12 的上升沿
+  fn ppu_address(&mut self, _address: u16, _cycle: u64) {}
+
+  /// 开启渲染时，PPU 在每条可见扫描线和预渲染扫描线的第 260 个时钟调用，
+  /// 相当于背景用 `$0000`、精灵用 `$1000` 时 PPU A12 的上升沿
+  fn scanline(&mut self) {}
+
+  /// 经过了 `cycles` 个 CPU 周期，用于按 CPU 周期计数的 IRQ
+  fn tick(&mut self, _cycles: u64) {}
+
+  /// 关机后需要保留的数据：电池供电的 PRG RAM，或者 Bandai 卡带上的 EEPROM。
+  /// 没有需要保存的内容时返回 `None`，由前端写入存档文件
+  fn save_data(&self) -> Option<&[u8]> {
+    return None;
+  }
+
+  /// 从存档文件恢复 `save_data()` 保存的内容
+  fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+/// CPU 总线和 PPU 共用同一个 mapper
+pub type SharedMapper = Rc<RefCell<Box<dyn Mapper>>>;
+
+/// 注册表中的一个 mapper
+pub struct MapperEntry {
+  pub number: u16,
+  pub name: &'static str,
+  create: fn(Cartridge) -> Box<dyn Mapper>,
+}
+
+/// 已经实现的 mapper，按 iNES 编号排列
+pub const REGISTRY: &[MapperEntry] = &[
+  MapperEntry {
+    number: 0,
+    name: "NROM",
+    create: |cartridge| Box::new(Nrom::new(cartridge)),
+  },
+  MapperEntry {
+    number: 1,
+    name: "MMC1",
+    create: |cartridge| Box::new(Mmc1::new(cartridge)),
+  },
+  MapperEntry {
+    number: 2,
+    name: "UxROM",
+    create: |cartridge| Box::new(Discrete::new(Board::UxRom, cartridge)),
+  },
+  MapperEntry {
+    number: 3,
+    name: "CNROM",
+    create: |cartridge| Box::new(Discrete::new(Board::CnRom, cartridge)),
+  },
+  MapperEntry {
+    number: 4,
+    name: "MMC3",
+    create: |cartridge| Box::new(Mmc3::new(cartridge)),
+  },
+  MapperEntry {
+    number: 5,
+    name: "MMC5",
+    create: |cartridge| Box::new(Mmc5::new(cartridge)),
+  },
+  MapperEntry {
+    number: 7,
+    name: "AxROM",
+    create: |cartridge| Box::new(Discrete::new(Board::AxRom, cartridge)),
+  },
+  MapperEntry {
+    number: 9,
+    name: "MMC2",
+    create: |cartridge| Box::new(Mmc2::new(cartridge)),
+  },
+  MapperEntry {
+    number: 10,
+    name: "MMC4",
+    create: |cartridge| Box::new(Mmc2::new(cartridge)),
+  },
+  MapperEntry {
+    number: 11,
+    name: "Color Dreams",
+    create: |cartridge| Box::new(Discrete::new(Board::ColorDreams, cartridge)),
+  },
+  MapperEntry {
+    number: 16,
+    name: "Bandai FCG",
+    create: |cartridge| Box::new(BandaiFcg::new(cartridge)),
+  },
+  MapperEntry {
+    number: 19,
+    name: "Namco 163",
+    create: |cartridge| Box::new(Namco163::new(cartridge)),
+  },
+  MapperEntry {
+    number: 21,
+    name: "VRC4a / VRC4c",
+    create: |cartridge| Box::new(Vrc::new(cartridge)),
+  },
+  MapperEntry {
+    number: 22,
+    name: "VRC2a",
+    create: |cartridge| Box::new(Vrc::new(cartridge)),
+  },
+  MapperEntry {
+    number: 23,
+    name: "VRC2b / VRC4e / VRC4f",
+    create: |cartridge| Box::new(Vrc::new(cartridge)),
+  },
+  MapperEntry {
+    number: 24,
+    name: "VRC6a",
+    create: |cartridge| Box::new(Vrc6::new(cartridge)),
+  },
+  MapperEntry {
+    number: 25,
+    name: "VRC2c / VRC4b / VRC4d",
+    create: |cartridge| Box::new(Vrc::new(cartridge)),
+  },
+  MapperEntry {
+    number: 26,
+    name: "VRC6b",
+    create: |cartridge| Box::new(Vrc6::new(cartridge)),
+  },
+  MapperEntry {
+    number: 34,
+    name: "BNROM / NINA-001",
+    create: |cartridge| Box::new(Discrete::mapper_34(cartridge)),
+  },
+  MapperEntry {
+    number: 66,
+    name: "GxROM",
+    create: |cartridge| Box::new(Discrete::new(Board::GxRom, cartridge)),
+  },
+  MapperEntry {
+    number: 69,
+    name: "Sunsoft FME-7",
+    create: |cartridge| Box::new(Fme7::new(cartridge)),
+  },
+  MapperEntry {
+    number: 85,
+    name: "VRC7",
+    create: |cartridge| Box::new(Vrc7::new(cartridge)),
+  },
+  MapperEntry {
+    number: 159,
+    name: "Bandai LZ93D50 + 24C01",
+    create: |cartridge| Box::new(BandaiFcg::new(cartridge)),
+  },
+];
+
+/// 按卡带头中的 mapper 编号创建 mapper
+pub fn create(cartridge: Cartridge) -> Result<Box<dyn Mapper>, UnsupportedMapper> {
+  let number = cartridge.mapper;
+  return match REGISTRY.iter().find(|entry| entry.number == number) {
+    Some(entry) => Ok((entry.create)(cartridge)),
+    None => Err(UnsupportedMapper { mapper: number }),
+  };
+}
+
+/// 创建可以在 CPU 总线和 PPU 之间共用的 mapper
+pub fn create_shared(cartridge: Cartridge) -> Result<SharedMapper, UnsupportedMapper> {
+  return Ok(Rc::new(RefCell::new(create(cartridge)?)));
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::cartridge::console::{ConsoleType, Timing};
+  use crate::cartridge::Format;
+
+  /// 只有图案表的 NROM 卡带，PRG ROM 全部为 0
+  pub fn test_mapper(chr_rom: Vec<u8>, mirroring: Mirroring) -> SharedMapper {
+    let cartridge = Cartridge {
+      prg_rom: vec![0; 0x4000],
+      chr_rom,
+      nametable_mirroring: mirroring,
+      ..banked_cartridge(0, 0x4000, 1, 0x2000, 0)
+    };
+    return create_shared(cartridge).unwrap();
+  }
+
+  /// 每个 bank 的内容都是它自己的编号，`chr_banks` 为 0 时使用 CHR RAM
+  pub fn banked_cartridge(mapper: u16, prg_bank_size: usize, prg_banks: usize, chr_bank_size: usize, chr_banks: usize) -> Cartridge {
+    let fill = |size: usize, count: usize| (0..count).flat_map(|bank| vec![bank as u8; size]).collect();
+    let chr_rom: Vec<u8> = fill(chr_bank_size, chr_banks);
+    return Cartridge {
+      format: Format::INes,
+      mapper,
+      submapper: 0,
+      prg_rom: fill(prg_bank_size, prg_banks),
+      chr_ram_size: if chr_rom.is_empty() { 0x2000 } else { 0 },
+      chr_rom,
+      nametable_mirroring: Mirroring::Horizontal,
+      prg_ram_size: 0x2000,
+      prg_nvram_size: 0,
+      chr_nvram_size: 0,
+      battery: false,
+      timing: Timing::Ntsc,
+      console_type: ConsoleType::Nes,
+      expansion_device: 0,
+    };
+  }
+
+  #[test]
+  fn test_unsupported_mapper() {
+    let cartridge = banked_cartridge(0xFF, 0x4000, 1, 0x2000, 0);
+    let error = create(cartridge).err().unwrap();
+    assert_eq!(error, UnsupportedMapper { mapper: 0xFF });
+    assert_eq!(error.to_string(), "mapper 255 is not supported");
+  }
+
+  #[test]
+  fn test_nrom_mirrors_16k_prg_rom() {
+    let mut prg_rom = vec![0; 0x4000];
+    prg_rom[0x0010] = 0x42;
+    let cartridge = Cartridge { prg_rom, ..banked_cartridge(0, 0x4000, 1, 0x2000, 0) };
+    let mut mapper = create(cartridge).unwrap();
+    assert_eq!(mapper.cpu_peek(0x8010), 0x42);
+    assert_eq!(mapper.cpu_peek(0xC010), 0x42);
+
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_read(0x6000), 0x11);
+    mapper.cpu_write(0x8010, 0x11);
+    assert_eq!(mapper.cpu_peek(0x8010), 0x42);
+
+    mapper.ppu_write(0x1234, 0x22);
+    assert_eq!(mapper.ppu_read(0x1234), 0x22);
+  }
+}
@@ -0,0 +1,243 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [Namco 163](https://www.nesdev.org/wiki/Namco_163_audio)（mapper 19）
+///
+/// | 地址            | 寄存器                                                        |
+/// |-----------------|--------------------------------------------------------------|
+/// | `$4800-$4FFF`   | 芯片内 128 字节 RAM 的数据端口（扩展音频也用这块 RAM）            |
+/// | `$5000-$57FF`   | IRQ 计数器低 8 位                                              |
+/// | `$5800-$5FFF`   | IRQ 计数器高 7 位，第 7 位开启 IRQ                               |
+/// | `$8000-$BFFF`   | 1 KiB CHR bank 0-7，每 `$800` 一个                              |
+/// | `$C000-$DFFF`   | 4 个命名表，$E0 及以上使用主机内的命名表 RAM，否则使用 CHR ROM       |
+/// | `$E000-$E7FF`   | `$8000` 的 8 KiB PRG bank，第 6 位关闭扩展音频                   |
+/// | `$E800-$EFFF`   | `$A000` 的 8 KiB PRG bank，第 6、7 位关闭图案表中的命名表 RAM      |
+/// | `$F000-$F7FF`   | `$C000` 的 8 KiB PRG bank，`$E000` 固定为最后一个 bank            |
+/// | `$F800-$FFFF`   | PRG RAM 写保护，同时是内部 RAM 的地址（第 7 位自动加一）            |
+///
+/// 写入计数器会确认 IRQ。计数器每个 CPU 周期加一，到 $7FFF 时触发 IRQ 并停止。
+/// 图案表的 bank 为 $E0 及以上时芯片可以把主机内的命名表 RAM 当作图案表，
+/// 但 mapper 接触不到这块 RAM，这里仍然按 CHR ROM 的 bank 处理。
+pub struct Namco163 {
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+  /// 作为命名表的 CHR ROM，按 1 KiB 编号
+  chr_rom: Vec<u8>,
+
+  internal_ram: [u8; 0x80],
+  /// `$F800`：写保护和内部 RAM 地址
+  ram_control: u8,
+  nametables: [u8; 4],
+
+  irq_counter: u16,
+  irq_enabled: bool,
+  irq_pending: bool,
+}
+
+impl Namco163 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let mut mapper = Namco163 {
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom.clone(), cartridge.chr_ram_size, 0x0400),
+      chr_rom: cartridge.chr_rom,
+      internal_ram: [0; 0x80],
+      ram_control: 0,
+      nametables: [0xE0, 0xE1, 0xE0, 0xE1],
+      irq_counter: 0,
+      irq_enabled: false,
+      irq_pending: false,
+    };
+    mapper.prg_rom.set_from_end(3, 1);
+    return mapper;
+  }
+
+  fn internal_ram_address(&self) -> usize {
+    return (self.ram_control & 0x7F) as usize;
+  }
+
+  /// `$F800` 的高 4 位为 `0100` 时才能写入，低 4 位分别保护 PRG RAM 的 4 个 2 KiB 区域
+  fn prg_ram_writable(&self, address: u16) -> bool {
+    let region = (address & 0x1FFF) >> 11;
+    return self.ram_control & 0xF0 == 0x40 && self.ram_control & (1 << region) == 0;
+  }
+
+  /// 命名表寄存器选择的 CHR ROM 1 KiB 页，`None` 表示使用主机内的命名表 RAM
+  fn nametable_page(&self, address: u16) -> Option<usize> {
+    let bank = self.nametables[((address >> 10) & 0x03) as usize];
+    if bank >= 0xE0 || self.chr_rom.is_empty() {
+      return None;
+    }
+    return Some(bank as usize);
+  }
+}
+
+impl Mapper for Namco163 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x4800..=0x4FFF => self.internal_ram[self.internal_ram_address()],
+      0x5000..=0x57FF => self.irq_counter as u8,
+      0x5800..=0x5FFF => ((self.irq_counter >> 8) as u8) | ((self.irq_enabled as u8) << 7),
+      0x6000..=0x7FFF => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_read(&mut self, address: u16) -> u8 {
+    let data = self.cpu_peek(address);
+    if let 0x4800..=0x4FFF = address {
+      if self.ram_control & 0x80 != 0 {
+        self.ram_control = 0x80 | (self.ram_control.wrapping_add(1) & 0x7F);
+      }
+    }
+    return data;
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x4800..=0x4FFF => {
+        self.internal_ram[self.internal_ram_address()] = data;
+        if self.ram_control & 0x80 != 0 {
+          self.ram_control = 0x80 | (self.ram_control.wrapping_add(1) & 0x7F);
+        }
+      }
+      0x5000..=0x57FF => {
+        self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+        self.irq_pending = false;
+      }
+      0x5800..=0x5FFF => {
+        self.irq_counter = (self.irq_counter & 0x00FF) | (((data & 0x7F) as u16) << 8);
+        self.irq_enabled = data & 0x80 != 0;
+        self.irq_pending = false;
+      }
+      0x6000..=0x7FFF if self.prg_ram_writable(address) => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0x8000..=0xBFFF => self.chr.set(((address - 0x8000) >> 11) as usize, data as usize),
+      0xC000..=0xDFFF => self.nametables[((address - 0xC000) >> 11) as usize] = data,
+      0xE000..=0xE7FF => self.prg_rom.set(0, (data & 0x3F) as usize),
+      0xE800..=0xEFFF => self.prg_rom.set(1, (data & 0x3F) as usize),
+      0xF000..=0xF7FF => self.prg_rom.set(2, (data & 0x3F) as usize),
+      0xF800..=0xFFFF => self.ram_control = data,
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return Mirroring::Vertical;
+  }
+
+  fn nametable_bank(&self, table: u16) -> usize {
+    return (self.nametables[(table & 0x03) as usize] & 0x01) as usize;
+  }
+
+  fn nametable_read(&mut self, address: u16) -> Option<u8> {
+    let page = self.nametable_page(address)?;
+    return Some(self.chr_rom[(page * 0x400 + (address & 0x03FF) as usize) % self.chr_rom.len()]);
+  }
+
+  fn nametable_write(&mut self, address: u16, _data: u8) -> bool {
+    // 写入作为命名表的 CHR ROM 没有效果
+    return self.nametable_page(address).is_some();
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq_pending;
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    if !self.irq_enabled || self.irq_counter == 0x7FFF {
+      return;
+    }
+    self.irq_counter = (self.irq_counter as u64 + cycles).min(0x7FFF) as u16;
+    if self.irq_counter == 0x7FFF {
+      self.irq_pending = true;
+    }
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_banks_and_nametables() {
+    let mut mapper = Namco163::new(banked_cartridge(19, 0x2000, 16, 0x0400, 32));
+    mapper.cpu_write(0xE000, 3);
+    mapper.cpu_write(0xF000, 5);
+    mapper.cpu_write(0x8800, 9);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xC000), 5);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+    assert_eq!(mapper.ppu_read(0x0400), 9);
+
+    // $E0 及以上用命名表 RAM，第 0 位选择哪一块
+    mapper.cpu_write(0xC800, 0xE0);
+    mapper.cpu_write(0xD000, 0xE1);
+    assert_eq!(mapper.nametable_bank(1), 0);
+    assert_eq!(mapper.nametable_bank(2), 1);
+    assert_eq!(mapper.nametable_read(0x2400), None);
+
+    // 否则用 CHR ROM 作命名表，写入无效
+    mapper.cpu_write(0xC000, 7);
+    assert_eq!(mapper.nametable_read(0x2010), Some(7));
+    assert!(mapper.nametable_write(0x2010, 0x42));
+  }
+
+  #[test]
+  fn test_internal_ram_and_write_protect() {
+    let mut mapper = Namco163::new(banked_cartridge(19, 0x2000, 16, 0x0400, 32));
+    mapper.cpu_write(0xF800, 0x80 | 0x10);
+    mapper.cpu_write(0x4800, 0x11);
+    mapper.cpu_write(0x4800, 0x22);
+    mapper.cpu_write(0xF800, 0x80 | 0x10);
+    assert_eq!(mapper.cpu_read(0x4800), 0x11);
+    assert_eq!(mapper.cpu_read(0x4800), 0x22);
+
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+    // 只保护第 1 个 2 KiB 区域
+    mapper.cpu_write(0xF800, 0x42);
+    mapper.cpu_write(0x6000, 0x42);
+    mapper.cpu_write(0x6800, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+    assert_eq!(mapper.cpu_peek(0x6800), 0);
+  }
+
+  #[test]
+  fn test_irq() {
+    let mut mapper = Namco163::new(banked_cartridge(19, 0x2000, 16, 0x0400, 32));
+    mapper.cpu_write(0x5000, 0xFD);
+    mapper.cpu_write(0x5800, 0xFF);
+    mapper.tick(1);
+    assert!(!mapper.irq());
+    mapper.tick(5);
+    assert!(mapper.irq());
+    assert_eq!(mapper.cpu_peek(0x5000), 0xFF);
+    assert_eq!(mapper.cpu_peek(0x5800), 0xFF);
+
+    mapper.cpu_write(0x5000, 0);
+    assert!(!mapper.irq());
+  }
+}
@@ -0,0 +1,54 @@
+use super::banks::Banks;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [NROM](https://www.nesdev.org/wiki/NROM)（mapper 0），没有 bank 切换。
+///
+/// 16 KiB 的 PRG ROM 在 `$C000` 处镜像一次，`$6000-$7FFF` 是 8 KiB 的 PRG RAM（Family BASIC）。
+pub struct Nrom {
+  prg_rom: Banks,
+  prg_ram: [u8; 0x2000],
+  chr: Banks,
+  mirroring: Mirroring,
+}
+
+impl Nrom {
+  pub fn new(cartridge: Cartridge) -> Self {
+    return Nrom {
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x8000, false),
+      prg_ram: [0; 0x2000],
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x2000),
+      mirroring: cartridge.nametable_mirroring,
+    };
+  }
+}
+
+impl Mapper for Nrom {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF => self.prg_ram[(address & 0x1FFF) as usize],
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    // 写入 PRG ROM 没有效果，有些游戏会这样写
+    if let 0x6000..=0x7FFF = address {
+      self.prg_ram[(address & 0x1FFF) as usize] = data;
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+}
@@ -0,0 +1,279 @@
+use super::banks::Banks;
+use super::vrc_irq::VrcIrq;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+  /// 没有 IRQ 和 PRG 模式，CHR bank 只有 8 位
+  Vrc2,
+  Vrc4,
+}
+
+/// VRC2/VRC4 的板子。芯片的寄存器地址线 A0、A1 在不同的板子上接到 CPU 不同的地址线，
+/// `a0`、`a1` 是对应 CPU 地址线的掩码，可以同时接多条以兼容同一 mapper 编号下的几种板子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+  pub chip: Chip,
+  pub a0: u16,
+  pub a1: u16,
+
+  /// VRC2a 的 CHR bank 编号要右移一位
+  pub chr_shift: bool,
+}
+
+impl Board {
+  pub const VRC2A: Board = Board::new(Chip::Vrc2, 1 << 1, 1 << 0);
+  pub const VRC2B: Board = Board::new(Chip::Vrc2, 1 << 0, 1 << 1);
+  pub const VRC2C: Board = Board::new(Chip::Vrc2, 1 << 1, 1 << 0);
+  pub const VRC4A: Board = Board::new(Chip::Vrc4, 1 << 1, 1 << 2);
+  pub const VRC4B: Board = Board::new(Chip::Vrc4, 1 << 1, 1 << 0);
+  pub const VRC4C: Board = Board::new(Chip::Vrc4, 1 << 6, 1 << 7);
+  pub const VRC4D: Board = Board::new(Chip::Vrc4, 1 << 3, 1 << 2);
+  pub const VRC4E: Board = Board::new(Chip::Vrc4, 1 << 2, 1 << 3);
+  pub const VRC4F: Board = Board::new(Chip::Vrc4, 1 << 0, 1 << 1);
+
+  const fn new(chip: Chip, a0: u16, a1: u16) -> Self {
+    return Board { chip, a0, a1, chr_shift: false };
+  }
+
+  /// 按 mapper 编号和 NES 2.0 子 mapper 选择板子。子 mapper 为 0 时同时接上这个编号下的两种 VRC4 接法
+  pub fn from_header(mapper: u16, submapper: u8) -> Board {
+    let combine = |x: Board, y: Board| Board::new(Chip::Vrc4, x.a0 | y.a0, x.a1 | y.a1);
+    return match (mapper, submapper) {
+      (21, 1) => Board::VRC4A,
+      (21, 2) => Board::VRC4C,
+      (21, _) => combine(Board::VRC4A, Board::VRC4C),
+      (22, _) => Board { chr_shift: true, ..Board::VRC2A },
+      (23, 1) => Board::VRC4F,
+      (23, 2) => Board::VRC4E,
+      (23, 3) => Board::VRC2B,
+      (23, _) => combine(Board::VRC4F, Board::VRC4E),
+      (25, 1) => Board::VRC4B,
+      (25, 2) => Board::VRC4D,
+      (25, 3) => Board::VRC2C,
+      (_, _) => combine(Board::VRC4B, Board::VRC4D),
+    };
+  }
+}
+
+/// [VRC2 和 VRC4](https://www.nesdev.org/wiki/VRC2_and_VRC4)（mapper 21、22、23、25）
+///
+/// | 地址            | 寄存器                                         |
+/// |-----------------|-----------------------------------------------|
+/// | `$8000-$8003`   | `$8000`（PRG 模式 1 时是 `$C000`）的 8 KiB PRG bank |
+/// | `$9000-$9001`   | 镜像                                           |
+/// | `$9002-$9003`   | VRC4：第 1 位交换 `$8000` 和 `$C000`             |
+/// | `$A000-$A003`   | `$A000` 的 8 KiB PRG bank                       |
+/// | `$B000-$E003`   | 8 个 1 KiB CHR bank，每个分低 4 位和高位两次写入  |
+/// | `$F000-$F003`   | VRC4：IRQ 锁存值低 4 位、高 4 位、控制、确认       |
+///
+/// 表中的低两位是芯片的 A0、A1，实际地址取决于板子的接线，见 [`Board`]。
+pub struct Vrc {
+  board: Board,
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  prg_banks: [u8; 2],
+  prg_swap: bool,
+  chr_banks: [usize; 8],
+  mirroring: Mirroring,
+  irq: VrcIrq,
+}
+
+impl Vrc {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let board = Board::from_header(cartridge.mapper, cartridge.submapper);
+    return Vrc::with_board(cartridge, board);
+  }
+
+  pub fn with_board(cartridge: Cartridge, board: Board) -> Self {
+    let mut mapper = Vrc {
+      board,
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      prg_banks: [0; 2],
+      prg_swap: false,
+      chr_banks: [0; 8],
+      mirroring: Mirroring::Vertical,
+      irq: VrcIrq::new(),
+    };
+    mapper.update_banks();
+    return mapper;
+  }
+
+  /// 把 CPU 地址换成芯片看到的寄存器地址，低两位是 A1、A0
+  fn register(&self, address: u16) -> u16 {
+    let a0 = (address & self.board.a0 != 0) as u16;
+    let a1 = (address & self.board.a1 != 0) as u16;
+    return (address & 0xF000) | (a1 << 1) | a0;
+  }
+
+  fn update_banks(&mut self) {
+    if self.prg_swap {
+      self.prg_rom.set_from_end(0, 2);
+      self.prg_rom.set(2, self.prg_banks[0] as usize);
+    } else {
+      self.prg_rom.set(0, self.prg_banks[0] as usize);
+      self.prg_rom.set_from_end(2, 2);
+    }
+    self.prg_rom.set(1, self.prg_banks[1] as usize);
+    self.prg_rom.set_from_end(3, 1);
+
+    for (slot, &bank) in self.chr_banks.iter().enumerate() {
+      self.chr.set(slot, if self.board.chr_shift { bank >> 1 } else { bank });
+    }
+  }
+
+  fn write_register(&mut self, register: u16, data: u8) {
+    let vrc4 = self.board.chip == Chip::Vrc4;
+    match register {
+      0x8000..=0x8003 => self.prg_banks[0] = data & 0x1F,
+      0x9002 | 0x9003 if vrc4 => self.prg_swap = data & 0x02 != 0,
+      0x9000..=0x9003 => {
+        let mode = if vrc4 { data & 0x03 } else { data & 0x01 };
+        self.mirroring = match mode {
+          0 => Mirroring::Vertical,
+          1 => Mirroring::Horizontal,
+          2 => Mirroring::SingleScreenLower,
+          _ => Mirroring::SingleScreenUpper,
+        };
+      }
+      0xA000..=0xA003 => self.prg_banks[1] = data & 0x1F,
+      0xB000..=0xEFFF => {
+        let slot = (((register - 0xB000) >> 12) * 2 + ((register >> 1) & 0x01)) as usize;
+        let bank = self.chr_banks[slot];
+        self.chr_banks[slot] = if register & 0x01 == 0 {
+          (bank & !0x0F) | (data & 0x0F) as usize
+        } else {
+          (bank & 0x0F) | (((data & 0x1F) as usize) << 4)
+        };
+      }
+      0xF000 if vrc4 => self.irq.write_latch_nibble(false, data),
+      0xF001 if vrc4 => self.irq.write_latch_nibble(true, data),
+      0xF002 if vrc4 => self.irq.write_control(data),
+      0xF003 if vrc4 => self.irq.acknowledge(),
+      _ => {}
+    }
+    self.update_banks();
+  }
+}
+
+impl Mapper for Vrc {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    match address {
+      0x6000..=0x7FFF => self.prg_ram.write((address & 0x1FFF) as usize, data),
+      0x8000..=0xFFFF => self.write_register(self.register(address), data),
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return self.mirroring;
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq.pending();
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    self.irq.tick(cycles);
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_board_wiring() {
+    // VRC4a 的 A0、A1 接 CPU 的 A1、A2，VRC4c 接 A6、A7
+    let mut cartridge = banked_cartridge(21, 0x2000, 16, 0x0400, 64);
+    cartridge.submapper = 1;
+    let mut mapper = Vrc::new(cartridge);
+    mapper.cpu_write(0xB004, 0x03);
+    assert_eq!(mapper.ppu_read(0x0400), 3);
+
+    let mut mapper = Vrc::new(banked_cartridge(21, 0x2000, 16, 0x0400, 64));
+    mapper.cpu_write(0xB080, 0x05);
+    mapper.cpu_write(0xB0C0, 0x02);
+    assert_eq!(mapper.ppu_read(0x0400), 0x25);
+    mapper.cpu_write(0xB004, 0x03);
+    assert_eq!(mapper.ppu_read(0x0400), 0x23);
+
+    // VRC2a 的 CHR bank 右移一位
+    let mut mapper = Vrc::new(banked_cartridge(22, 0x2000, 16, 0x0400, 64));
+    mapper.cpu_write(0xB000, 0x06);
+    assert_eq!(mapper.ppu_read(0x0000), 3);
+  }
+
+  #[test]
+  fn test_prg_swap_and_mirroring() {
+    let mut mapper = Vrc::with_board(banked_cartridge(23, 0x2000, 16, 0x0400, 8), Board::VRC4F);
+    mapper.cpu_write(0x8000, 3);
+    mapper.cpu_write(0xA000, 4);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xA000), 4);
+    assert_eq!(mapper.cpu_peek(0xC000), 14);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+
+    mapper.cpu_write(0x9002, 0x02);
+    assert_eq!(mapper.cpu_peek(0x8000), 14);
+    assert_eq!(mapper.cpu_peek(0xC000), 3);
+
+    mapper.cpu_write(0x9000, 0x03);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+    // VRC2 只有 1 位镜像，也没有 PRG 模式
+    let mut mapper = Vrc::with_board(banked_cartridge(23, 0x2000, 16, 0x0400, 8), Board::VRC2B);
+    mapper.cpu_write(0x9000, 0x03);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.cpu_write(0x9002, 0x02);
+    assert_eq!(mapper.cpu_peek(0xC000), 14);
+  }
+
+  #[test]
+  fn test_vrc4_irq() {
+    let mut mapper = Vrc::with_board(banked_cartridge(25, 0x2000, 16, 0x0400, 8), Board::VRC4B);
+    // VRC4b 的 A0、A1 接 CPU 的 A1、A0
+    mapper.cpu_write(0xF000, 0x0E);
+    mapper.cpu_write(0xF002, 0x0F);
+    mapper.cpu_write(0xF001, 0x06);
+    mapper.tick(1);
+    assert!(!mapper.irq());
+    mapper.tick(1);
+    assert!(mapper.irq());
+    mapper.cpu_write(0xF003, 0);
+    assert!(!mapper.irq());
+  }
+}
@@ -0,0 +1,240 @@
+use super::banks::Banks;
+use super::vrc_irq::VrcIrq;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [VRC6](https://www.nesdev.org/wiki/VRC6)（mapper 24 VRC6a，mapper 26 VRC6b）
+///
+/// | 地址            | 寄存器                                              |
+/// |-----------------|----------------------------------------------------|
+/// | `$8000-$8003`   | `$8000` 的 16 KiB PRG bank                          |
+/// | `$9000-$B002`   | 扩展音频：两个方波和一个锯齿波                          |
+/// | `$B003`         | CHR 模式、镜像、A10 规则，第 7 位开启 PRG RAM           |
+/// | `$C000-$C003`   | `$C000` 的 8 KiB PRG bank，`$E000` 固定为最后一个 bank |
+/// | `$D000-$E003`   | CHR bank 0-7                                        |
+/// | `$F000-$F002`   | IRQ 锁存值、控制、确认                                 |
+///
+/// VRC6b 交换了芯片的 A0 和 A1。只实现了从 CIRAM 取命名表的情形，
+/// `$B003` 第 4 位选择的“用 CHR ROM 作命名表”没有游戏使用。
+/// `$B003` 第 5 位为 1 时 2 KiB bank 的两半是 `R & !1`、`R | 1`，为 0 时两半都是 `R`。
+/// 模拟器还没有 APU，扩展音频的寄存器只是锁存下来，由 [`Vrc6::audio_register`] 读出。
+pub struct Vrc6 {
+  /// VRC6b 交换 A0、A1
+  swap_lines: bool,
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  chr_banks: [usize; 8],
+  banking: u8,
+  /// `$9000-$9003`、`$A000-$A002`、`$B000-$B002` 的扩展音频寄存器
+  audio: [u8; 12],
+  irq: VrcIrq,
+}
+
+impl Vrc6 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let mut mapper = Vrc6 {
+      swap_lines: cartridge.mapper == 26,
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      chr_banks: [0; 8],
+      banking: 0,
+      audio: [0; 12],
+      irq: VrcIrq::new(),
+    };
+    mapper.prg_rom.set_from_end(3, 1);
+    return mapper;
+  }
+
+  fn register(&self, address: u16) -> u16 {
+    let lines = address & 0x03;
+    let lines = if self.swap_lines { ((lines & 0x01) << 1) | (lines >> 1) } else { lines };
+    return (address & 0xF000) | lines;
+  }
+
+  /// 最后写入扩展音频寄存器 `register`（`$9000-$B002`，按 VRC6a 的地址）的值
+  pub fn audio_register(&self, register: u16) -> u8 {
+    return self.audio[Self::audio_index(register)];
+  }
+
+  fn audio_index(register: u16) -> usize {
+    return (((register >> 12) - 0x9) * 4 + (register & 0x03)) as usize;
+  }
+
+  /// 2 KiB bank 的两个 1 KiB 半边
+  fn chr_halves(&self, bank: usize) -> (usize, usize) {
+    if self.banking & 0x20 == 0 {
+      return (bank, bank);
+    }
+    return (bank & !1, bank | 1);
+  }
+
+  fn update_chr(&mut self) {
+    let r = self.chr_banks;
+    match self.banking & 0x03 {
+      // 8 个 1 KiB bank
+      0 => {
+        for (slot, &bank) in r.iter().enumerate() {
+          self.chr.set(slot, bank);
+        }
+      }
+      // R0-R3 各是 2 KiB bank
+      1 => {
+        for (i, &bank) in r[..4].iter().enumerate() {
+          let (low, high) = self.chr_halves(bank);
+          self.chr.set(i * 2, low);
+          self.chr.set(i * 2 + 1, high);
+        }
+      }
+      // $0000 是 R0-R3 的 1 KiB bank，$1000 是 R4、R5 的 2 KiB bank
+      _ => {
+        for (i, &bank) in r[..4].iter().enumerate() {
+          self.chr.set(i, bank);
+        }
+        for (i, &bank) in r[4..6].iter().enumerate() {
+          let (low, high) = self.chr_halves(bank);
+          self.chr.set(4 + i * 2, low);
+          self.chr.set(5 + i * 2, high);
+        }
+      }
+    }
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    return self.banking & 0x80 != 0;
+  }
+}
+
+impl Mapper for Vrc6 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    if let 0x6000..=0x7FFF = address {
+      if self.prg_ram_enabled() {
+        self.prg_ram.write((address & 0x1FFF) as usize, data);
+      }
+      return;
+    }
+
+    match self.register(address) {
+      0x8000..=0x8003 => {
+        let bank = (data & 0x0F) as usize * 2;
+        self.prg_rom.set(0, bank);
+        self.prg_rom.set(1, bank + 1);
+      }
+      0xB003 => {
+        self.banking = data;
+        self.update_chr();
+      }
+      register @ (0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002) => {
+        self.audio[Self::audio_index(register)] = data;
+      }
+      0xC000..=0xC003 => self.prg_rom.set(2, (data & 0x1F) as usize),
+      register @ 0xD000..=0xE003 => {
+        let slot = (((register - 0xD000) >> 12) * 4 + (register & 0x03)) as usize;
+        self.chr_banks[slot] = data as usize;
+        self.update_chr();
+      }
+      0xF000 => self.irq.write_latch(data),
+      0xF001 => self.irq.write_control(data),
+      0xF002 => self.irq.acknowledge(),
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return match (self.banking >> 2) & 0x03 {
+      0 => Mirroring::Vertical,
+      1 => Mirroring::Horizontal,
+      2 => Mirroring::SingleScreenLower,
+      _ => Mirroring::SingleScreenUpper,
+    };
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq.pending();
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    self.irq.tick(cycles);
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_banks_and_line_swap() {
+    for (mapper_number, chr_register) in [(24, 0xD001), (26, 0xD002)] {
+      let mut mapper = Vrc6::new(banked_cartridge(mapper_number, 0x2000, 16, 0x0400, 16));
+      mapper.cpu_write(0x8000, 2);
+      mapper.cpu_write(0xC000, 7);
+      assert_eq!(mapper.cpu_peek(0x8000), 4);
+      assert_eq!(mapper.cpu_peek(0xA000), 5);
+      assert_eq!(mapper.cpu_peek(0xC000), 7);
+      assert_eq!(mapper.cpu_peek(0xE000), 15);
+
+      mapper.cpu_write(chr_register, 9);
+      assert_eq!(mapper.ppu_read(0x0400), 9);
+    }
+  }
+
+  #[test]
+  fn test_banking_register() {
+    let mut mapper = Vrc6::new(banked_cartridge(24, 0x2000, 4, 0x0400, 16));
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0);
+
+    mapper.cpu_write(0xB003, 0x80 | 0x04 | 0x01);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+    // 2 KiB 模式，A10 规则关闭时两半都是 R1
+    mapper.cpu_write(0xD001, 5);
+    assert_eq!(mapper.ppu_read(0x0800), 5);
+    assert_eq!(mapper.ppu_read(0x0C00), 5);
+    mapper.cpu_write(0xB003, 0x80 | 0x20 | 0x04 | 0x01);
+    assert_eq!(mapper.ppu_read(0x0800), 4);
+    assert_eq!(mapper.ppu_read(0x0C00), 5);
+  }
+
+  #[test]
+  fn test_audio_registers_are_latched() {
+    let mut mapper = Vrc6::new(banked_cartridge(26, 0x2000, 4, 0x0400, 16));
+    // VRC6b 的 $9002 是芯片的 $9001
+    mapper.cpu_write(0x9002, 0x42);
+    mapper.cpu_write(0xB001, 0x24);
+    assert_eq!(mapper.audio_register(0x9001), 0x42);
+    assert_eq!(mapper.audio_register(0xB002), 0x24);
+  }
+}
@@ -0,0 +1,183 @@
+use super::banks::Banks;
+use super::vrc_irq::VrcIrq;
+use super::Mapper;
+use crate::cartridge::mirroring::Mirroring;
+use crate::cartridge::Cartridge;
+
+/// [VRC7](https://www.nesdev.org/wiki/VRC7)（mapper 85）
+///
+/// | 地址            | 寄存器                                        |
+/// |-----------------|----------------------------------------------|
+/// | `$8000`         | `$8000` 的 8 KiB PRG bank                     |
+/// | `$8010`         | `$A000` 的 8 KiB PRG bank                     |
+/// | `$9000`         | `$C000` 的 8 KiB PRG bank，`$E000` 固定        |
+/// | `$9010-$9030`   | 扩展音频（FM 合成）的寄存器选择和数据            |
+/// | `$A000-$D010`   | CHR bank 0-7                                  |
+/// | `$E000`         | 镜像、第 6 位静音、第 7 位开启 PRG RAM           |
+/// | `$E010-$F010`   | IRQ 锁存值、控制、确认                          |
+///
+/// 表中的 `$xx10` 是芯片的 A0 为 1。VRC7a（子 mapper 2）的 A0 接 CPU 的 A4，
+/// VRC7b（子 mapper 1）接 A3，子 mapper 为 0 时两条都接。
+/// 模拟器还没有 APU，`$9010` 选择、`$9030` 写入的 FM 合成寄存器只是锁存下来，
+/// 由 [`Vrc7::audio_register`] 读出。
+pub struct Vrc7 {
+  a0: u16,
+  prg_rom: Banks,
+  prg_ram: Banks,
+  battery: bool,
+  chr: Banks,
+
+  control: u8,
+  /// `$9010` 选择的 FM 合成寄存器，以及各个寄存器最后写入的值
+  audio_select: u8,
+  audio: [u8; 0x40],
+  irq: VrcIrq,
+}
+
+impl Vrc7 {
+  pub fn new(cartridge: Cartridge) -> Self {
+    let a0 = match cartridge.submapper {
+      1 => 0x08,
+      2 => 0x10,
+      _ => 0x18,
+    };
+    let mut mapper = Vrc7 {
+      a0,
+      prg_rom: Banks::new(cartridge.prg_rom, 0x8000, 0x2000, false),
+      prg_ram: Banks::new(vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size], 0x2000, 0x2000, true),
+      battery: cartridge.battery,
+      chr: Banks::chr(cartridge.chr_rom, cartridge.chr_ram_size, 0x0400),
+      control: 0,
+      audio_select: 0,
+      audio: [0; 0x40],
+      irq: VrcIrq::new(),
+    };
+    mapper.prg_rom.set_from_end(3, 1);
+    return mapper;
+  }
+
+  /// 最后写入 FM 合成寄存器 `register`（`$00-$3F`）的值
+  pub fn audio_register(&self, register: u8) -> u8 {
+    return self.audio[(register & 0x3F) as usize];
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    return self.control & 0x80 != 0;
+  }
+}
+
+impl Mapper for Vrc7 {
+  fn cpu_peek(&self, address: u16) -> u8 {
+    return match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read((address & 0x1FFF) as usize),
+      0x8000..=0xFFFF => self.prg_rom.read((address & 0x7FFF) as usize),
+      _ => 0,
+    };
+  }
+
+  fn cpu_write(&mut self, address: u16, data: u8) {
+    if let 0x6000..=0x7FFF = address {
+      if self.prg_ram_enabled() {
+        self.prg_ram.write((address & 0x1FFF) as usize, data);
+      }
+      return;
+    }
+
+    let a0 = (address & self.a0 != 0) as u16;
+    match ((address & 0xF000), a0) {
+      (0x8000, 0) => self.prg_rom.set(0, (data & 0x3F) as usize),
+      (0x8000, _) => self.prg_rom.set(1, (data & 0x3F) as usize),
+      (0x9000, 0) => self.prg_rom.set(2, (data & 0x3F) as usize),
+      // 扩展音频的寄存器由 A5 区分选择和数据
+      (0x9000, _) if address & 0x20 == 0 => self.audio_select = data & 0x3F,
+      (0x9000, _) => self.audio[self.audio_select as usize] = data,
+      (register @ 0xA000..=0xD000, a0) => {
+        let slot = (((register - 0xA000) >> 12) * 2 + a0) as usize;
+        self.chr.set(slot, data as usize);
+      }
+      (0xE000, 0) => self.control = data,
+      (0xE000, _) => self.irq.write_latch(data),
+      (0xF000, 0) => self.irq.write_control(data),
+      (0xF000, _) => self.irq.acknowledge(),
+      _ => {}
+    }
+  }
+
+  fn ppu_read(&mut self, address: u16) -> u8 {
+    return self.chr.read(address as usize);
+  }
+
+  fn ppu_write(&mut self, address: u16, data: u8) {
+    self.chr.write(address as usize, data);
+  }
+
+  fn mirroring(&self) -> Mirroring {
+    return match self.control & 0x03 {
+      0 => Mirroring::Vertical,
+      1 => Mirroring::Horizontal,
+      2 => Mirroring::SingleScreenLower,
+      _ => Mirroring::SingleScreenUpper,
+    };
+  }
+
+  fn irq(&self) -> bool {
+    return self.irq.pending();
+  }
+
+  fn tick(&mut self, cycles: u64) {
+    self.irq.tick(cycles);
+  }
+
+  fn save_data(&self) -> Option<&[u8]> {
+    return self.battery.then(|| self.prg_ram.data());
+  }
+
+  fn load_save_data(&mut self, data: &[u8]) {
+    self.prg_ram.load(data);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mapper::test::banked_cartridge;
+
+  #[test]
+  fn test_registers() {
+    let mut cartridge = banked_cartridge(85, 0x2000, 16, 0x0400, 16);
+    cartridge.submapper = 1;
+    let mut mapper = Vrc7::new(cartridge);
+    mapper.cpu_write(0x8000, 3);
+    mapper.cpu_write(0x8008, 4);
+    mapper.cpu_write(0x9000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), 3);
+    assert_eq!(mapper.cpu_peek(0xA000), 4);
+    assert_eq!(mapper.cpu_peek(0xC000), 5);
+    assert_eq!(mapper.cpu_peek(0xE000), 15);
+
+    mapper.cpu_write(0xB008, 7);
+    assert_eq!(mapper.ppu_read(0x0C00), 7);
+
+    mapper.cpu_write(0xE000, 0x81);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), 0x42);
+
+    mapper.cpu_write(0xE008, 0xFF);
+    mapper.cpu_write(0xF000, 0x06);
+    mapper.tick(1);
+    assert!(mapper.irq());
+    mapper.cpu_write(0xF008, 0);
+    assert!(!mapper.irq());
+  }
+
+  #[test]
+  fn test_audio_registers_are_latched() {
+    let mut mapper = Vrc7::new(banked_cartridge(85, 0x2000, 16, 0x0400, 16));
+    mapper.cpu_write(0x9000, 5);
+    mapper.cpu_write(0x9010, 0x30);
+    mapper.cpu_write(0x9030, 0x7F);
+    assert_eq!(mapper.audio_register(0x30), 0x7F);
+    assert_eq!(mapper.cpu_peek(0xC000), 5);
+  }
+}
@@ -0,0 +1,132 @@
+/// 一条扫描线是 341 个 PPU 时钟，也就是 113.667 个 CPU 周期
+const PRESCALER_PERIOD: i16 = 341;
+
+/// [Konami VRC 系列的 IRQ 计数器](https://www.nesdev.org/wiki/VRC_IRQ)，VRC4、VRC6、VRC7 共用。
+///
+/// 计数器按 CPU 周期计时，不看 PPU：周期模式下每个 CPU 周期加一，
+/// 扫描线模式下由预分频器每 341/3 个 CPU 周期加一。计数器从 $FF 溢出时装入锁存值并触发 IRQ。
+pub struct VrcIrq {
+  latch: u8,
+  counter: u8,
+  prescaler: i16,
+
+  enabled: bool,
+  /// 确认 IRQ 之后是否继续开启
+  enable_after_ack: bool,
+  cycle_mode: bool,
+  pending: bool,
+}
+
+impl VrcIrq {
+  pub fn new() -> Self {
+    return VrcIrq {
+      latch: 0,
+      counter: 0,
+      prescaler: PRESCALER_PERIOD,
+      enabled: false,
+      enable_after_ack: false,
+      cycle_mode: false,
+      pending: false,
+    };
+  }
+
+  pub fn write_latch(&mut self, data: u8) {
+    self.latch = data;
+  }
+
+  /// VRC4 的锁存值分两次写入，每次 4 位
+  pub fn write_latch_nibble(&mut self, high: bool, data: u8) {
+    if high {
+      self.latch = (self.latch & 0x0F) | (data << 4);
+    } else {
+      self.latch = (self.latch & 0xF0) | (data & 0x0F);
+    }
+  }
+
+  /// 控制寄存器：第 0 位是确认后是否开启，第 1 位开启，第 2 位选择周期模式。
+  /// 写入会确认 IRQ；开启时重新装载计数器和预分频器
+  pub fn write_control(&mut self, data: u8) {
+    self.enable_after_ack = data & 0x01 != 0;
+    self.enabled = data & 0x02 != 0;
+    self.cycle_mode = data & 0x04 != 0;
+    self.pending = false;
+    if self.enabled {
+      self.counter = self.latch;
+      self.prescaler = PRESCALER_PERIOD;
+    }
+  }
+
+  pub fn acknowledge(&mut self) {
+    self.pending = false;
+    self.enabled = self.enable_after_ack;
+  }
+
+  pub fn pending(&self) -> bool {
+    return self.pending;
+  }
+
+  pub fn tick(&mut self, cycles: u64) {
+    if !self.enabled {
+      return;
+    }
+    for _ in 0..cycles {
+      if self.cycle_mode {
+        self.clock();
+        continue;
+      }
+      self.prescaler -= 3;
+      if self.prescaler <= 0 {
+        self.prescaler += PRESCALER_PERIOD;
+        self.clock();
+      }
+    }
+  }
+
+  fn clock(&mut self) {
+    if self.counter == 0xFF {
+      self.counter = self.latch;
+      self.pending = true;
+    } else {
+      self.counter += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_cycle_mode() {
+    let mut irq = VrcIrq::new();
+    irq.write_latch(0xFD);
+    irq.write_control(0x07);
+    irq.tick(2);
+    assert!(!irq.pending());
+    irq.tick(1);
+    assert!(irq.pending());
+
+    // 确认之后按控制寄存器第 0 位继续计数，计数器已经装回锁存值
+    irq.acknowledge();
+    irq.tick(3);
+    assert!(irq.pending());
+  }
+
+  #[test]
+  fn test_scanline_mode() {
+    let mut irq = VrcIrq::new();
+    irq.write_latch_nibble(false, 0x0E);
+    irq.write_latch_nibble(true, 0x0F);
+    irq.write_control(0x02);
+
+    // 两条扫描线是 227.3 个 CPU 周期
+    irq.tick(227);
+    assert!(!irq.pending());
+    irq.tick(1);
+    assert!(irq.pending());
+
+    irq.acknowledge();
+    irq.tick(1000);
+    assert!(!irq.pending());
+  }
+}
@@ -0,0 +1,60 @@
+use super::Memory;
+
+/// 整个 64 KiB 地址空间都是可读写的 RAM，没有镜像也没有外设。
+///
+/// 用来直接运行原始的 6502 程序，例如 Klaus Dormann 的功能测试。
+pub struct FlatMemory {
+  data: Box<[u8; 0x10000]>,
+}
+
+impl FlatMemory {
+  pub fn new() -> Self {
+    return FlatMemory {
+      data: Box::new([0; 0x10000]),
+    };
+  }
+
+  /// 把原始镜像写入 `address` 开始的位置，超过 `$FFFF` 的部分回绕到 `$0000`
+  pub fn load(&mut self, address: u16, image: &[u8]) {
+    for (i, byte) in image.iter().enumerate() {
+      self.data[address.wrapping_add(i as u16) as usize] = *byte;
+    }
+  }
+
+  /// 设置复位向量 `$FFFC`，`CPU::reset` 之后从 `address` 开始执行
+  pub fn set_reset_vector(&mut self, address: u16) {
+    self.load(0xFFFC, &address.to_le_bytes());
+  }
+}
+
+impl Memory for FlatMemory {
+  fn peek(&self, address: u16) -> u8 {
+    return self.data[address as usize];
+  }
+
+  fn write(&mut self, address: u16, data: u8) {
+    self.data[address as usize] = data;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_load_wraps_around_address_space() {
+    let mut memory = FlatMemory::new();
+    memory.load(0xFFFE, &[0x01, 0x02, 0x03]);
+
+    assert_eq!(memory.peek(0xFFFF), 0x02);
+    assert_eq!(memory.peek(0x0000), 0x03);
+  }
+
+  #[test]
+  fn test_set_reset_vector() {
+    let mut memory = FlatMemory::new();
+    memory.set_reset_vector(0x0400);
+
+    assert_eq!(memory.peek_u16(0xFFFC), 0x0400);
+  }
+}
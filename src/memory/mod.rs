@@ -0,0 +1,72 @@
+use crate::cpu::interrupt::IrqSource;
+
+pub mod flat;
+
+/// CPU 看到的 16 位地址空间。
+///
+/// NES 的 [`Bus`](crate::bus::Bus) 是其中一种实现，测试或者其他 6502 机器可以换成别的实现，
+/// 例如整块 64 KiB RAM 的 [`FlatMemory`](flat::FlatMemory)。
+///
+/// 读取分为两种：`read` 是 CPU 真正的总线读取，可能带有副作用（例如读取 PPU 状态寄存器会清除 vblank 标志）；
+/// `peek` 只查看当前的值，不改变任何状态，供 trace 和调试器使用。
+/// 读取没有副作用的实现只需要实现 `peek`。
+pub trait Memory {
+  fn peek(&self, address: u16) -> u8;
+
+  fn read(&mut self, address: u16) -> u8 {
+    return self.peek(address);
+  }
+
+  fn write(&mut self, address: u16, data: u8);
+
+  /// 小端序读取 16 位数据
+  fn read_u16(&mut self, address: u16) -> u16 {
+    let lo = self.read(address) as u16;
+    let hi = self.read(address.wrapping_add(1)) as u16;
+    return (hi << 8) | lo;
+  }
+
+  /// 小端序查看 16 位数据，不产生副作用
+  fn peek_u16(&self, address: u16) -> u16 {
+    let lo = self.peek(address) as u16;
+    let hi = self.peek(address.wrapping_add(1)) as u16;
+    return (hi << 8) | lo;
+  }
+
+  /// 通知外设经过了 `cycles` 个 CPU 周期。CPU 在执行指令的最后一个周期之前和之后各调用一次，
+  /// 响应中断后也会调用
+  fn tick(&mut self, _cycles: u64) {}
+
+  /// 是否有外设产生了 NMI，取出后清除
+  fn poll_nmi(&mut self) -> bool {
+    return false;
+  }
+
+  /// 外设当前拉低 IRQ 线的情况，IRQ 是电平触发的，外设自己负责在被响应后释放
+  fn irq(&self) -> IrqSource {
+    return IrqSource::empty();
+  }
+
+  /// 刚执行完的指令是否启动了需要暂停 CPU 的 OAM DMA，取出后清除
+  fn poll_dma(&mut self) -> bool {
+    return false;
+  }
+
+  /// 视频设备已经完成的帧数，没有视频设备时为 `None`
+  fn frame_count(&self) -> Option<u64> {
+    return None;
+  }
+
+  /// 视频设备当前的扫描线和时钟，供 trace 使用，没有视频设备时为 `None`
+  fn ppu_position(&self) -> Option<(u16, u16)> {
+    return None;
+  }
+
+  /// 小端序写入 16 位数据
+  fn write_u16(&mut self, address: u16, data: u16) {
+    let lo = (data & 0x00FF) as u8;
+    let hi = (data >> 8) as u8;
+    self.write(address, lo);
+    self.write(address.wrapping_add(1), hi);
+  }
+}
@@ -0,0 +1,27 @@
+/// PPU 输出的一帧画面，每个像素按 RGB 顺序占 3 个字节
+pub struct Frame {
+  pub data: Vec<u8>,
+}
+
+impl Frame {
+  pub const WIDTH: usize = 256;
+  pub const HEIGHT: usize = 240;
+
+  pub fn new() -> Self {
+    return Frame {
+      data: vec![0; Frame::WIDTH * Frame::HEIGHT * 3],
+    };
+  }
+
+  pub fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+    let base = (y * Frame::WIDTH + x) * 3;
+    self.data[base] = rgb.0;
+    self.data[base + 1] = rgb.1;
+    self.data[base + 2] = rgb.2;
+  }
+
+  pub fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+    let base = (y * Frame::WIDTH + x) * 3;
+    return (self.data[base], self.data[base + 1], self.data[base + 2]);
+  }
+}
@@ -0,0 +1,526 @@
+pub mod frame;
+pub mod palette;
+pub mod registers;
+mod render;
+pub mod sprite;
+
+use self::frame::Frame;
+use self::registers::controller::ControlRegister;
+use self::registers::loopy::VramAddress;
+use self::registers::mask::MaskRegister;
+use self::registers::status::StatusRegister;
+use self::render::BackgroundPipeline;
+use self::sprite::{Sprite, SpritePixel};
+use crate::mapper::SharedMapper;
+
+/// 每条扫描线的时钟数
+pub const DOTS_PER_SCANLINE: u16 = 341;
+
+/// 每帧的扫描线数，0-239 可见，240 空闲，241-260 vblank，261 是预渲染扫描线
+pub const SCANLINES_PER_FRAME: u16 = 262;
+
+/// 预渲染扫描线
+const PRE_RENDER_SCANLINE: u16 = 261;
+
+/// vblank 开始的扫描线
+const VBLANK_SCANLINE: u16 = 241;
+
+/// [PPU](https://www.nesdev.org/wiki/PPU)
+///
+/// PPU 有自己独立的 14 位地址空间：
+///
+/// | 地址范围        | 内容                          |
+/// |-----------------|-------------------------------|
+/// | `$0000-$1FFF`   | 图案表，由卡带的 mapper 提供    |
+/// | `$2000-$2FFF`   | 命名表，`$3000-$3EFF` 是它的镜像 |
+/// | `$3F00-$3F1F`   | 调色板，`$3F20-$3FFF` 是它的镜像 |
+///
+/// CPU 只能通过 `$2000-$2007` 这 8 个寄存器访问 PPU。
+pub struct PPU {
+  /// 卡带，提供图案表和命名表的镜像方式
+  mapper: SharedMapper,
+
+  pub palette_table: [u8; 32],
+
+  /// 主机自带 2 KiB 命名表 RAM，四屏模式下卡带额外提供 2 KiB
+  pub vram: [u8; 0x1000],
+
+  pub oam_address: u8,
+
+  pub oam_data: [u8; 256],
+
+  pub controller: ControlRegister,
+
+  pub mask: MaskRegister,
+
+  pub status: StatusRegister,
+
+  /// 当前的 VRAM 地址（v），渲染时就是当前的滚动位置
+  pub v: VramAddress,
+
+  /// 临时的 VRAM 地址（t），`$2000`、`$2005`、`$2006` 写入这里
+  pub t: VramAddress,
+
+  /// 图块内的像素列（x），由 `$2005` 第一次写入
+  pub fine_x: u8,
+
+  /// `$2005` 和 `$2006` 共用的写入开关（w），读取 `$2002` 时清零
+  write_toggle: bool,
+
+  /// PPUDATA 的读取缓冲，读取调色板以外的地址时返回的是上一次读取的值
+  read_buffer: u8,
+
+  /// 最近一次读写寄存器时留在数据总线上的值，读取只写寄存器时返回它
+  open_bus: u8,
+
+  /// 当前的扫描线，0-261
+  pub scanline: u16,
+
+  /// 当前扫描线上的时钟，0-340
+  pub dot: u16,
+
+  /// 正在绘制的画面，在 vblank 开始时完整
+  pub frame: Frame,
+
+  /// 已经完成的帧数
+  frame_count: u64,
+
+  /// 上电以来经过的 PPU 时钟数，mapper 用它测量 A12 保持低电平的时间
+  cycles: u64,
+
+  /// 奇数帧开启渲染时，预渲染扫描线少一个时钟
+  odd_frame: bool,
+
+  /// 等待 CPU 响应的 NMI
+  nmi_interrupt: bool,
+
+  /// vblank 开始前的一个时钟读取了 PPUSTATUS，本帧不设置 vblank 标志
+  suppress_vblank: bool,
+
+  /// 背景取数流水线，见 `render.rs`
+  background: BackgroundPipeline,
+
+  /// 本条扫描线求值选出的、要在下一条扫描线绘制的精灵
+  line_sprites: Vec<Sprite>,
+
+  /// 当前扫描线每个像素上的精灵，在上一条扫描线的第 257-320 个时钟取得
+  sprite_line: [Option<SpritePixel>; Frame::WIDTH],
+
+  /// 不限制每条扫描线 8 个精灵，可以减少闪烁，游戏看到的状态标志不变
+  pub remove_sprite_limit: bool,
+}
+
+impl PPU {
+  pub fn new(mapper: SharedMapper) -> Self {
+    return PPU {
+      mapper,
+      palette_table: [0; 32],
+      vram: [0; 0x1000],
+      oam_address: 0,
+      oam_data: [0; 256],
+      controller: ControlRegister::empty(),
+      mask: MaskRegister::empty(),
+      status: StatusRegister::empty(),
+      v: VramAddress::default(),
+      t: VramAddress::default(),
+      fine_x: 0,
+      write_toggle: false,
+      read_buffer: 0,
+      open_bus: 0,
+      scanline: 0,
+      dot: 0,
+      frame: Frame::new(),
+      frame_count: 0,
+      cycles: 0,
+      odd_frame: false,
+      nmi_interrupt: false,
+      suppress_vblank: false,
+      background: BackgroundPipeline::default(),
+      line_sprites: Vec::with_capacity(64),
+      sprite_line: [None; Frame::WIDTH],
+      remove_sprite_limit: false,
+    };
+  }
+
+  /// 已经完成的帧数，每次 vblank 开始时加一，此时 `frame` 中是完整的一帧
+  pub fn frame_count(&self) -> u64 {
+    return self.frame_count;
+  }
+
+  /// 取出等待响应的 NMI
+  pub fn poll_nmi(&mut self) -> bool {
+    return std::mem::take(&mut self.nmi_interrupt);
+  }
+
+  /// 前进 `dots` 个 PPU 时钟，CPU 的 1 个周期对应 3 个 PPU 时钟
+  pub fn tick(&mut self, dots: u64) {
+    for _ in 0..dots {
+      self.step();
+    }
+  }
+
+  /// 当前是否处在取数的扫描线（可见扫描线和预渲染扫描线）并且开启了渲染
+  fn is_rendering(&self) -> bool {
+    return self.mask.rendering_enabled() && (self.scanline < 240 || self.scanline == PRE_RENDER_SCANLINE);
+  }
+
+  /// 执行一个 PPU 时钟，[时序图](https://www.nesdev.org/wiki/PPU_rendering#Frame_timing_diagram)
+  fn step(&mut self) {
+    if self.is_rendering() {
+      self.render_dot();
+    }
+    if self.scanline < 240 && (1..=256).contains(&self.dot) {
+      self.output_pixel((self.dot - 1) as usize, self.scanline as usize);
+    }
+    if self.dot == 260 && self.is_rendering() {
+      self.mapper.borrow_mut().scanline();
+    }
+
+    match (self.scanline, self.dot) {
+      (0..=239, 256) => self.evaluate_sprites(self.scanline),
+      (0..=239 | PRE_RENDER_SCANLINE, 257..=320) => self.fetch_sprites(self.scanline),
+      (VBLANK_SCANLINE, 1) => {
+        if !std::mem::take(&mut self.suppress_vblank) {
+          self.status.insert(StatusRegister::VBLANK_STARTED);
+          if self.controller.generate_vblank_nmi() {
+            self.nmi_interrupt = true;
+          }
+        }
+        self.frame_count += 1;
+      }
+      (PRE_RENDER_SCANLINE, 1) => {
+        self.status.remove(
+          StatusRegister::VBLANK_STARTED | StatusRegister::SPRITE_ZERO_HIT | StatusRegister::SPRITE_OVERFLOW,
+        );
+        self.line_sprites.clear();
+      }
+      _ => {}
+    }
+
+    self.cycles += 1;
+    self.dot += 1;
+    // 奇数帧开启渲染时跳过预渲染扫描线的最后一个时钟，直接进入 (0, 0)
+    if self.scanline == PRE_RENDER_SCANLINE && self.dot == 340 && self.odd_frame && self.mask.rendering_enabled() {
+      self.dot = DOTS_PER_SCANLINE;
+    }
+    if self.dot == DOTS_PER_SCANLINE {
+      self.dot = 0;
+      self.scanline += 1;
+      if self.scanline == SCANLINES_PER_FRAME {
+        self.scanline = 0;
+        self.odd_frame = !self.odd_frame;
+      }
+    }
+  }
+
+  /// 当前的 VRAM 地址，也就是下一次访问 PPUDATA 的地址
+  pub fn vram_address(&self) -> u16 {
+    return self.v.address();
+  }
+
+  /// CPU 读取 `$2000-$2007`，地址需要先由总线折叠到这个范围
+  pub fn read_register(&mut self, address: u16) -> u8 {
+    let data = match address {
+      // PPUSTATUS
+      0x2002 => {
+        let data = self.peek_register(address);
+        self.status.remove(StatusRegister::VBLANK_STARTED);
+        self.write_toggle = false;
+        // 与 vblank 开始同时读取会错过标志或 NMI
+        if self.scanline == VBLANK_SCANLINE {
+          match self.dot {
+            1 => self.suppress_vblank = true,
+            2 | 3 => self.nmi_interrupt = false,
+            _ => {}
+          }
+        }
+        data
+      }
+      // OAMDATA
+      0x2004 => self.peek_register(address),
+      // PPUDATA
+      0x2007 => self.read_data(),
+      // 只写寄存器
+      _ => return self.open_bus,
+    };
+    self.open_bus = data;
+    return data;
+  }
+
+  /// 查看寄存器的值，不改变任何状态
+  pub fn peek_register(&self, address: u16) -> u8 {
+    return match address {
+      0x2002 => (self.status.bits() & 0xE0) | (self.open_bus & 0x1F),
+      0x2004 => {
+        let data = self.oam_data[self.oam_address as usize];
+        // 属性字节的第 2-4 位不存在，读出来总是 0
+        if self.oam_address & 0x03 == 0x02 { data & 0xE3 } else { data }
+      }
+      0x2007 => {
+        let address = self.v.address();
+        if address >= 0x3F00 { self.read_palette(address) } else { self.read_buffer }
+      }
+      _ => self.open_bus,
+    };
+  }
+
+  /// CPU 写入 `$2000-$2007`，地址需要先由总线折叠到这个范围
+  pub fn write_register(&mut self, address: u16, data: u8) {
+    self.open_bus = data;
+    match address {
+      0x2000 => {
+        let nmi_enabled = self.controller.generate_vblank_nmi();
+        self.controller = ControlRegister::from_bits_truncate(data);
+        self.t.set_nametable(data);
+        // vblank 期间打开 NMI 会立即产生一次 NMI
+        if !nmi_enabled && self.controller.generate_vblank_nmi() && self.status.contains(StatusRegister::VBLANK_STARTED) {
+          self.nmi_interrupt = true;
+        }
+      }
+      0x2001 => self.mask = MaskRegister::from_bits_truncate(data),
+      // PPUSTATUS 是只读的
+      0x2002 => {}
+      0x2003 => self.oam_address = data,
+      0x2004 => {
+        self.oam_data[self.oam_address as usize] = data;
+        self.oam_address = self.oam_address.wrapping_add(1);
+      }
+      0x2005 => {
+        if self.write_toggle {
+          self.t.set_scroll_y(data);
+        } else {
+          self.t.set_scroll_x(data);
+          self.fine_x = data & 0x07;
+        }
+        self.write_toggle = !self.write_toggle;
+      }
+      0x2006 => {
+        if self.write_toggle {
+          self.t.set_address_low(data);
+          self.v = self.t;
+          // 不渲染时 v 直接驱动 PPU 地址总线，MMC3 可以看到 A12 的变化
+          self.mapper.borrow_mut().ppu_address(self.v.address(), self.cycles);
+        } else {
+          self.t.set_address_high(data);
+        }
+        self.write_toggle = !self.write_toggle;
+      }
+      0x2007 => {
+        self.write_vram(self.v.address(), data);
+        self.increment_vram_address();
+      }
+      _ => unreachable!("PPU register {:04X} is not folded into $2000-$2007", address),
+    }
+  }
+
+  fn read_data(&mut self) -> u8 {
+    let address = self.v.address();
+    self.increment_vram_address();
+
+    if address >= 0x3F00 {
+      // 调色板直接返回，缓冲区装入调色板“下面”的命名表数据
+      self.read_buffer = self.read_vram(address - 0x1000);
+      return self.read_palette(address);
+    }
+    let data = self.read_buffer;
+    self.read_buffer = self.read_vram(address);
+    return data;
+  }
+
+  /// 访问 PPUDATA 之后递增 v。渲染期间访问会让 v 同时做一次水平和垂直的图块递增
+  fn increment_vram_address(&mut self) {
+    if self.is_rendering() {
+      self.v.increment_x();
+      self.v.increment_y();
+    } else {
+      self.v.increment(self.controller.vram_address_increment());
+    }
+  }
+
+  /// 按 PPU 地址空间读取
+  pub fn read_vram(&mut self, address: u16) -> u8 {
+    let address = address & 0x3FFF;
+    self.mapper.borrow_mut().ppu_address(address, self.cycles);
+    return match address {
+      0x0000..=0x1FFF => self.mapper.borrow_mut().ppu_read(address),
+      0x2000..=0x3EFF => {
+        let data = self.mapper.borrow_mut().nametable_read(address);
+        data.unwrap_or_else(|| self.vram[self.mirror_nametable_address(address)])
+      }
+      _ => self.read_palette(address),
+    };
+  }
+
+  /// 按 PPU 地址空间写入，CHR ROM 不可写
+  pub fn write_vram(&mut self, address: u16, data: u8) {
+    let address = address & 0x3FFF;
+    self.mapper.borrow_mut().ppu_address(address, self.cycles);
+    match address {
+      0x0000..=0x1FFF => self.mapper.borrow_mut().ppu_write(address, data),
+      0x2000..=0x3EFF => {
+        let handled = self.mapper.borrow_mut().nametable_write(address, data);
+        if !handled {
+          self.vram[self.mirror_nametable_address(address)] = data;
+        }
+      }
+      _ => self.palette_table[palette_index(address)] = data & 0x3F,
+    }
+  }
+
+  fn read_palette(&self, address: u16) -> u8 {
+    return self.palette_table[palette_index(address)];
+  }
+
+  /// 把 `$2000-$3EFF` 映射到命名表 RAM 的下标
+  ///
+  /// ```text
+  /// 水平镜像    垂直镜像    四屏         单屏
+  /// [ A ] [ a ]  [ A ] [ B ]  [ A ] [ B ]  [ A ] [ a ]
+  /// [ B ] [ b ]  [ a ] [ b ]  [ C ] [ D ]  [ a ] [ a ]
+  /// ```
+  pub fn mirror_nametable_address(&self, address: u16) -> usize {
+    let address = (address - 0x2000) & 0x0FFF;
+    let table = address / 0x400;
+    let offset = (address % 0x400) as usize;
+    let physical = self.mapper.borrow().nametable_bank(table);
+    return physical * 0x400 + offset;
+  }
+}
+
+/// `$3F10/$3F14/$3F18/$3F1C` 是 `$3F00/$3F04/$3F08/$3F0C` 的镜像
+fn palette_index(address: u16) -> usize {
+  let index = (address & 0x1F) as usize;
+  return if index >= 0x10 && index & 0x03 == 0 { index - 0x10 } else { index };
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::cartridge::mirroring::Mirroring;
+  use crate::mapper::test::test_mapper;
+
+  fn test_ppu(mirroring: Mirroring) -> PPU {
+    return PPU::new(test_mapper(vec![0; 0x2000], mirroring));
+  }
+
+  fn set_address(ppu: &mut PPU, address: u16) {
+    ppu.write_register(0x2006, (address >> 8) as u8);
+    ppu.write_register(0x2006, address as u8);
+  }
+
+  #[test]
+  fn test_ppudata_read_is_buffered() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x2305, 0x66);
+    ppu.write_vram(0x2306, 0x77);
+
+    set_address(&mut ppu, 0x2305);
+    ppu.read_register(0x2007);
+    assert_eq!(ppu.read_register(0x2007), 0x66);
+    assert_eq!(ppu.read_register(0x2007), 0x77);
+    assert_eq!(ppu.vram_address(), 0x2308);
+  }
+
+  #[test]
+  fn test_ppudata_increment_32() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_register(0x2000, 0b100);
+    set_address(&mut ppu, 0x21FF);
+    ppu.write_register(0x2007, 0x66);
+    ppu.write_register(0x2007, 0x77);
+
+    assert_eq!(ppu.read_vram(0x21FF), 0x66);
+    assert_eq!(ppu.read_vram(0x221F), 0x77);
+  }
+
+  #[test]
+  fn test_palette_read_is_not_buffered() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x2F00, 0x12);
+    set_address(&mut ppu, 0x3F00);
+    ppu.write_register(0x2007, 0x2A);
+
+    set_address(&mut ppu, 0x3F00);
+    assert_eq!(ppu.read_register(0x2007), 0x2A);
+    // 缓冲区装入了 $2F00 的数据
+    set_address(&mut ppu, 0x2000);
+    assert_eq!(ppu.read_register(0x2007), 0x12);
+  }
+
+  #[test]
+  fn test_palette_mirrors() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x3F10, 0x01);
+    ppu.write_vram(0x3F24, 0x02);
+    ppu.write_vram(0x3F11, 0x03);
+
+    assert_eq!(ppu.read_vram(0x3F00), 0x01);
+    assert_eq!(ppu.read_vram(0x3F04), 0x02);
+    assert_eq!(ppu.read_vram(0x3F01), 0x00);
+    assert_eq!(ppu.read_vram(0x3FF1), 0x03);
+  }
+
+  #[test]
+  fn test_nametable_mirroring() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x2405, 0x11);
+    ppu.write_vram(0x2805, 0x22);
+    assert_eq!(ppu.read_vram(0x2005), 0x11);
+    assert_eq!(ppu.read_vram(0x2C05), 0x22);
+    assert_eq!(ppu.read_vram(0x3005), 0x11);
+
+    let mut ppu = test_ppu(Mirroring::Vertical);
+    ppu.write_vram(0x2805, 0x11);
+    ppu.write_vram(0x2C05, 0x22);
+    assert_eq!(ppu.read_vram(0x2005), 0x11);
+    assert_eq!(ppu.read_vram(0x2405), 0x22);
+  }
+
+  #[test]
+  fn test_status_read_resets_write_toggle_and_vblank() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x2305, 0x66);
+    ppu.status.insert(StatusRegister::VBLANK_STARTED);
+
+    ppu.write_register(0x2006, 0x21);
+    ppu.write_register(0x2006, 0x23);
+    ppu.write_register(0x2006, 0x05);
+    assert_ne!(ppu.vram_address(), 0x2305);
+
+    assert_eq!(ppu.read_register(0x2002) >> 7, 1);
+    assert_eq!(ppu.status.bits() >> 7, 0);
+    assert_eq!(ppu.peek_register(0x2002) >> 7, 0);
+
+    set_address(&mut ppu, 0x2305);
+    ppu.read_register(0x2007);
+    assert_eq!(ppu.read_register(0x2007), 0x66);
+  }
+
+  #[test]
+  fn test_oam_read_write() {
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_register(0x2003, 0x10);
+    ppu.write_register(0x2004, 0x66);
+    ppu.write_register(0x2004, 0x77);
+    ppu.write_register(0x2004, 0xFF);
+
+    ppu.write_register(0x2003, 0x10);
+    assert_eq!(ppu.read_register(0x2004), 0x66);
+    ppu.write_register(0x2003, 0x11);
+    assert_eq!(ppu.read_register(0x2004), 0x77);
+    // 属性字节
+    ppu.write_register(0x2003, 0x12);
+    assert_eq!(ppu.read_register(0x2004), 0xE3);
+  }
+
+  #[test]
+  fn test_chr_ram_is_writable() {
+    let mut ppu = PPU::new(test_mapper(vec![], Mirroring::Horizontal));
+    ppu.write_vram(0x1234, 0x55);
+    assert_eq!(ppu.read_vram(0x1234), 0x55);
+
+    let mut ppu = test_ppu(Mirroring::Horizontal);
+    ppu.write_vram(0x1234, 0x55);
+    assert_eq!(ppu.read_vram(0x1234), 0x00);
+  }
+}
@@ -0,0 +1,52 @@
+use bitflags::bitflags;
+
+bitflags! {
+  /// [PPUCTRL](https://www.nesdev.org/wiki/PPU_registers#PPUCTRL) `$2000` (bit 7 to bit 0)
+  ///
+  /// | Bit | Flag |    |
+  /// |-----|------|--------------------------------------------------|
+  /// |  7  | V    | 在 vblank 开始时产生 NMI                           |
+  /// |  6  | P    | PPU 主从选择                                       |
+  /// |  5  | H    | 精灵大小（0: 8x8, 1: 8x16）                        |
+  /// |  4  | B    | 背景图案表地址（0: $0000, 1: $1000）                |
+  /// |  3  | S    | 8x8 精灵图案表地址（0: $0000, 1: $1000）            |
+  /// |  2  | I    | 访问 PPUDATA 后 VRAM 地址的增量（0: 1, 1: 32）      |
+  /// | 1-0 | NN   | 基础命名表地址（$2000, $2400, $2800, $2C00）        |
+  pub struct ControlRegister: u8 {
+    const NAMETABLE1 = 0b00000001;
+    const NAMETABLE2 = 0b00000010;
+    const VRAM_ADD_INCREMENT = 0b00000100;
+    const SPRITE_PATTERN_ADDR = 0b00001000;
+    const BACKGROUND_PATTERN_ADDR = 0b00010000;
+    const SPRITE_SIZE = 0b00100000;
+    const MASTER_SLAVE_SELECT = 0b01000000;
+    const GENERATE_NMI = 0b10000000;
+  }
+}
+
+impl ControlRegister {
+  pub fn nametable_address(&self) -> u16 {
+    return 0x2000 + (self.bits() & 0b11) as u16 * 0x400;
+  }
+
+  pub fn vram_address_increment(&self) -> u16 {
+    return if self.contains(ControlRegister::VRAM_ADD_INCREMENT) { 32 } else { 1 };
+  }
+
+  pub fn sprite_pattern_address(&self) -> u16 {
+    return if self.contains(ControlRegister::SPRITE_PATTERN_ADDR) { 0x1000 } else { 0x0000 };
+  }
+
+  pub fn background_pattern_address(&self) -> u16 {
+    return if self.contains(ControlRegister::BACKGROUND_PATTERN_ADDR) { 0x1000 } else { 0x0000 };
+  }
+
+  /// 精灵的高度，8 或 16
+  pub fn sprite_size(&self) -> u8 {
+    return if self.contains(ControlRegister::SPRITE_SIZE) { 16 } else { 8 };
+  }
+
+  pub fn generate_vblank_nmi(&self) -> bool {
+    return self.contains(ControlRegister::GENERATE_NMI);
+  }
+}
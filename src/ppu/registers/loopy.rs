@@ -0,0 +1,168 @@
+/// PPU 内部的 15 位 VRAM 地址寄存器（v 和 t），见 [PPU scrolling](https://www.nesdev.org/wiki/PPU_scrolling)
+///
+/// 渲染时它同时记录了滚动位置：
+///
+/// ```text
+/// yyy NN YYYYY XXXXX
+/// ||| || ||||| +++++-- 图块列（coarse X）
+/// ||| || +++++-------- 图块行（coarse Y）
+/// ||| ++-------------- 命名表
+/// +++----------------- 图块内的像素行（fine Y）
+/// ```
+///
+/// `$2005` 和 `$2006` 只写入 t，`$2006` 第二次写入和渲染过程中的特定时钟才把 t 复制到 v。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VramAddress(pub u16);
+
+impl VramAddress {
+  const COARSE_X: u16 = 0x001F;
+  const COARSE_Y: u16 = 0x03E0;
+  const NAMETABLE_X: u16 = 0x0400;
+  const NAMETABLE_Y: u16 = 0x0800;
+  const FINE_Y: u16 = 0x7000;
+
+  /// 水平方向的位：图块列和水平命名表
+  const HORIZONTAL: u16 = VramAddress::COARSE_X | VramAddress::NAMETABLE_X;
+
+  /// 垂直方向的位：图块行、垂直命名表和像素行
+  const VERTICAL: u16 = VramAddress::COARSE_Y | VramAddress::NAMETABLE_Y | VramAddress::FINE_Y;
+
+  pub fn coarse_x(&self) -> u16 {
+    return self.0 & VramAddress::COARSE_X;
+  }
+
+  pub fn coarse_y(&self) -> u16 {
+    return (self.0 & VramAddress::COARSE_Y) >> 5;
+  }
+
+  pub fn fine_y(&self) -> u16 {
+    return (self.0 & VramAddress::FINE_Y) >> 12;
+  }
+
+  /// 访问 PPU 地址空间时使用的 14 位地址
+  pub fn address(&self) -> u16 {
+    return self.0 & 0x3FFF;
+  }
+
+  /// 当前图块在命名表中的地址
+  pub fn tile_address(&self) -> u16 {
+    return 0x2000 | (self.0 & 0x0FFF);
+  }
+
+  /// 当前图块所在 32x32 区域在属性表中的地址
+  pub fn attribute_address(&self) -> u16 {
+    return 0x23C0 | (self.0 & 0x0C00) | ((self.0 >> 4) & 0x38) | ((self.0 >> 2) & 0x07);
+  }
+
+  /// 非渲染期间访问 PPUDATA 后的递增
+  pub fn increment(&mut self, increment: u16) {
+    self.0 = self.0.wrapping_add(increment) & 0x7FFF;
+  }
+
+  /// 移动到下一个图块，越过第 31 列时切换水平命名表
+  pub fn increment_x(&mut self) {
+    if self.coarse_x() == 31 {
+      self.0 &= !VramAddress::COARSE_X;
+      self.0 ^= VramAddress::NAMETABLE_X;
+    } else {
+      self.0 += 1;
+    }
+  }
+
+  /// 移动到下一个像素行。越过第 29 行时切换垂直命名表；
+  /// 第 30、31 行实际上是属性表，从这里越过时回到第 0 行而不切换命名表
+  pub fn increment_y(&mut self) {
+    if self.fine_y() < 7 {
+      self.0 += 0x1000;
+      return;
+    }
+    self.0 &= !VramAddress::FINE_Y;
+    let coarse_y = match self.coarse_y() {
+      29 => {
+        self.0 ^= VramAddress::NAMETABLE_Y;
+        0
+      }
+      31 => 0,
+      coarse_y => coarse_y + 1,
+    };
+    self.0 = (self.0 & !VramAddress::COARSE_Y) | (coarse_y << 5);
+  }
+
+  /// 从 t 复制水平方向的位，发生在每条扫描线的第 257 个时钟
+  pub fn copy_horizontal(&mut self, t: VramAddress) {
+    self.0 = (self.0 & !VramAddress::HORIZONTAL) | (t.0 & VramAddress::HORIZONTAL);
+  }
+
+  /// 从 t 复制垂直方向的位，发生在预渲染扫描线的第 280-304 个时钟
+  pub fn copy_vertical(&mut self, t: VramAddress) {
+    self.0 = (self.0 & !VramAddress::VERTICAL) | (t.0 & VramAddress::VERTICAL);
+  }
+
+  /// 写入 `$2000`：命名表选择
+  pub fn set_nametable(&mut self, data: u8) {
+    self.0 = (self.0 & !(VramAddress::NAMETABLE_X | VramAddress::NAMETABLE_Y)) | ((data as u16 & 0x03) << 10);
+  }
+
+  /// 第一次写入 `$2005`：图块列，像素列由调用方保存到 x
+  pub fn set_scroll_x(&mut self, data: u8) {
+    self.0 = (self.0 & !VramAddress::COARSE_X) | (data as u16 >> 3);
+  }
+
+  /// 第二次写入 `$2005`：图块行和像素行
+  pub fn set_scroll_y(&mut self, data: u8) {
+    let data = data as u16;
+    self.0 = (self.0 & !(VramAddress::COARSE_Y | VramAddress::FINE_Y)) | ((data >> 3) << 5) | ((data & 0x07) << 12);
+  }
+
+  /// 第一次写入 `$2006`：地址的高 6 位，第 14 位被清零
+  pub fn set_address_high(&mut self, data: u8) {
+    self.0 = (self.0 & 0x00FF) | ((data as u16 & 0x3F) << 8);
+  }
+
+  /// 第二次写入 `$2006`：地址的低 8 位
+  pub fn set_address_low(&mut self, data: u8) {
+    self.0 = (self.0 & 0xFF00) | data as u16;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_increment_x_wraps_into_next_nametable() {
+    let mut v = VramAddress(0x001F);
+    v.increment_x();
+    assert_eq!(v.0, 0x0400);
+    v.increment_x();
+    assert_eq!(v.0, 0x0401);
+  }
+
+  #[test]
+  fn test_increment_y() {
+    let mut v = VramAddress(0x6000 | (29 << 5));
+    v.increment_y();
+    assert_eq!(v.0, 0x7000 | (29 << 5));
+    v.increment_y();
+    assert_eq!(v.0, 0x0800);
+
+    // 从属性表所在的第 31 行越过时不切换命名表
+    let mut v = VramAddress(0x7000 | (31 << 5));
+    v.increment_y();
+    assert_eq!(v.0, 0x0000);
+  }
+
+  #[test]
+  fn test_scroll_writes() {
+    let mut t = VramAddress::default();
+    t.set_nametable(0x03);
+    t.set_scroll_x(0x7D);
+    t.set_scroll_y(0x5E);
+    // fine Y 6，命名表 3，coarse Y 11，coarse X 15
+    assert_eq!(t.0, (6 << 12) | (3 << 10) | (11 << 5) | 15);
+
+    t.set_address_high(0xFF);
+    t.set_address_low(0x12);
+    assert_eq!(t.0, 0x3F12);
+  }
+}
@@ -0,0 +1,33 @@
+use bitflags::bitflags;
+
+bitflags! {
+  /// [PPUMASK](https://www.nesdev.org/wiki/PPU_registers#PPUMASK) `$2001` (bit 7 to bit 0)
+  ///
+  /// | Bit | Flag |    |
+  /// |-----|------|--------------------------------|
+  /// |  7  | B    | 强调蓝色                         |
+  /// |  6  | G    | 强调绿色（PAL 上为红色）          |
+  /// |  5  | R    | 强调红色（PAL 上为绿色）          |
+  /// |  4  | s    | 显示精灵                         |
+  /// |  3  | b    | 显示背景                         |
+  /// |  2  | M    | 在屏幕最左侧 8 个像素显示精灵      |
+  /// |  1  | m    | 在屏幕最左侧 8 个像素显示背景      |
+  /// |  0  | G    | 灰度显示                         |
+  pub struct MaskRegister: u8 {
+    const GREYSCALE = 0b00000001;
+    const SHOW_BACKGROUND_LEFT = 0b00000010;
+    const SHOW_SPRITES_LEFT = 0b00000100;
+    const SHOW_BACKGROUND = 0b00001000;
+    const SHOW_SPRITES = 0b00010000;
+    const EMPHASIZE_RED = 0b00100000;
+    const EMPHASIZE_GREEN = 0b01000000;
+    const EMPHASIZE_BLUE = 0b10000000;
+  }
+}
+
+impl MaskRegister {
+  /// 背景和精灵只要有一个开启，PPU 就处于渲染状态
+  pub fn rendering_enabled(&self) -> bool {
+    return self.intersects(MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_SPRITES);
+  }
+}
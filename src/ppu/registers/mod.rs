@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod loopy;
+pub mod mask;
+pub mod status;
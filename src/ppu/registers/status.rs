@@ -0,0 +1,17 @@
+use bitflags::bitflags;
+
+bitflags! {
+  /// [PPUSTATUS](https://www.nesdev.org/wiki/PPU_registers#PPUSTATUS) `$2002` (bit 7 to bit 0)
+  ///
+  /// | Bit | Flag |    |
+  /// |-----|------|------------------------------------|
+  /// |  7  | V    | vblank 已经开始                      |
+  /// |  6  | S    | 精灵 0 命中                          |
+  /// |  5  | O    | 精灵溢出                             |
+  /// | 4-0 |      | 没有使用，读取时返回 PPU 总线上残留的值 |
+  pub struct StatusRegister: u8 {
+    const SPRITE_OVERFLOW = 0b00100000;
+    const SPRITE_ZERO_HIT = 0b01000000;
+    const VBLANK_STARTED = 0b10000000;
+  }
+}
@@ -0,0 +1,315 @@
+use super::palette::SYSTEM_PALETTE;
+use super::registers::mask::MaskRegister;
+use super::registers::status::StatusRegister;
+use super::PPU;
+
+/// 背景取数流水线。每 8 个时钟取一个图块的命名表、属性表和图案表数据，
+/// 装入 16 位移位寄存器的低 8 位，高 8 位是正在输出的图块
+#[derive(Debug, Default)]
+pub(super) struct BackgroundPipeline {
+  next_tile: u8,
+  next_attribute: u8,
+  next_pattern_lo: u8,
+  next_pattern_hi: u8,
+
+  pattern_lo: u16,
+  pattern_hi: u16,
+  attribute_lo: u16,
+  attribute_hi: u16,
+}
+
+impl BackgroundPipeline {
+  fn shift(&mut self) {
+    self.pattern_lo <<= 1;
+    self.pattern_hi <<= 1;
+    self.attribute_lo <<= 1;
+    self.attribute_hi <<= 1;
+  }
+
+  fn reload(&mut self) {
+    self.pattern_lo = (self.pattern_lo & 0xFF00) | self.next_pattern_lo as u16;
+    self.pattern_hi = (self.pattern_hi & 0xFF00) | self.next_pattern_hi as u16;
+    let fill = |bit: u8| if self.next_attribute & bit != 0 { 0xFF } else { 0x00 };
+    self.attribute_lo = (self.attribute_lo & 0xFF00) | fill(0x01);
+    self.attribute_hi = (self.attribute_hi & 0xFF00) | fill(0x02);
+  }
+
+  /// 按像素列 `fine_x` 取出当前像素的（调色板，颜色值）
+  fn pixel(&self, fine_x: u8) -> (u8, u8) {
+    let bit = 15 - fine_x as u16;
+    let value = (((self.pattern_hi >> bit) & 0x01) << 1) | ((self.pattern_lo >> bit) & 0x01);
+    let palette = (((self.attribute_hi >> bit) & 0x01) << 1) | ((self.attribute_lo >> bit) & 0x01);
+    return (palette as u8, value as u8);
+  }
+}
+
+impl PPU {
+  /// 可见扫描线和预渲染扫描线上开启渲染时的取数和 v 的更新
+  pub(super) fn render_dot(&mut self) {
+    let dot = self.dot;
+    if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+      self.background.shift();
+    }
+    if (1..=257).contains(&dot) || (321..=337).contains(&dot) {
+      match (dot - 1) % 8 {
+        0 => {
+          if dot != 1 && dot != 321 {
+            self.background.reload();
+          }
+          // 第 257 个时钟的命名表读取属于取精灵的阶段，见 `fetch_sprites`
+          if dot != 257 {
+            self.background.next_tile = self.read_vram(self.v.tile_address());
+          }
+        }
+        2 => {
+          let attribute = self.read_vram(self.v.attribute_address());
+          let shift = ((self.v.coarse_y() & 0x02) << 1) | (self.v.coarse_x() & 0x02);
+          self.background.next_attribute = (attribute >> shift) & 0x03;
+        }
+        4 => self.background.next_pattern_lo = self.read_vram(self.background_pattern_row()),
+        6 => self.background.next_pattern_hi = self.read_vram(self.background_pattern_row() + 8),
+        7 => self.v.increment_x(),
+        _ => {}
+      }
+    }
+
+    match dot {
+      256 => self.v.increment_y(),
+      257 => self.v.copy_horizontal(self.t),
+      // 扫描线末尾多余的命名表读取
+      339 => {
+        self.read_vram(self.v.tile_address());
+      }
+      280..=304 if self.scanline == super::PRE_RENDER_SCANLINE => self.v.copy_vertical(self.t),
+      _ => {}
+    }
+  }
+
+  fn background_pattern_row(&self) -> u16 {
+    return self.controller.background_pattern_address() + self.background.next_tile as u16 * 16 + self.v.fine_y();
+  }
+
+  /// 输出第 `line` 条扫描线上第 `x` 个像素，合成背景和精灵并判断精灵 0 命中
+  pub(super) fn output_pixel(&mut self, x: usize, line: usize) {
+    if !self.mask.rendering_enabled() {
+      // 关闭渲染时输出背景色；如果 v 指向调色板，则输出 v 指向的颜色
+      let address = self.v.address();
+      let index = if address >= 0x3F00 { super::palette_index(address) } else { 0 };
+      let color = SYSTEM_PALETTE[(self.palette_table[index] & self.greyscale_mask()) as usize];
+      self.frame.set_pixel(x, line, color);
+      return;
+    }
+
+    let (mut palette, mut value) = (0, 0);
+    if self.mask.contains(MaskRegister::SHOW_BACKGROUND)
+      && (x >= 8 || self.mask.contains(MaskRegister::SHOW_BACKGROUND_LEFT))
+    {
+      (palette, value) = self.background.pixel(self.fine_x);
+    }
+
+    let sprite = self.sprite_line[x].filter(|_| {
+      return self.mask.contains(MaskRegister::SHOW_SPRITES) && (x >= 8 || self.mask.contains(MaskRegister::SHOW_SPRITES_LEFT));
+    });
+    if let Some(sprite) = sprite {
+      // 精灵 0 命中不看优先级，但不会发生在最右边的像素上
+      if sprite.sprite_zero && value != 0 && x != 255 {
+        self.status.insert(StatusRegister::SPRITE_ZERO_HIT);
+      }
+      if value == 0 || !sprite.behind_background {
+        (palette, value) = (sprite.palette, sprite.value);
+      }
+    }
+
+    let color = self.palette_color(palette, value);
+    self.frame.set_pixel(x, line, color);
+  }
+
+  fn greyscale_mask(&self) -> u8 {
+    return if self.mask.contains(MaskRegister::GREYSCALE) { 0x30 } else { 0x3F };
+  }
+
+  /// 查调色板得到 RGB 颜色，`value` 为 0 时使用通用背景色
+  pub(super) fn palette_color(&self, palette: u8, value: u8) -> (u8, u8, u8) {
+    let index = if value == 0 { 0 } else { (palette * 4 + value) as usize };
+    return SYSTEM_PALETTE[(self.palette_table[index] & self.greyscale_mask()) as usize];
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::cartridge::mirroring::Mirroring;
+  use crate::mapper::test::test_mapper;
+  use crate::ppu::registers::status::StatusRegister;
+  use crate::ppu::DOTS_PER_SCANLINE;
+
+  /// 图块 1 的左半边颜色值为 1，右半边为 2；图块 2 全部为 3
+  fn test_ppu() -> PPU {
+    let mut chr = vec![0; 0x2000];
+    for row in 0..8 {
+      chr[16 + row] = 0xF0;
+      chr[16 + 8 + row] = 0x0F;
+      chr[32 + row] = 0xFF;
+      chr[32 + 8 + row] = 0xFF;
+    }
+    let mut ppu = PPU::new(test_mapper(chr, Mirroring::Vertical));
+    for (i, color) in [0x0F, 0x01, 0x02, 0x03, 0x0F, 0x11, 0x12, 0x13].iter().enumerate() {
+      ppu.write_vram(0x3F00 + i as u16, *color);
+    }
+    return ppu;
+  }
+
+  /// 从预渲染扫描线开始绘制完整的一帧，停在 vblank 开始处
+  fn render_frame(ppu: &mut PPU) {
+    let frame = ppu.frame_count();
+    while ppu.frame_count() < frame + 2 {
+      ppu.tick(1);
+    }
+  }
+
+  fn color(index: u8) -> (u8, u8, u8) {
+    return SYSTEM_PALETTE[index as usize];
+  }
+
+  #[test]
+  fn test_background_uses_patterns_and_palette() {
+    let mut ppu = test_ppu();
+    ppu.write_vram(0x2000, 0x01);
+    ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    render_frame(&mut ppu);
+
+    assert_eq!(ppu.frame.get_pixel(0, 0), color(0x01));
+    assert_eq!(ppu.frame.get_pixel(4, 7), color(0x02));
+    assert_eq!(ppu.frame.get_pixel(8, 0), color(0x0F));
+  }
+
+  #[test]
+  fn test_background_uses_attribute_table() {
+    let mut ppu = test_ppu();
+    // 第二个 16x16 区域（右上）选择调色板 1
+    ppu.write_vram(0x2002, 0x02);
+    ppu.write_vram(0x23C0, 0b0000_0100);
+    ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    render_frame(&mut ppu);
+
+    assert_eq!(ppu.frame.get_pixel(16, 0), color(0x13));
+  }
+
+  #[test]
+  fn test_background_scroll() {
+    let mut ppu = test_ppu();
+    // 第二个命名表（垂直镜像下位于右侧）的第一个图块
+    ppu.write_vram(0x2400, 0x02);
+    ppu.write_vram(0x2000 + 32 * 2, 0x01);
+    ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    ppu.write_register(0x2005, 0xFC);
+    ppu.write_register(0x2005, 0x00);
+    render_frame(&mut ppu);
+
+    // X 滚动 252，屏幕上的第 4 列是右侧命名表的第 0 列
+    assert_eq!(ppu.frame.get_pixel(4, 0), color(0x03));
+    assert_eq!(ppu.frame.get_pixel(3, 0), color(0x0F));
+    // Y 滚动 12，第 16 行图块出现在屏幕的第 4 行
+    ppu.write_register(0x2005, 0x00);
+    ppu.write_register(0x2005, 0x0C);
+    render_frame(&mut ppu);
+    assert_eq!(ppu.frame.get_pixel(0, 4), color(0x01));
+    assert_eq!(ppu.frame.get_pixel(0, 3), color(0x0F));
+  }
+
+  #[test]
+  fn test_left_column_clipping_and_background_disable() {
+    let mut ppu = test_ppu();
+    ppu.write_vram(0x2000, 0x02);
+    ppu.write_vram(0x2001, 0x02);
+    ppu.mask = MaskRegister::SHOW_BACKGROUND;
+    render_frame(&mut ppu);
+
+    assert_eq!(ppu.frame.get_pixel(7, 0), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(8, 0), color(0x03));
+
+    ppu.mask = MaskRegister::empty();
+    render_frame(&mut ppu);
+    assert_eq!(ppu.frame.get_pixel(8, 0), color(0x0F));
+  }
+
+  #[test]
+  fn test_vblank_and_nmi() {
+    let mut ppu = test_ppu();
+    ppu.write_register(0x2000, 0x80);
+    ppu.tick(241 * DOTS_PER_SCANLINE as u64 + 1);
+    assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+    assert!(!ppu.poll_nmi());
+
+    ppu.tick(1);
+    assert!(ppu.status.contains(StatusRegister::VBLANK_STARTED));
+    assert_eq!(ppu.frame_count(), 1);
+    assert!(ppu.poll_nmi());
+    assert!(!ppu.poll_nmi());
+
+    // vblank 期间重新打开 NMI 会再次触发
+    ppu.write_register(0x2000, 0x00);
+    ppu.write_register(0x2000, 0x80);
+    assert!(ppu.poll_nmi());
+
+    ppu.tick(20 * DOTS_PER_SCANLINE as u64);
+    assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+  }
+
+  #[test]
+  fn test_status_read_at_vblank_start_suppresses_nmi() {
+    let mut ppu = test_ppu();
+    ppu.write_register(0x2000, 0x80);
+    ppu.tick(241 * DOTS_PER_SCANLINE as u64 + 2);
+    assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+    assert!(!ppu.poll_nmi());
+  }
+
+  #[test]
+  fn test_odd_frames_are_one_dot_shorter_when_rendering() {
+    let mut ppu = test_ppu();
+    ppu.mask = MaskRegister::SHOW_BACKGROUND;
+    let mut lengths = vec![];
+    for _ in 0..4 {
+      let frame = ppu.frame_count();
+      let mut dots = 0;
+      while ppu.frame_count() == frame {
+        ppu.tick(1);
+        dots += 1;
+      }
+      lengths.push(dots);
+    }
+    assert_eq!(lengths[1..], [89342, 89341, 89342]);
+
+    ppu.mask = MaskRegister::empty();
+    ppu.tick(89342);
+    assert_eq!(ppu.frame_count(), 5);
+    assert_eq!((ppu.scanline, ppu.dot), (241, 2));
+  }
+
+  #[test]
+  fn test_mid_frame_address_write_splits_screen() {
+    let mut ppu = test_ppu();
+    for offset in 0..0x3C0 {
+      ppu.write_vram(0x2400 + offset, 0x02);
+    }
+    ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    render_frame(&mut ppu);
+    while (ppu.scanline, ppu.dot) != (100, 300) {
+      ppu.tick(1);
+    }
+    // 在水平消隐期间把 v 指向右侧的命名表
+    ppu.write_register(0x2006, 0x24);
+    ppu.write_register(0x2006, 0x00);
+    ppu.tick(DOTS_PER_SCANLINE as u64 * 100);
+
+    assert_eq!(ppu.frame.get_pixel(10, 100), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(10, 101), color(0x03));
+    assert_eq!(ppu.frame.get_pixel(255, 150), color(0x03));
+
+    // `$2006` 同时改写了 t，下一帧要重新写 PPUCTRL 选择命名表
+    ppu.write_register(0x2000, 0x00);
+    render_frame(&mut ppu);
+    assert_eq!(ppu.frame.get_pixel(10, 101), color(0x0F));
+  }
+}
@@ -0,0 +1,394 @@
+use bitflags::bitflags;
+
+use super::frame::Frame;
+use super::registers::status::StatusRegister;
+use super::PPU;
+
+/// 每条扫描线最多显示的精灵数，也是次级 OAM 的容量
+pub const SPRITES_PER_LINE: usize = 8;
+
+bitflags! {
+  /// [精灵属性](https://www.nesdev.org/wiki/PPU_OAM#Byte_2)，OAM 中每个精灵的第 2 个字节
+  ///
+  /// | Bit | Flag |    |
+  /// |-----|------|--------------------------------|
+  /// |  7  | V    | 垂直翻转                         |
+  /// |  6  | H    | 水平翻转                         |
+  /// |  5  | P    | 优先级（0: 在背景前, 1: 在背景后）  |
+  /// | 1-0 | PP   | 调色板（4-7）                     |
+  pub struct SpriteAttributes: u8 {
+    const PALETTE = 0b00000011;
+    const BEHIND_BACKGROUND = 0b00100000;
+    const FLIP_HORIZONTAL = 0b01000000;
+    const FLIP_VERTICAL = 0b10000000;
+  }
+}
+
+/// OAM 中的一个精灵
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sprite {
+  /// 在 OAM 中的序号，0-63
+  pub index: u8,
+
+  /// 精灵顶端所在扫描线的前一条，精灵从第 `y + 1` 条扫描线开始显示
+  pub y: u8,
+
+  pub tile: u8,
+
+  pub attributes: SpriteAttributes,
+
+  pub x: u8,
+}
+
+impl Sprite {
+  fn from_oam(oam: &[u8; 256], index: usize) -> Self {
+    let base = index * 4;
+    return Sprite {
+      index: index as u8,
+      y: oam[base],
+      tile: oam[base + 1],
+      attributes: SpriteAttributes::from_bits_truncate(oam[base + 2]),
+      x: oam[base + 3],
+    };
+  }
+}
+
+/// 一个像素上最前面的不透明精灵
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpritePixel {
+  /// 颜色值，1-3
+  pub value: u8,
+
+  /// 调色板，4-7
+  pub palette: u8,
+
+  pub behind_background: bool,
+
+  /// 来自精灵 0，用于精灵 0 命中
+  pub sprite_zero: bool,
+}
+
+/// 精灵的 Y 坐标是否覆盖第 `line` 条扫描线
+fn in_range(y: u8, line: u16, height: u8) -> bool {
+  return line >= y as u16 && line - (y as u16) < height as u16;
+}
+
+impl PPU {
+  /// [精灵求值](https://www.nesdev.org/wiki/PPU_sprite_evaluation)，选出下一条扫描线要绘制的精灵。
+  ///
+  /// 前 8 个命中的精灵进入次级 OAM。之后硬件继续查找第 9 个精灵来设置溢出标志，
+  /// 但它在没有命中时会把 OAM 字节偏移 m 和精灵序号 n 一起递增，于是把图块、属性、X 当作 Y 比较，
+  /// 造成溢出标志的误报和漏报，这里照样模拟。
+  ///
+  /// 开启 `remove_sprite_limit` 后所有命中的精灵都会绘制，但溢出标志和精灵 0 的判定不受影响。
+  pub(super) fn evaluate_sprites(&mut self, line: u16) {
+    self.line_sprites.clear();
+    if !self.mask.rendering_enabled() {
+      return;
+    }
+
+    let height = self.controller.sprite_size();
+    let mut n = 0;
+    while n < 64 && self.line_sprites.len() < SPRITES_PER_LINE {
+      if in_range(self.oam_data[n * 4], line, height) {
+        self.line_sprites.push(Sprite::from_oam(&self.oam_data, n));
+      }
+      n += 1;
+    }
+    // 溢出查找会跳过精灵，去掉限制时从第 8 个命中的精灵之后重新按 Y 坐标查找
+    let rest = n;
+
+    let mut m = 0;
+    while n < 64 {
+      if in_range(self.oam_data[n * 4 + m], line, height) {
+        self.status.insert(StatusRegister::SPRITE_OVERFLOW);
+        break;
+      }
+      n += 1;
+      m = (m + 1) & 0x03;
+    }
+
+    if self.remove_sprite_limit {
+      for index in rest..64 {
+        let sprite = Sprite::from_oam(&self.oam_data, index);
+        if in_range(sprite.y, line, height) {
+          self.line_sprites.push(sprite);
+        }
+      }
+    }
+  }
+
+  /// 第 257-320 个时钟取出第 `line` 条扫描线选出的精灵的图案，得到下一条扫描线每个像素上的精灵。
+  ///
+  /// 每个精灵占 8 个时钟：两次无用的命名表读取，然后是图案的低位和高位平面。
+  /// 不足 8 个精灵时空位照样取图块 `$FF` 的图案，mapper 在地址总线上能看到这些访问。
+  pub(super) fn fetch_sprites(&mut self, line: u16) {
+    let step = self.dot - 257;
+    if step == 0 {
+      self.sprite_line = [None; Frame::WIDTH];
+    }
+    if !self.mask.rendering_enabled() {
+      return;
+    }
+
+    let slot = (step / 8) as usize;
+    match step % 8 {
+      0 | 2 => {
+        self.read_vram(self.v.tile_address());
+      }
+      4 => {
+        // 去掉精灵数量限制时，多出来的精灵在最后一个槽里一起取
+        let last = if slot == SPRITES_PER_LINE - 1 { self.line_sprites.len().max(SPRITES_PER_LINE) } else { slot + 1 };
+        for i in slot..last {
+          self.fetch_sprite(line, i);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  /// 取第 `i` 个选出的精灵的图案并画到 `sprite_line` 上。
+  ///
+  /// 每个像素取序号最小的不透明精灵，即使它在背景后面而被背景挡住，也会挡住序号更大的精灵。
+  fn fetch_sprite(&mut self, line: u16, i: usize) {
+    let height = self.controller.sprite_size();
+    let Some(&sprite) = self.line_sprites.get(i) else {
+      let pattern = self.sprite_pattern_address(0xFF, 0, height);
+      self.read_vram(pattern);
+      self.read_vram(pattern + 8);
+      return;
+    };
+
+    let mut row = line - sprite.y as u16;
+    if sprite.attributes.contains(SpriteAttributes::FLIP_VERTICAL) {
+      row = height as u16 - 1 - row;
+    }
+    let pattern = self.sprite_pattern_address(sprite.tile, row, height);
+    let lo = self.read_vram(pattern);
+    let hi = self.read_vram(pattern + 8);
+
+    for column in 0..8 {
+      let x = sprite.x as usize + column;
+      if x >= Frame::WIDTH || self.sprite_line[x].is_some() {
+        continue;
+      }
+      let bit = if sprite.attributes.contains(SpriteAttributes::FLIP_HORIZONTAL) { column } else { 7 - column };
+      let value = (((hi >> bit) & 0x01) << 1) | ((lo >> bit) & 0x01);
+      if value == 0 {
+        continue;
+      }
+      self.sprite_line[x] = Some(SpritePixel {
+        value,
+        palette: 4 + (sprite.attributes & SpriteAttributes::PALETTE).bits(),
+        behind_background: sprite.attributes.contains(SpriteAttributes::BEHIND_BACKGROUND),
+        sprite_zero: sprite.index == 0,
+      });
+    }
+  }
+
+  fn sprite_pattern_address(&self, tile: u8, row: u16, height: u8) -> u16 {
+    if height == 16 {
+      // 8x16 精灵由图块编号的第 0 位选择图案表，上下两半是相邻的两个图块
+      let bank = (tile as u16 & 0x01) * 0x1000;
+      return bank + ((tile & 0xFE) as u16 + row / 8) * 16 + row % 8;
+    }
+    return self.controller.sprite_pattern_address() + tile as u16 * 16 + row;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::cartridge::mirroring::Mirroring;
+  use crate::mapper::test::test_mapper;
+  use crate::ppu::palette::SYSTEM_PALETTE;
+  use crate::ppu::registers::mask::MaskRegister;
+
+  /// 图块 1 的左半边颜色值为 1，图块 2 全部为 3，图块 3 只有第 0 行为 1，
+  /// `$1000` 处的图块 2 全部为 2。精灵调色板 4 的颜色是 `$2X`，调色板 5 是 `$1X`
+  fn test_ppu() -> PPU {
+    let mut chr = vec![0; 0x2000];
+    for row in 0..8 {
+      chr[16 + row] = 0xF0;
+      chr[32 + row] = 0xFF;
+      chr[32 + 8 + row] = 0xFF;
+      chr[0x1000 + 32 + 8 + row] = 0xFF;
+    }
+    chr[48] = 0xFF;
+    let mut ppu = PPU::new(test_mapper(chr, Mirroring::Vertical));
+    for (i, color) in [0x0F, 0x01, 0x02, 0x03].iter().enumerate() {
+      ppu.write_vram(0x3F00 + i as u16, *color);
+    }
+    for i in 1..4 {
+      ppu.write_vram(0x3F10 + i, 0x20 + i as u8);
+      ppu.write_vram(0x3F14 + i, 0x10 + i as u8);
+    }
+    ppu.oam_data = [0xFF; 256];
+    ppu.mask = MaskRegister::SHOW_SPRITES | MaskRegister::SHOW_SPRITES_LEFT;
+    return ppu;
+  }
+
+  fn set_sprite(ppu: &mut PPU, index: usize, y: u8, tile: u8, attributes: u8, x: u8) {
+    ppu.oam_data[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attributes, x]);
+  }
+
+  /// 运行到下一次到达第 `scanline` 条扫描线的第 `dot` 个时钟
+  fn run_to(ppu: &mut PPU, scanline: u16, dot: u16) {
+    ppu.tick(1);
+    while (ppu.scanline, ppu.dot) != (scanline, dot) {
+      ppu.tick(1);
+    }
+  }
+
+  fn color(index: u8) -> (u8, u8, u8) {
+    return SYSTEM_PALETTE[index as usize];
+  }
+
+  #[test]
+  fn test_sprite_is_drawn_one_line_below_y() {
+    let mut ppu = test_ppu();
+    set_sprite(&mut ppu, 0, 9, 0x01, 0x01, 20);
+    run_to(&mut ppu, 20, 0);
+
+    assert_eq!(ppu.frame.get_pixel(20, 9), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(20, 10), color(0x11));
+    assert_eq!(ppu.frame.get_pixel(24, 10), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(23, 17), color(0x11));
+    assert_eq!(ppu.frame.get_pixel(20, 18), color(0x0F));
+  }
+
+  #[test]
+  fn test_sprite_flip() {
+    let mut ppu = test_ppu();
+    set_sprite(&mut ppu, 0, 9, 0x01, 0x40, 20);
+    set_sprite(&mut ppu, 1, 9, 0x03, 0x80, 40);
+    run_to(&mut ppu, 20, 0);
+
+    assert_eq!(ppu.frame.get_pixel(20, 10), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(24, 10), color(0x21));
+    assert_eq!(ppu.frame.get_pixel(40, 10), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(40, 17), color(0x21));
+  }
+
+  #[test]
+  fn test_8x16_sprite_uses_tile_bank_bit() {
+    let mut ppu = test_ppu();
+    ppu.write_register(0x2000, 0x20);
+    // 图块 $03：上半部分是 $1000 处的图块 2，下半部分是图块 3
+    set_sprite(&mut ppu, 0, 9, 0x03, 0x00, 20);
+    run_to(&mut ppu, 30, 0);
+
+    assert_eq!(ppu.frame.get_pixel(20, 10), color(0x22));
+    assert_eq!(ppu.frame.get_pixel(20, 17), color(0x22));
+    assert_eq!(ppu.frame.get_pixel(20, 18), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(20, 25), color(0x0F));
+    assert_eq!(ppu.frame.get_pixel(20, 26), color(0x0F));
+  }
+
+  #[test]
+  fn test_lower_index_sprite_wins_even_behind_background() {
+    let mut ppu = test_ppu();
+    ppu.mask |= MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    // 背景第 1 行图块的左半边不透明
+    ppu.write_vram(0x2020 + 2, 0x01);
+    set_sprite(&mut ppu, 1, 7, 0x02, 0x20, 16);
+    set_sprite(&mut ppu, 2, 7, 0x02, 0x00, 16);
+    run_to(&mut ppu, 20, 0);
+
+    // 精灵 1 在背景后面：背景不透明处显示背景，透明处显示精灵 1，精灵 2 始终被挡住
+    assert_eq!(ppu.frame.get_pixel(16, 8), color(0x01));
+    assert_eq!(ppu.frame.get_pixel(20, 8), color(0x23));
+  }
+
+  #[test]
+  fn test_sprite_limit_and_overflow() {
+    let mut ppu = test_ppu();
+    for i in 0..9 {
+      set_sprite(&mut ppu, i, 9, 0x02, 0x00, i as u8 * 10);
+    }
+    run_to(&mut ppu, 11, 0);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    assert_eq!(ppu.frame.get_pixel(70, 10), color(0x23));
+    assert_eq!(ppu.frame.get_pixel(80, 10), color(0x0F));
+
+    ppu.remove_sprite_limit = true;
+    run_to(&mut ppu, 11, 0);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    assert_eq!(ppu.frame.get_pixel(80, 10), color(0x23));
+  }
+
+  #[test]
+  fn test_overflow_evaluation_bug() {
+    let mut ppu = test_ppu();
+    for i in 0..8 {
+      set_sprite(&mut ppu, i, 9, 0x02, 0x00, 0);
+    }
+    // 只有 8 个精灵命中，但硬件会把精灵 9 的图块编号当作 Y 坐标比较
+    set_sprite(&mut ppu, 9, 0xF0, 9, 0x00, 0);
+    run_to(&mut ppu, 11, 0);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+
+    // 第 9 个精灵确实命中，但硬件比较的是它的图块编号，没有设置溢出标志
+    let mut ppu = test_ppu();
+    for i in 0..8 {
+      set_sprite(&mut ppu, i, 9, 0x02, 0x00, 0);
+    }
+    set_sprite(&mut ppu, 9, 9, 0xF0, 0x00, 0);
+    run_to(&mut ppu, 11, 0);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+  }
+
+  #[test]
+  fn test_remove_sprite_limit_when_overflow_scan_misses() {
+    let mut ppu = test_ppu();
+    ppu.remove_sprite_limit = true;
+    for i in 0..8 {
+      set_sprite(&mut ppu, i, 9, 0x02, 0x00, i as u8 * 10);
+    }
+    // 溢出查找比较的是精灵 9 的图块编号，没有命中，但精灵 9 仍然要绘制
+    set_sprite(&mut ppu, 9, 9, 0x01, 0x00, 90);
+    run_to(&mut ppu, 11, 0);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    assert_eq!(ppu.frame.get_pixel(90, 10), color(0x21));
+  }
+
+  #[test]
+  fn test_sprite_zero_hit_timing() {
+    let mut ppu = test_ppu();
+    ppu.mask |= MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_BACKGROUND_LEFT;
+    ppu.write_vram(0x2020 + 4, 0x02);
+    set_sprite(&mut ppu, 0, 7, 0x01, 0x20, 30);
+
+    run_to(&mut ppu, 8, 33);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    ppu.tick(1);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+    // 读取 PPUSTATUS 不会清除命中标志，只有预渲染扫描线会
+    ppu.read_register(0x2002);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    run_to(&mut ppu, 261, 2);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+  }
+
+  #[test]
+  fn test_no_sprite_zero_hit_at_x_255_or_when_clipped() {
+    let mut ppu = test_ppu();
+    ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_SPRITES;
+    for tile in 0..32 {
+      ppu.write_vram(0x2000 + tile, 0x02);
+    }
+    set_sprite(&mut ppu, 0, 0, 0x03, 0x00, 255);
+    set_sprite(&mut ppu, 1, 0, 0x02, 0x00, 0);
+    run_to(&mut ppu, 2, 0);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+    set_sprite(&mut ppu, 0, 0, 0x03, 0x00, 0);
+    run_to(&mut ppu, 2, 0);
+    assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+    set_sprite(&mut ppu, 0, 0, 0x03, 0x00, 1);
+    run_to(&mut ppu, 2, 0);
+    assert!(ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+  }
+}
@@ -1,13 +1,14 @@
 use crate::cpu::addressing_mode::AddressingMode;
 use crate::cpu::CPU;
-use crate::cpu::opcodes;
-use std::collections::HashMap;
+use crate::memory::Memory;
 
-pub fn trace(cpu: &CPU) -> String {
-  let ref opscodes: HashMap<u8, &'static opcodes::Opcode> = *opcodes::OPCODES_MAP;
+/// 以 nestest.log 的格式输出下一条指令和寄存器状态，内存只通过 `peek` 访问
+pub fn trace<M: Memory>(cpu: &CPU<M>) -> String {
+  let read = |address: u16| cpu.bus.peek(address);
+  let read_u16 = |address: u16| cpu.bus.peek_u16(address);
 
-  let code = cpu.bus.read(cpu.registers.program_counter);
-  let ops = opscodes.get(&code).expect(&format!("CODE: {:X}", code));
+  let code = read(cpu.registers.program_counter);
+  let ops = &CPU::<M>::OPCODES[code as usize];
 
   let begin = cpu.registers.program_counter;
   let mut hex_dump = vec![];
@@ -16,19 +17,19 @@ pub fn trace(cpu: &CPU) -> String {
   let (mem_addr, stored_value) = match ops.mode {
       AddressingMode::Immediate | AddressingMode::Implicit => (0, 0),
       _ => {
-          let addr = cpu.get_absolute_address(&ops.mode, begin + 1);
-          (addr, cpu.bus.read(addr))
+          let (addr, _) = cpu.get_absolute_address(&ops.mode, begin + 1).unwrap_or((0, false));
+          (addr, read(addr))
       }
   };
 
   let tmp = match ops.length {
       1 => match ops.code {
-          0x0a | 0x4a | 0x2a | 0x6a => format!("A "),
+          0x0a | 0x4a | 0x2a | 0x6a => String::from("A "),
           _ => String::from(""),
       },
       2 => {
-          let address: u8 = cpu.bus.read(begin + 1);
-          // let value = cpu.bus.read(address));
+          let address: u8 = read(begin + 1);
+          // let value = read(address));
           hex_dump.push(address);
 
           match ops.mode {
@@ -70,26 +71,26 @@ pub fn trace(cpu: &CPU) -> String {
           }
       }
       3 => {
-          let address_lo = cpu.bus.read(begin + 1);
-          let address_hi = cpu.bus.read(begin + 2);
+          let address_lo = read(begin + 1);
+          let address_hi = read(begin + 2);
           hex_dump.push(address_lo);
           hex_dump.push(address_hi);
 
-          let address = cpu.bus.read_u16(begin + 1);
+          let address = read_u16(begin + 1);
 
           match ops.mode {
               AddressingMode::Implicit | AddressingMode::Indirect => {
                   if ops.code == 0x6c {
                       //jmp indirect
                       let jmp_addr = if address & 0x00FF == 0x00FF {
-                          let lo = cpu.bus.read(address);
-                          let hi = cpu.bus.read(address & 0xFF00);
+                          let lo = read(address);
+                          let hi = read(address & 0xFF00);
                           (hi as u16) << 8 | (lo as u16)
                       } else {
-                          cpu.bus.read_u16(address)
+                          read_u16(address)
                       };
 
-                      // let jmp_addr = cpu.bus.read_u16(address);
+                      // let jmp_addr = read_u16(address);
                       format!("(${:04x}) = {:04x}", address, jmp_addr)
                   } else {
                       format!("${:04x}", address)
@@ -128,9 +129,16 @@ pub fn trace(cpu: &CPU) -> String {
       .trim()
       .to_string();
 
+  // 没有 PPU 时按 CPU 周期的 3 倍推算，与 nestest.log 的约定一样从第 0 条扫描线开始
+  let (scanline, dot) = cpu.bus.ppu_position().unwrap_or_else(|| {
+    let ppu_dots = cpu.cycles * 3;
+    return ((ppu_dots / 341 % 262) as u16, (ppu_dots % 341) as u16);
+  });
+
   format!(
-      "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x}",
+      "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x} PPU:{:3},{:3} CYC:{}",
       asm_str, cpu.registers.a, cpu.registers.x, cpu.registers.y, cpu.registers.status, cpu.registers.stack_pointer,
+      scanline, dot, cpu.cycles,
   )
   .to_ascii_uppercase()
 }
@@ -140,10 +148,32 @@ mod test {
   use super::*;
   use crate::bus::Bus;
   use crate::cartridge::test::test_rom;
+  use crate::memory::flat::FlatMemory;
+
+  /// 记录 `read` 次数的内存，用来确认 trace 只使用 `peek`
+  struct CountingMemory {
+    memory: FlatMemory,
+    reads: usize,
+  }
+
+  impl Memory for CountingMemory {
+    fn peek(&self, address: u16) -> u8 {
+      return self.memory.peek(address);
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
+      self.reads += 1;
+      return self.memory.peek(address);
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+      return self.memory.write(address, data);
+    }
+  }
 
   #[test]
   fn test_format_trace() {
-    let mut bus = Bus::new(test_rom());
+    let mut bus = Bus::new(test_rom()).unwrap();
     bus.write(100, 0xa2);
     bus.write(101, 0x01);
     bus.write(102, 0xca);
@@ -151,7 +181,7 @@ mod test {
     bus.write(104, 0x00);
 
     let mut cpu = CPU::new(bus);
-    cpu.registers.program_counter = 0x64;
+    cpu.registers.reset(0x64);
     cpu.registers.a = 1;
     cpu.registers.x = 2;
     cpu.registers.y = 3;
@@ -160,43 +190,74 @@ mod test {
       result.push(trace(cpu));
     });
     assert_eq!(
-      "0064  A2 01     LDX #$01                        A:01 X:02 Y:03 P:24 SP:FD",
+      "0064  A2 01     LDX #$01                        A:01 X:02 Y:03 P:24 SP:FD PPU:  0,  0 CYC:0",
       result[0]
     );
     assert_eq!(
-      "0066  CA        DEX                             A:01 X:01 Y:03 P:24 SP:FD",
+      "0066  CA        DEX                             A:01 X:01 Y:03 P:24 SP:FD PPU:  0,  6 CYC:2",
       result[1]
     );
     assert_eq!(
-      "0067  88        DEY                             A:01 X:00 Y:03 P:26 SP:FD",
+      "0067  88        DEY                             A:01 X:00 Y:03 P:26 SP:FD PPU:  0, 12 CYC:4",
       result[2]
     );
   }
 
+  #[test]
+  fn test_ppu_column_comes_from_ppu() {
+    let mut cpu = CPU::new(Bus::new(test_rom()).unwrap());
+    cpu.registers.reset(0x64);
+    cpu.bus.ppu.tick(341 + 5);
+    assert!(trace(&cpu).ends_with("PPU:  1,  5 CYC:0"));
+  }
+
   #[test]
   fn test_format_mem_access() {
-    let mut bus = Bus::new(test_rom());
+    let mut bus = Bus::new(test_rom()).unwrap();
     // ORA ($33), Y
     bus.write(100, 0x11);
     bus.write(101, 0x33);
 
     //data
-    bus.write(0x33, 00);
-    bus.write(0x34, 04);
+    bus.write(0x33, 0x00);
+    bus.write(0x34, 0x04);
 
     //target cell
     bus.write(0x400, 0xAA);
 
     let mut cpu = CPU::new(bus);
-    cpu.registers.program_counter = 0x64;
+    cpu.registers.reset(0x64);
     cpu.registers.y = 0;
     let mut result: Vec<String> = vec![];
     cpu.run_with_callback(|cpu| {
       result.push(trace(cpu));
     });
     assert_eq!(
-      "0064  11 33     ORA ($33),Y = 0400 @ 0400 = AA  A:00 X:00 Y:00 P:24 SP:FD",
+      "0064  11 33     ORA ($33),Y = 0400 @ 0400 = AA  A:00 X:00 Y:00 P:24 SP:FD PPU:  0,  0 CYC:0",
       result[0]
     );
   }
+
+  #[test]
+  fn test_trace_has_no_side_effects() {
+    let mut memory = FlatMemory::new();
+    // LDA ($33),Y
+    memory.load(0x0600, &[0xB1, 0x33]);
+    memory.load(0x33, &[0x00, 0x04]);
+    memory.load(0x0400, &[0x55]);
+
+    let mut cpu = CPU::new(CountingMemory { memory, reads: 0 });
+    cpu.registers.reset(0x0600);
+    assert_eq!(
+      "0600  B1 33     LDA ($33),Y = 0400 @ 0400 = 55  A:00 X:00 Y:00 P:24 SP:FD PPU:  0,  0 CYC:0",
+      trace(&cpu)
+    );
+    assert_eq!(cpu.bus.reads, 0);
+
+    cpu.step();
+    assert_eq!(cpu.registers.a, 0x55);
+    assert!(cpu.bus.reads > 0);
+    // 没有 PPU 时按 CPU 周期推算 PPU 的位置
+    assert!(trace(&cpu).ends_with("PPU:  0, 15 CYC:5"));
+  }
 }
@@ -0,0 +1,104 @@
+#![allow(clippy::needless_return)]
+
+use std::path::Path;
+
+use nes_emulator::bus::Bus;
+use nes_emulator::cartridge::Cartridge;
+use nes_emulator::cpu::CPU;
+use nes_emulator::memory::Memory;
+use nes_emulator::trace::trace;
+
+/// trace 输出中寄存器部分的字段，按出现的顺序排列
+const FIELDS: [&str; 7] = ["A:", "X:", "Y:", "P:", "SP:", "PPU:", "CYC:"];
+
+/// 寄存器部分在每一行中的起始列
+const REGISTERS_COLUMN: usize = 48;
+
+fn read_file(name: &str) -> Vec<u8> {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+  return std::fs::read(&path).unwrap_or_else(|error| panic!("failed to read {}: {}", path.display(), error));
+}
+
+/// 把一行拆成指令部分和各个寄存器字段
+fn split_fields(line: &str) -> Vec<(&'static str, &str)> {
+  let column = REGISTERS_COLUMN.min(line.len());
+  let mut fields = vec![("instruction", line[..column].trim_end())];
+
+  let registers = &line[column..];
+  let mut positions = vec![];
+  let mut from = 0;
+  for name in FIELDS {
+    match registers[from..].find(name) {
+      Some(offset) => {
+        positions.push((name, from + offset));
+        from += offset + name.len();
+      }
+      None => break,
+    }
+  }
+  for (i, (name, position)) in positions.iter().enumerate() {
+    let end = positions.get(i + 1).map_or(registers.len(), |(_, next)| *next);
+    fields.push((name.trim_end_matches(':'), registers[position + name.len()..end].trim()));
+  }
+  return fields;
+}
+
+/// 生成第一处不一致的说明，逐项列出不同的字段
+fn describe_mismatch(line_number: usize, expected: &str, actual: &str) -> String {
+  let mut message = format!(
+    "nestest.log line {} does not match\n  expected: {}\n  actual:   {}\n",
+    line_number, expected, actual
+  );
+
+  let expected_fields = split_fields(expected);
+  let actual_fields = split_fields(actual);
+  for (name, expected_value) in &expected_fields {
+    let actual_value = actual_fields.iter().find(|(field, _)| field == name).map(|(_, value)| *value);
+    match actual_value {
+      Some(actual_value) if actual_value == *expected_value => {}
+      Some(actual_value) => {
+        message += &format!("  {}: expected `{}`, got `{}`\n", name, expected_value, actual_value);
+      }
+      None => message += &format!("  {}: expected `{}`, missing from trace\n", name, expected_value),
+    }
+  }
+  return message;
+}
+
+#[test]
+fn test_nestest_matches_log() {
+  let cartridge = Cartridge::new(&read_file("nestest.nes")).unwrap();
+  let log = String::from_utf8(read_file("nestest.log")).unwrap();
+
+  let mut cpu = CPU::new(Bus::new(cartridge).unwrap());
+  cpu.reset();
+  // 没有 PPU 时 nestest 需要从 $C000 开始以自动模式运行
+  cpu.registers.program_counter = 0xC000;
+
+  for (index, expected) in log.lines().enumerate() {
+    let actual = trace(&cpu);
+    if actual != expected {
+      panic!("{}", describe_mismatch(index + 1, expected, &actual));
+    }
+    cpu.step();
+  }
+
+  // nestest 把官方和非官方指令的测试结果分别写在 $02 和 $03，0 表示全部通过
+  assert_eq!(cpu.bus.peek(0x0002), 0x00);
+  assert_eq!(cpu.bus.peek(0x0003), 0x00);
+}
+
+#[test]
+fn test_describe_mismatch_lists_differing_fields() {
+  let expected = "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7";
+  let actual = "C000  4C F5 C5  JMP $C5F5                       A:00 X:01 Y:00 P:24 SP:FD PPU:  0, 24 CYC:8";
+
+  let message = describe_mismatch(1, expected, actual);
+  assert!(message.starts_with("nestest.log line 1 does not match\n"));
+  assert!(message.contains("  X: expected `00`, got `01`\n"));
+  assert!(message.contains("  PPU: expected `0, 21`, got `0, 24`\n"));
+  assert!(message.contains("  CYC: expected `7`, got `8`\n"));
+  assert!(!message.contains("  A: expected"));
+  assert!(!message.contains("  P: expected"));
+  assert!(!message.contains("  instruction: expected"));
+}